@@ -50,6 +50,139 @@ pub fn is_ref(the_type: &syn::Type) -> Result<Option<syn::Type>, String> {
     }
 }
 
+/// Returns the error type if the given type is `Result<T, E>`.
+pub fn result_error_type(the_type: &syn::Type) -> Option<&syn::Type> {
+    result_argument(the_type, 1)
+}
+
+/// Returns the success type if the given type is `Result<T, E>`.
+pub fn result_ok_type(the_type: &syn::Type) -> Option<&syn::Type> {
+    result_argument(the_type, 0)
+}
+
+fn result_argument(the_type: &syn::Type, index: usize) -> Option<&syn::Type> {
+    let path = match the_type {
+        syn::Type::Path(x) if x.qself.is_none() => &x.path,
+        _ => return None,
+    };
+    let last = path.segments.last()?;
+    if last.ident != "Result" {
+        return None;
+    }
+    match &last.arguments {
+        syn::PathArguments::AngleBracketed(x) if x.args.len() == 2 => match &x.args[index] {
+            syn::GenericArgument::Type(t) => Some(t),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the output type if the given type is `BoxFuture<'_, T>`.
+pub fn future_output_type(the_type: &syn::Type) -> Option<&syn::Type> {
+    let path = match the_type {
+        syn::Type::Path(x) if x.qself.is_none() => &x.path,
+        _ => return None,
+    };
+    let last = path.segments.last()?;
+    if last.ident != "BoxFuture" {
+        return None;
+    }
+    match &last.arguments {
+        syn::PathArguments::AngleBracketed(x) if x.args.len() == 2 => match &x.args[1] {
+            syn::GenericArgument::Type(t) => Some(t),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Checks whether the type is `()`.
+pub fn is_unit(the_type: &syn::Type) -> bool {
+    matches!(the_type, syn::Type::Tuple(x) if x.elems.is_empty())
+}
+
+/// Returns the type of the value that the method gives, which is the output of the future for an async method.
+pub fn value_type(output: &syn::ReturnType) -> Option<&syn::Type> {
+    match output {
+        syn::ReturnType::Type(_, t) => Some(future_output_type(t).unwrap_or(t)),
+        syn::ReturnType::Default => None,
+    }
+}
+
+/// Checks whether the return type is `Result<T, RemoteError>`, or a future of it.
+pub fn returns_remote_error(output: &syn::ReturnType) -> bool {
+    let the_type = match value_type(output) {
+        Some(t) => t,
+        None => return false,
+    };
+    match result_error_type(the_type) {
+        Some(syn::Type::Path(x)) => x
+            .path
+            .segments
+            .last()
+            .map(|s| s.ident == "RemoteError")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Describes the type in a method signature, as it is compared for the compatibility of services.
+///
+/// References are removed like in `is_ref()`, and only the last segment of each path is kept,
+/// so that the same type is described the same regardless of how it is written.
+/// Wrappers that are transparent to serde and all the ways of passing a service are unified as well.
+pub fn type_descriptor(the_type: &syn::Type) -> String {
+    match the_type {
+        syn::Type::Reference(x) => match &*x.elem {
+            syn::Type::Path(p) if p.path.is_ident("str") => "String".to_owned(),
+            syn::Type::Slice(s) => format!("Vec<{}>", type_descriptor(&s.elem)),
+            elem => type_descriptor(elem),
+        },
+        syn::Type::Paren(x) => type_descriptor(&x.elem),
+        syn::Type::Group(x) => type_descriptor(&x.elem),
+        syn::Type::Tuple(x) => {
+            let elems: Vec<String> = x.elems.iter().map(type_descriptor).collect();
+            if elems.len() == 1 {
+                format!("({},)", elems[0])
+            } else {
+                format!("({})", elems.join(", "))
+            }
+        }
+        syn::Type::Slice(x) => format!("[{}]", type_descriptor(&x.elem)),
+        syn::Type::Array(x) => {
+            let len = &x.len;
+            format!("[{}; {}]", type_descriptor(&x.elem), quote! {#len})
+        }
+        syn::Type::Path(x) if x.qself.is_none() => {
+            let last = match x.path.segments.last() {
+                Some(last) => last,
+                None => return String::new(),
+            };
+            let name = last.ident.to_string();
+            let args: Vec<String> = match &last.arguments {
+                syn::PathArguments::AngleBracketed(x) => x
+                    .args
+                    .iter()
+                    .filter_map(|arg| match arg {
+                        syn::GenericArgument::Type(t) => Some(type_descriptor(t)),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            match name.as_str() {
+                "ServiceToExport" | "ServiceToImport" | "ServiceRef" => "ServiceRef<_>".to_owned(),
+                "Handoff" => "Handoff<_>".to_owned(),
+                "Box" | "Arc" | "Rc" if args.len() == 1 => args[0].clone(),
+                _ if args.is_empty() => name,
+                _ => format!("{}<{}>", name, args.join(", ")),
+            }
+        }
+        other => quote! {#other}.to_string(),
+    }
+}
+
 #[test]
 fn recognize_ref() {
     let t = syn::parse_str::<syn::Type>("Vec<u32>").unwrap();
@@ -69,3 +202,63 @@ fn recognize_ref() {
     let t = syn::parse_str::<syn::Type>("&mut i32").unwrap();
     assert!(is_ref(&t).is_err())
 }
+
+#[test]
+fn recognize_remote_error() {
+    let t = syn::parse_str::<syn::ReturnType>("-> Result<u32, RemoteError>").unwrap();
+    assert!(returns_remote_error(&t));
+    let t =
+        syn::parse_str::<syn::ReturnType>("-> std::result::Result<(), rto::RemoteError>").unwrap();
+    assert!(returns_remote_error(&t));
+    let t = syn::parse_str::<syn::ReturnType>("-> Result<u32, ()>").unwrap();
+    assert!(!returns_remote_error(&t));
+    let t = syn::parse_str::<syn::ReturnType>("-> RemoteError").unwrap();
+    assert!(!returns_remote_error(&t));
+    let t = syn::parse_str::<syn::ReturnType>("").unwrap();
+    assert!(!returns_remote_error(&t));
+    let t =
+        syn::parse_str::<syn::ReturnType>("-> BoxFuture<'_, Result<u32, RemoteError>>").unwrap();
+    assert!(returns_remote_error(&t));
+
+    let t = syn::parse_str::<syn::Type>("Result<(), RemoteError>").unwrap();
+    assert!(is_unit(result_ok_type(&t).unwrap()));
+    let t = syn::parse_str::<syn::Type>("Result<u32, ()>").unwrap();
+    assert!(!is_unit(result_ok_type(&t).unwrap()));
+    assert!(is_unit(result_error_type(&t).unwrap()));
+}
+
+#[test]
+fn recognize_future() {
+    let t = syn::parse_str::<syn::Type>("BoxFuture<'_, u32>").unwrap();
+    let tu = syn::parse_str::<syn::Type>("u32").unwrap();
+    assert_eq!(future_output_type(&t).unwrap(), &tu);
+    let t = syn::parse_str::<syn::Type>("rto::BoxFuture<'static, ()>").unwrap();
+    let tu = syn::parse_str::<syn::Type>("()").unwrap();
+    assert_eq!(future_output_type(&t).unwrap(), &tu);
+    let t = syn::parse_str::<syn::Type>("Box<u32>").unwrap();
+    assert!(future_output_type(&t).is_none());
+    let t = syn::parse_str::<syn::Type>("BoxFuture").unwrap();
+    assert!(future_output_type(&t).is_none());
+}
+
+#[test]
+fn describe_type() {
+    let describe = |s| type_descriptor(&syn::parse_str::<syn::Type>(s).unwrap());
+    assert_eq!(describe("&&&u32"), "u32");
+    assert_eq!(describe("&str"), "String");
+    assert_eq!(describe("std::string::String"), "String");
+    assert_eq!(describe("&[u8]"), describe("Vec<u8>"));
+    assert_eq!(
+        describe("std::collections::HashMap<String, Vec<&'static str>>"),
+        "HashMap<String, Vec<String>>"
+    );
+    assert_eq!(
+        describe("Result<(), RemoteError>"),
+        "Result<(), RemoteError>"
+    );
+    assert_eq!(describe("(u8,)"), "(u8,)");
+    assert_eq!(describe("[u8; 4]"), "[u8; 4]");
+    assert_eq!(describe("ServiceToExport<dyn Store>"), "ServiceRef<_>");
+    assert_eq!(describe("rto::ServiceRef<dyn Store>"), "ServiceRef<_>");
+    assert_eq!(describe("Box<Pizza>"), "Pizza");
+}
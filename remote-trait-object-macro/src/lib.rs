@@ -23,6 +23,20 @@ fn create_env_path() -> syn::Path {
 /// - `no_proxy` - If provided, the trait will be used only as a service object.
 /// - `no_skeleton` - If provided, the trait will be used only as a proxy object.
 ///
+/// A method can also take attributes
+/// - `#[fallible]` - The method returns `Result<T, E>` where `E: From<RemoteError>`, and its proxy will return
+///   a failure of the remote call as `Err` instead of panicking. This is implied for `Result<T, RemoteError>`.
+/// - `#[pending]` - The trait gets a `<method>_pending` variant that returns `PendingReply<T>`,
+///   whose proxy returns as soon as the request is sent.
+/// - `#[oneway]` - The method returns nothing, and its proxy returns as soon as the request is sent,
+///   without taking a call slot. The other side doesn't send any response.
+///   A fallible one returns `Result<(), E>`, which tells only whether the request has been sent.
+///
+/// A method can be an `async fn`, which is rewritten into a method that returns `BoxFuture<'_, T>`.
+/// Its proxy returns a future that resolves when the response arrives.
+/// The skeleton blocks the thread handling the call on the future of the service object, which doesn't provide any reactor
+/// unless the context has been created in a multi-thread tokio runtime with the `tokio` feature.
+///
 /// There will be many new public `struct`s, but you don't have to know about them.
 #[proc_macro_attribute]
 pub fn service(args: TokenStream, input: TokenStream) -> TokenStream {
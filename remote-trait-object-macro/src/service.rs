@@ -7,6 +7,7 @@ pub mod dispatcher;
 pub mod from_skeleton;
 pub mod id;
 pub mod proxy;
+pub mod signature;
 
 struct SingleArg<T: Parse> {
     pub arg_name: syn::Ident,
@@ -93,11 +94,223 @@ impl Parse for MacroArgsRaw {
     }
 }
 
+/// Method-level arguments, given as attributes on each method of the trait.
+#[derive(Default)]
+struct MethodArgs {
+    pub fallible: bool,
+    /// The method returns `BoxFuture<'_, T>`, which includes an `async fn` after desugaring.
+    pub asynchronous: bool,
+    /// The trait gets a `<method>_pending` variant, which returns a `PendingReply`.
+    pub pending: bool,
+    /// The proxy sends the request without waiting for any response.
+    pub oneway: bool,
+}
+
+const METHOD_ATTRIBUTES: &[&str] = &["fallible", "pending", "oneway"];
+
+impl MethodArgs {
+    fn parse(method: &syn::TraitItemMethod) -> Result<Self, TokenStream2> {
+        let mut result = MethodArgs::default();
+        for attr in &method.attrs {
+            if attr.path.is_ident("fallible") {
+                if !attr.tokens.is_empty() {
+                    return Err(
+                        syn::Error::new_spanned(attr, "`fallible` takes no argument")
+                            .to_compile_error(),
+                    );
+                }
+                if result.fallible {
+                    return Err(
+                        syn::Error::new_spanned(attr, "Duplicated arguments").to_compile_error()
+                    );
+                }
+                let returns_result = crate::helper::value_type(&method.sig.output)
+                    .map(|t| crate::helper::result_error_type(t).is_some())
+                    .unwrap_or(false);
+                if !returns_result {
+                    return Err(syn::Error::new_spanned(
+                        &method.sig,
+                        "A fallible method must return `Result`",
+                    )
+                    .to_compile_error());
+                }
+                result.fallible = true;
+            } else if attr.path.is_ident("pending") {
+                if !attr.tokens.is_empty() {
+                    return Err(syn::Error::new_spanned(attr, "`pending` takes no argument")
+                        .to_compile_error());
+                }
+                if result.pending {
+                    return Err(
+                        syn::Error::new_spanned(attr, "Duplicated arguments").to_compile_error()
+                    );
+                }
+                result.pending = true;
+            } else if attr.path.is_ident("oneway") {
+                if !attr.tokens.is_empty() {
+                    return Err(syn::Error::new_spanned(attr, "`oneway` takes no argument")
+                        .to_compile_error());
+                }
+                if result.oneway {
+                    return Err(
+                        syn::Error::new_spanned(attr, "Duplicated arguments").to_compile_error()
+                    );
+                }
+                result.oneway = true;
+            }
+        }
+        result.fallible |= crate::helper::returns_remote_error(&method.sig.output);
+        result.asynchronous = match &method.sig.output {
+            syn::ReturnType::Type(_, t) => crate::helper::future_output_type(t).is_some(),
+            syn::ReturnType::Default => false,
+        };
+        if result.oneway {
+            // A fallible one can still fail to send the request.
+            let returns_unit = match &method.sig.output {
+                syn::ReturnType::Default => true,
+                syn::ReturnType::Type(_, t) => {
+                    crate::helper::is_unit(t)
+                        || (result.fallible
+                            && crate::helper::result_ok_type(t)
+                                .map(crate::helper::is_unit)
+                                .unwrap_or(false))
+                }
+            };
+            if !returns_unit {
+                return Err(syn::Error::new_spanned(
+                    &method.sig,
+                    "A oneway method must return `()`, or `Result<(), E>` if it is fallible, since no response comes back",
+                )
+                .to_compile_error());
+            }
+        }
+        if result.pending && result.oneway {
+            return Err(syn::Error::new_spanned(
+                &method.sig,
+                "A oneway method can't be `pending`, since no response comes back",
+            )
+            .to_compile_error());
+        }
+        if result.pending && result.asynchronous {
+            return Err(syn::Error::new_spanned(
+                &method.sig,
+                "An async method doesn't need `pending`, since its future is already non-blocking",
+            )
+            .to_compile_error());
+        }
+        Ok(result)
+    }
+}
+
+/// Removes the method-level arguments from the trait, since they are not real attributes.
+fn strip_method_attributes(the_trait: &mut syn::ItemTrait) {
+    for item in the_trait.items.iter_mut() {
+        if let syn::TraitItem::Method(method) = item {
+            method
+                .attrs
+                .retain(|attr| !METHOD_ATTRIBUTES.iter().any(|x| attr.path.is_ident(x)));
+        }
+    }
+}
+
+/// The name of the `PendingReply` variant of a `#[pending]` method.
+pub(crate) fn pending_method_ident(method: &syn::TraitItemMethod) -> syn::Ident {
+    quote::format_ident!("{}_pending", method.sig.ident)
+}
+
+/// The signature of the `PendingReply` variant of a `#[pending]` method.
+pub(crate) fn pending_method_signature(method: &syn::TraitItemMethod) -> syn::Signature {
+    let env_path = crate::create_env_path();
+    let output = match &method.sig.output {
+        syn::ReturnType::Type(_, t) => quote! {#t},
+        syn::ReturnType::Default => quote! {()},
+    };
+    let mut sig = method.sig.clone();
+    sig.ident = pending_method_ident(method);
+    sig.output = syn::parse2(quote! {
+        -> #env_path::PendingReply<#output>
+    })
+    .unwrap();
+    sig
+}
+
+/// Adds the `PendingReply` variant of each `#[pending]` method to the trait.
+///
+/// Its default implementation just calls the original method, which is what a local service object needs.
+/// The proxy overrides it to return as soon as the request is sent.
+fn add_pending_methods(the_trait: &mut syn::ItemTrait) -> Result<(), TokenStream2> {
+    let env_path = crate::create_env_path();
+    let mut pending_methods = Vec::new();
+    for item in the_trait.items.iter() {
+        if let syn::TraitItem::Method(method) = item {
+            if !MethodArgs::parse(method)?.pending {
+                continue;
+            }
+            let sig = pending_method_signature(method);
+            let ident = &method.sig.ident;
+            let mut arguments = Vec::new();
+            for arg in &method.sig.inputs {
+                if let syn::FnArg::Typed(pattern) = arg {
+                    if let syn::Pat::Ident(the_arg) = &*pattern.pat {
+                        arguments.push(the_arg.ident.clone());
+                    } else {
+                        return Err(syn::Error::new_spanned(
+                            arg,
+                            "You must not use a pattern for the argument",
+                        )
+                        .to_compile_error());
+                    }
+                }
+            }
+            pending_methods.push(syn::TraitItem::Method(
+                syn::parse2(quote! {
+                    #sig {
+                        #env_path::PendingReply::ready(Ok(self.#ident(#(#arguments),*)))
+                    }
+                })
+                .unwrap(),
+            ));
+        }
+    }
+    the_trait.items.extend(pending_methods);
+    Ok(())
+}
+
+/// Rewrites `async fn method(..) -> T` into `fn method(..) -> BoxFuture<'_, T>`,
+/// since a trait object can't have an `async fn`.
+fn desugar_async_methods(the_trait: &mut syn::ItemTrait) -> Result<(), TokenStream2> {
+    let env_path = crate::create_env_path();
+    for item in the_trait.items.iter_mut() {
+        if let syn::TraitItem::Method(method) = item {
+            if method.sig.asyncness.is_none() {
+                continue;
+            }
+            if method.default.is_some() {
+                return Err(syn::Error::new_spanned(
+                    &method.sig,
+                    "An async method can't have a default implementation",
+                )
+                .to_compile_error());
+            }
+            let output = match &method.sig.output {
+                syn::ReturnType::Type(_, t) => quote! {#t},
+                syn::ReturnType::Default => quote! {()},
+            };
+            method.sig.asyncness = None;
+            method.sig.output = syn::parse2(quote! {
+                -> #env_path::BoxFuture<'_, #output>
+            })
+            .unwrap();
+        }
+    }
+    Ok(())
+}
+
 pub fn service(args: TokenStream2, input: TokenStream2) -> Result<TokenStream2, TokenStream2> {
     let args: MacroArgsRaw = syn::parse2(args).map_err(|e| e.to_compile_error())?;
     let args = args.fill_default_values();
 
-    let source_trait = match syn::parse2::<syn::ItemTrait>(input.clone()) {
+    let mut source_trait = match syn::parse2::<syn::ItemTrait>(input.clone()) {
         Ok(x) => x,
         Err(_) => {
             return Err(
@@ -107,14 +320,27 @@ pub fn service(args: TokenStream2, input: TokenStream2) -> Result<TokenStream2,
         }
     };
 
+    desugar_async_methods(&mut source_trait)?;
+
+    for item in source_trait.items.iter() {
+        if let syn::TraitItem::Method(method) = item {
+            MethodArgs::parse(method)?;
+        }
+    }
+
     let id = id::generate_id(&source_trait, &args)?;
+    let signature = signature::generate_signature(&source_trait, &args)?;
     let dispatcher = dispatcher::generate_dispatcher(&source_trait, &args)?;
     let proxy = proxy::generate_proxy(&source_trait, &args)?;
     let from_skeleton = from_skeleton::generate_from_skeleton(&source_trait, &args)?;
 
+    add_pending_methods(&mut source_trait)?;
+    strip_method_attributes(&mut source_trait);
+
     Ok(quote! {
         #source_trait
         #id
+        #signature
         #dispatcher
         #proxy
         #from_skeleton
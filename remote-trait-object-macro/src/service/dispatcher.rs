@@ -16,6 +16,20 @@ pub(super) fn generate_dispatcher(
     let arc_dispatcher_ident = quote::format_ident!("{}ArcDispatcher", trait_ident);
     let rwlock_dispatcher_ident = quote::format_ident!("{}RwLockDispatcher", trait_ident);
     let serde_format = &args.serde_format;
+    let lit_trait_name = syn::LitStr::new(&format!("{}", trait_ident), Span::call_site());
+    let method_name_fn_ident = super::id::method_name_fn_ident(source_trait);
+    let signature_fn_ident = super::signature::signature_fn_ident(source_trait);
+    let trait_name_fn = quote! {
+        fn trait_name(&self) -> &'static str {
+            #lit_trait_name
+        }
+        fn method_name(&self, method: #env_path::MethodId) -> &'static str {
+            #method_name_fn_ident(method)
+        }
+        fn signature(&self) -> Option<#env_path::ServiceSignature> {
+            Some(#signature_fn_ident())
+        }
+    };
 
     // TODO: If # of methods is larger than certain limit,
     // then introduce a closure list for the method dispatch,
@@ -124,25 +138,44 @@ pub(super) fn generate_dispatcher(
 
         let stmt_deserialize = quote! {
             // TODO: Make the macro be able to take deserialization scheme
-            let #the_let_pattern: #type_annotation =  <#serde_format as #env_path::SerdeFormat>::from_slice(args).unwrap();
+            let #the_let_pattern: #type_annotation =  <#serde_format as #env_path::SerdeFormat>::from_slice(args).map_err(|_| #env_path::RemoteError::InvalidData)?;
         };
 
         let method_name = method.sig.ident.clone();
-        let stmt_call = quote! {
-            let result = self.object.#method_name(#the_args);
+        let call = quote! {
+            self.object.#method_name(#the_args)
         };
-        let stmt_call_rwlock = if mut_self {
+        let call_rwlock = if mut_self {
             quote! {
-                let result = self.object.write().#method_name(#the_args);
+                self.object.write().#method_name(#the_args)
             }
         } else {
             quote! {
-                let result = self.object.read().#method_name(#the_args);
+                self.object.read().#method_name(#the_args)
             }
         };
+        let (stmt_call, stmt_call_rwlock) = if super::MethodArgs::parse(method)?.asynchronous {
+            (
+                quote! {
+                    let result = #env_path::block_on(#call);
+                },
+                quote! {
+                    let result = #env_path::block_on(#call_rwlock);
+                },
+            )
+        } else {
+            (
+                quote! {
+                    let result = #call;
+                },
+                quote! {
+                    let result = #call_rwlock;
+                },
+            )
+        };
 
         let the_return = quote! {
-            return <#serde_format as #env_path::SerdeFormat>::to_vec(&result).unwrap();
+            return <#serde_format as #env_path::SerdeFormat>::to_vec(&result).map_err(|_| #env_path::RemoteError::InvalidData);
         };
 
         if_else_clauses.extend(quote! {
@@ -162,10 +195,16 @@ pub(super) fn generate_dispatcher(
         });
     }
     if_else_clauses.extend(quote! {
-        panic!("Invalid remote-trait-object call. Fatal Error.")
+        Err(#env_path::RemoteError::UnknownMethod {
+            trait_name: #lit_trait_name.to_owned(),
+            method,
+        })
     });
     if_else_clauses_rwlock.extend(quote! {
-        panic!("Invalid remote-trait-object call. Fatal Error.")
+        Err(#env_path::RemoteError::UnknownMethod {
+            trait_name: #lit_trait_name.to_owned(),
+            method,
+        })
     });
 
     let box_dispatcher = if is_this_trait_mutable {
@@ -184,9 +223,10 @@ pub(super) fn generate_dispatcher(
                 }
             }
             impl #env_path::Dispatch for #box_dispatcher_ident {
-                fn dispatch_and_call(&self, method: #env_path::MethodId, args: &[u8]) -> Vec<u8> {
+                fn dispatch_and_call(&self, method: #env_path::MethodId, args: &[u8]) -> Result<Vec<u8>, #env_path::RemoteError> {
                     #if_else_clauses_rwlock
                 }
+                #trait_name_fn
             }
             impl #env_path::IntoSkeleton<dyn #trait_ident> for Box<dyn #trait_ident> {
                 fn into_skeleton(self) -> #env_path::Skeleton {
@@ -210,9 +250,10 @@ pub(super) fn generate_dispatcher(
                 }
             }
             impl #env_path::Dispatch for #box_dispatcher_ident {
-                fn dispatch_and_call(&self, method: #env_path::MethodId, args: &[u8]) -> Vec<u8> {
+                fn dispatch_and_call(&self, method: #env_path::MethodId, args: &[u8]) -> Result<Vec<u8>, #env_path::RemoteError> {
                     #if_else_clauses
                 }
+                #trait_name_fn
             }
             impl #env_path::IntoSkeleton<dyn #trait_ident> for Box<dyn #trait_ident> {
                 fn into_skeleton(self) -> #env_path::Skeleton {
@@ -240,9 +281,10 @@ pub(super) fn generate_dispatcher(
                 }
             }
             impl #env_path::Dispatch for #arc_dispatcher_ident {
-                fn dispatch_and_call(&self, method: #env_path::MethodId, args: &[u8]) -> Vec<u8> {
+                fn dispatch_and_call(&self, method: #env_path::MethodId, args: &[u8]) -> Result<Vec<u8>, #env_path::RemoteError> {
                     #if_else_clauses
                 }
+                #trait_name_fn
             }
             impl #env_path::IntoSkeleton<dyn #trait_ident> for std::sync::Arc<dyn #trait_ident> {
                 fn into_skeleton(self) -> #env_path::Skeleton {
@@ -267,9 +309,10 @@ pub(super) fn generate_dispatcher(
             }
         }
         impl #env_path::Dispatch for #rwlock_dispatcher_ident {
-            fn dispatch_and_call(&self, method: #env_path::MethodId, args: &[u8]) -> Vec<u8> {
+            fn dispatch_and_call(&self, method: #env_path::MethodId, args: &[u8]) -> Result<Vec<u8>, #env_path::RemoteError> {
                 #if_else_clauses_rwlock
             }
+            #trait_name_fn
         }
         impl #env_path::IntoSkeleton<dyn #trait_ident> for std::sync::Arc<parking_lot::RwLock<dyn #trait_ident>> {
             fn into_skeleton(self) -> #env_path::Skeleton {
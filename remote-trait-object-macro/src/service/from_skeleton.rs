@@ -75,10 +75,20 @@ pub(super) fn generate_from_skeleton(
             }
         }
 
+        // The skeleton has already driven the future to completion, so it returns a ready one.
+        let the_return = if super::MethodArgs::parse(method)?.asynchronous {
+            quote! {
+                Box::pin(std::future::ready(<#serde_format as #env_path::SerdeFormat>::from_slice(&result).unwrap()))
+            }
+        } else {
+            quote! {
+                <#serde_format as #env_path::SerdeFormat>::from_slice(&result).unwrap()
+            }
+        };
         let the_call = quote! {
             let args = <#serde_format as #env_path::SerdeFormat>::to_vec(&#arguments_in_tuple).unwrap();
-            let result = #env_path::get_dispatch(&self.skeleton).dispatch_and_call(#id_ident.load(#env_path::ID_ORDERING), &args);
-            <#serde_format as #env_path::SerdeFormat>::from_slice(&result).unwrap()
+            let result = #env_path::get_dispatch(&self.skeleton).dispatch_and_call(#id_ident.load(#env_path::ID_ORDERING), &args).unwrap();
+            #the_return
         };
         the_method
             .block
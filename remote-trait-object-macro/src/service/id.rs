@@ -15,6 +15,10 @@ fn lit_index(index: usize) -> syn::Lit {
     ))
 }
 
+pub fn method_name_fn_ident(the_trait: &syn::ItemTrait) -> Ident {
+    quote::format_ident!("method_name_{}", the_trait.ident)
+}
+
 fn id_method_entry_ident(the_trait: &syn::ItemTrait, method: &syn::TraitItemMethod) -> Ident {
     quote::format_ident!("ID_METHOD_ENTRY_{}_{}", the_trait.ident, method.sig.ident)
 }
@@ -30,6 +34,7 @@ pub(super) fn generate_id(
     let env_path = create_env_path();
     let lit_trait_name = syn::LitStr::new(&format!("{}", source_trait.ident), Span::call_site());
     let mut method_id_table = TokenStream2::new();
+    let mut method_name_clauses = TokenStream2::new();
 
     for (i, item) in source_trait.items.iter().enumerate() {
         let method = match item {
@@ -45,9 +50,9 @@ pub(super) fn generate_id(
         let lit_index = lit_index(i);
         let lit_method_name = syn::LitStr::new(&format!("{}", method.sig.ident), Span::call_site());
 
-        let id_ident = id_method_ident(&source_trait, method);
-        let id_entry_ident = id_method_entry_ident(&source_trait, method);
-        let id_setter_ident = id_method_setter_ident(&source_trait, method);
+        let id_ident = id_method_ident(source_trait, method);
+        let id_entry_ident = id_method_entry_ident(source_trait, method);
+        let id_setter_ident = id_method_setter_ident(source_trait, method);
         let id_entry = quote! {
             #[allow(non_upper_case_globals)]
             static #id_ident: #env_path::MethodIdAtomic = #env_path::MethodIdAtomic::new(#lit_index);
@@ -61,6 +66,20 @@ pub(super) fn generate_id(
             }
         };
         method_id_table.extend(id_entry);
+        method_name_clauses.extend(quote! {
+            if method == #id_ident.load(#env_path::ID_ORDERING) {
+                return #lit_method_name;
+            }
+        });
     }
+
+    let method_name_fn_ident = method_name_fn_ident(source_trait);
+    method_id_table.extend(quote! {
+        #[allow(non_snake_case, dead_code)]
+        fn #method_name_fn_ident(method: #env_path::MethodId) -> &'static str {
+            #method_name_clauses
+            ""
+        }
+    });
     Ok(method_id_table)
 }
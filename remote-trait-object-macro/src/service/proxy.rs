@@ -16,6 +16,8 @@ pub(super) fn generate_proxy(
 
     let trait_ident = source_trait.ident.clone();
     let struct_ident = quote::format_ident!("{}Proxy", trait_ident);
+    let lit_trait_name = syn::LitStr::new(&format!("{}", trait_ident), Span::call_site());
+    let method_name_fn_ident = super::id::method_name_fn_ident(source_trait);
     let mut imported_struct = quote! {
         #[derive(Debug)]
         #[doc(hidden)]
@@ -75,8 +77,26 @@ pub(super) fn generate_proxy(
             }
         }
 
-        let the_call = quote! {
-            self.handle.call::<#serde_format, _, _>(#id_ident.load(#env_path::ID_ORDERING), &#arguments_in_tuple)
+        let method_args = super::MethodArgs::parse(method)?;
+        let the_call = match (method_args.fallible, method_args.asynchronous) {
+            (true, _) if method_args.oneway => quote! {
+                self.handle.try_call_oneway::<#serde_format, _, _>(#id_ident.load(#env_path::ID_ORDERING), &#arguments_in_tuple)
+            },
+            (false, _) if method_args.oneway => quote! {
+                self.handle.call_oneway::<#serde_format, _>(#id_ident.load(#env_path::ID_ORDERING), &#arguments_in_tuple)
+            },
+            (true, false) => quote! {
+                self.handle.try_call::<#serde_format, _, _, _>(#id_ident.load(#env_path::ID_ORDERING), &#arguments_in_tuple)
+            },
+            (false, false) => quote! {
+                self.handle.call::<#serde_format, _, _>(#id_ident.load(#env_path::ID_ORDERING), &#arguments_in_tuple)
+            },
+            (true, true) => quote! {
+                Box::pin(self.handle.try_call_async::<#serde_format, _, _, _>(#id_ident.load(#env_path::ID_ORDERING), &#arguments_in_tuple))
+            },
+            (false, true) => quote! {
+                Box::pin(self.handle.call_async::<#serde_format, _, _>(#id_ident.load(#env_path::ID_ORDERING), &#arguments_in_tuple))
+            },
         };
         the_method
             .block
@@ -85,6 +105,18 @@ pub(super) fn generate_proxy(
         imported_struct_impl
             .items
             .push(syn::ImplItem::Method(the_method));
+
+        if method_args.pending {
+            let sig = super::pending_method_signature(method);
+            imported_struct_impl.items.push(syn::ImplItem::Method(
+                syn::parse2(quote! {
+                    #sig {
+                        self.handle.call_pending::<#serde_format, _, _>(#id_ident.load(#env_path::ID_ORDERING), &#arguments_in_tuple)
+                    }
+                })
+                .unwrap(),
+            ));
+        }
     }
     imported_struct.extend(imported_struct_impl.to_token_stream());
     imported_struct.extend(quote! {
@@ -93,21 +125,21 @@ pub(super) fn generate_proxy(
         impl #env_path::ImportProxy<dyn #trait_ident> for Box<dyn #trait_ident> {
             fn import_proxy(port: std::sync::Weak<dyn #env_path::Port>, handle: #env_path::HandleToExchange) -> Self {
                 Box::new(#struct_ident {
-                    handle: #env_path::Handle::new(handle, port),
+                    handle: #env_path::Handle::new(handle, port).with_names(#lit_trait_name, #method_name_fn_ident),
                 })
             }
         }
         impl #env_path::ImportProxy<dyn #trait_ident> for std::sync::Arc<dyn #trait_ident> {
             fn import_proxy(port: std::sync::Weak<dyn #env_path::Port>, handle: #env_path::HandleToExchange) -> Self {
                 std::sync::Arc::new(#struct_ident {
-                    handle: #env_path::Handle::new(handle, port),
+                    handle: #env_path::Handle::new(handle, port).with_names(#lit_trait_name, #method_name_fn_ident),
                 })
             }
         }
         impl #env_path::ImportProxy<dyn #trait_ident> for std::sync::Arc<parking_lot::RwLock<dyn #trait_ident>> {
             fn import_proxy(port: std::sync::Weak<dyn #env_path::Port>, handle: #env_path::HandleToExchange) -> Self {
                 std::sync::Arc::new(parking_lot::RwLock::new(#struct_ident {
-                    handle: #env_path::Handle::new(handle, port),
+                    handle: #env_path::Handle::new(handle, port).with_names(#lit_trait_name, #method_name_fn_ident),
                 }))
             }
         }
@@ -0,0 +1,66 @@
+use super::MacroArgs;
+use crate::create_env_path;
+use crate::helper::{type_descriptor, value_type};
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use syn::Ident;
+
+pub fn signature_fn_ident(the_trait: &syn::ItemTrait) -> Ident {
+    quote::format_ident!("signature_{}", the_trait.ident)
+}
+
+pub(super) fn generate_signature(
+    source_trait: &syn::ItemTrait,
+    _args: &MacroArgs,
+) -> Result<TokenStream2, TokenStream2> {
+    let env_path = create_env_path();
+    let trait_ident = &source_trait.ident;
+    let lit_trait_name = syn::LitStr::new(&format!("{}", trait_ident), Span::call_site());
+    let mut methods = Vec::new();
+
+    for item in source_trait.items.iter() {
+        let method = match item {
+            syn::TraitItem::Method(x) => x,
+            non_method => {
+                return Err(syn::Error::new_spanned(
+                    non_method,
+                    "Service trait must have only methods",
+                )
+                .to_compile_error())
+            }
+        };
+        let id_ident = super::id::id_method_ident(source_trait, method);
+        let lit_method_name = syn::LitStr::new(&format!("{}", method.sig.ident), Span::call_site());
+        let params = method.sig.inputs.iter().filter_map(|arg| match arg {
+            syn::FnArg::Receiver(_) => None,
+            syn::FnArg::Typed(pattern) => Some(syn::LitStr::new(
+                &type_descriptor(&pattern.ty),
+                Span::call_site(),
+            )),
+        });
+        let output = value_type(&method.sig.output)
+            .map(type_descriptor)
+            .unwrap_or_else(|| "()".to_owned());
+        let lit_output = syn::LitStr::new(&output, Span::call_site());
+        methods.push(quote! {
+            #env_path::MethodSignature::new(
+                #id_ident.load(#env_path::ID_ORDERING),
+                #lit_method_name,
+                &[#(#params),*],
+                #lit_output,
+            )
+        });
+    }
+
+    let signature_fn_ident = signature_fn_ident(source_trait);
+    Ok(quote! {
+        #[allow(non_snake_case)]
+        fn #signature_fn_ident() -> #env_path::ServiceSignature {
+            #env_path::ServiceSignature::new(#lit_trait_name, vec![#(#methods),*])
+        }
+        impl #env_path::DescribeService for dyn #trait_ident {
+            fn signature() -> #env_path::ServiceSignature {
+                #signature_fn_ident()
+            }
+        }
+    })
+}
@@ -0,0 +1,62 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use remote_trait_object::transport::intra;
+use remote_trait_object::transport::{TransportRecv, TransportSend};
+use std::thread;
+
+const SIZES: &[usize] = &[64, 64 * 1024, 1024 * 1024];
+
+/// Spawns a thread that sends back every packet, until the connection is closed.
+fn echo(
+    send: impl TransportSend + 'static,
+    recv: impl TransportRecv + 'static,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        while let Ok(data) = recv.recv(None) {
+            if send.send(&data, None).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+fn round_trip(send: &impl TransportSend, recv: &impl TransportRecv, data: &[u8]) {
+    send.send(data, None).unwrap();
+    assert_eq!(recv.recv(None).unwrap().len(), data.len());
+}
+
+pub fn round_trips(c: &mut Criterion) {
+    let mut group = c.benchmark_group("round_trip");
+    for &size in SIZES {
+        let data = vec![0u8; size];
+        group.throughput(Throughput::Bytes(size as u64));
+
+        group.bench_with_input(BenchmarkId::new("intra", size), &data, |b, data| {
+            let intra::TransportEnds {
+                send1,
+                recv1,
+                send2,
+                recv2,
+            } = intra::create();
+            let echo = echo(send2, recv2);
+            b.iter(|| round_trip(&send1, &recv1, data));
+            drop(send1);
+            drop(recv1);
+            echo.join().unwrap();
+        });
+
+        #[cfg(target_os = "linux")]
+        group.bench_with_input(BenchmarkId::new("shm", size), &data, |b, data| {
+            use remote_trait_object::transport::shm;
+            let ((send1, recv1), (send2, recv2)) = shm::pair(shm::DEFAULT_CAPACITY).unwrap();
+            let echo = echo(send2, recv2);
+            b.iter(|| round_trip(&send1, &recv1, data));
+            drop(send1);
+            drop(recv1);
+            echo.join().unwrap();
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, round_trips);
+criterion_main!(benches);
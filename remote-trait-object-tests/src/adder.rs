@@ -0,0 +1,44 @@
+//! A service shared by the tests of the transports, which checks that contexts work over a transport.
+
+use remote_trait_object::transport::{TransportRecv, TransportSend};
+use remote_trait_object::*;
+
+#[service]
+pub trait Adder: Service {
+    fn add(&self, a: u64, b: u64) -> u64;
+}
+
+struct SimpleAdder;
+
+impl Service for SimpleAdder {}
+
+impl Adder for SimpleAdder {
+    fn add(&self, a: u64, b: u64) -> u64 {
+        a + b
+    }
+}
+
+pub fn create_adder() -> ServiceToExport<dyn Adder> {
+    ServiceToExport::new(Box::new(SimpleAdder) as Box<dyn Adder>)
+}
+
+/// Connects two contexts over the two ends of a transport, and makes calls through them.
+pub fn check_context<S1, R1, S2, R2>(send1: S1, recv1: R1, send2: S2, recv2: R2)
+where
+    S1: TransportSend + 'static,
+    R1: TransportRecv + 'static,
+    S2: TransportSend + 'static,
+    R2: TransportRecv + 'static,
+{
+    let ctx1 =
+        Context::with_initial_service_export(Config::default_setup(), send1, recv1, create_adder());
+    let (ctx2, adder): (_, ServiceToImport<dyn Adder>) =
+        Context::with_initial_service_import(Config::default_setup(), send2, recv2);
+    let adder: Box<dyn Adder> = adder.into_proxy();
+    for i in 0..100 {
+        assert_eq!(adder.add(i, 1), i + 1);
+    }
+    drop(adder);
+    drop(ctx2);
+    drop(ctx1);
+}
@@ -0,0 +1,207 @@
+use remote_trait_object::transport::asynchronous;
+use remote_trait_object::transport::{framed, tcp};
+use remote_trait_object::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[service]
+pub trait Store: Service {
+    async fn put(&self, amount: u64) -> u64;
+    async fn sleep(&self, ms: u64) -> Result<u64, RemoteError>;
+    fn total(&self) -> u64;
+    fn open_account(&self) -> ServiceRef<dyn Account>;
+}
+
+#[service]
+pub trait Account: Service {
+    fn deposit(&self, amount: u64) -> u64;
+}
+
+struct SimpleStore {
+    total: AtomicU64,
+}
+
+impl Service for SimpleStore {}
+
+impl Store for SimpleStore {
+    fn put(&self, amount: u64) -> BoxFuture<'_, u64> {
+        Box::pin(async move { self.total.fetch_add(amount, Ordering::SeqCst) + amount })
+    }
+
+    fn sleep(&self, ms: u64) -> BoxFuture<'_, Result<u64, RemoteError>> {
+        Box::pin(async move {
+            std::thread::sleep(Duration::from_millis(ms));
+            Ok(ms)
+        })
+    }
+
+    fn total(&self) -> u64 {
+        self.total.load(Ordering::SeqCst)
+    }
+
+    fn open_account(&self) -> ServiceRef<dyn Account> {
+        ServiceRef::create_export(Box::new(SimpleAccount {
+            balance: AtomicU64::new(0),
+        }) as Box<dyn Account>)
+    }
+}
+
+struct SimpleAccount {
+    balance: AtomicU64,
+}
+
+impl Service for SimpleAccount {}
+
+impl Account for SimpleAccount {
+    fn deposit(&self, amount: u64) -> u64 {
+        self.balance.fetch_add(amount, Ordering::SeqCst) + amount
+    }
+}
+
+fn create_store() -> Box<dyn Store> {
+    Box::new(SimpleStore {
+        total: AtomicU64::new(0),
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn calls() {
+    let (ctx1, ctx2, store): (_, _, Arc<dyn Store>) = crate::connect_async(
+        Config::default_setup(),
+        Config::default_setup(),
+        create_store(),
+    );
+    assert_eq!(store.put(3).await, 3);
+    assert_eq!(store.sleep(10).await, Ok(10));
+
+    // Blocking methods are fine out of the runtime's worker threads.
+    let store_ = Arc::clone(&store);
+    let account = tokio::task::spawn_blocking(move || {
+        assert_eq!(store_.total(), 3);
+        let account: Box<dyn Account> = store_.open_account().unwrap_import().into_proxy();
+        assert_eq!(account.deposit(5), 5);
+        assert_eq!(account.deposit(6), 11);
+        account
+    })
+    .await
+    .unwrap();
+    drop(account);
+
+    drop(store);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn concurrent_calls() {
+    let (ctx1, ctx2, store): (_, _, Arc<dyn Store>) = crate::connect_async(
+        Config::default_setup(),
+        Config::default_setup(),
+        create_store(),
+    );
+    let tasks: Vec<_> = (0..100)
+        .map(|_| {
+            let store = Arc::clone(&store);
+            tokio::spawn(async move { store.put(1).await })
+        })
+        .collect();
+    let mut results = Vec::new();
+    for task in tasks {
+        results.push(task.await.unwrap());
+    }
+    results.sort_unstable();
+    assert_eq!(results, (1..=100).collect::<Vec<_>>());
+
+    // The calls are handled in parallel.
+    let start = std::time::Instant::now();
+    let sleeps: Vec<_> = (0..4).map(|_| store.sleep(200)).collect();
+    for sleep in sleeps {
+        assert_eq!(sleep.await, Ok(200));
+    }
+    assert!(start.elapsed() < Duration::from_millis(600));
+
+    drop(store);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn metrics() {
+    let (ctx1, ctx2, store): (_, _, Arc<dyn Store>) = crate::connect_async(
+        Config::default_setup(),
+        Config::default_setup(),
+        create_store(),
+    );
+    let sleep = store.sleep(200);
+    let start = std::time::Instant::now();
+    while ctx2.metrics().call_slots_in_use != 1 {
+        assert!(start.elapsed() < Duration::from_secs(5));
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    assert_eq!(sleep.await, Ok(200));
+    assert_eq!(store.put(1).await, 1);
+
+    let metrics = ctx2.metrics();
+    assert_eq!(metrics.call_slots_in_use, 0);
+    assert_eq!(metrics.imported_proxies, 1);
+    assert_eq!(metrics.outgoing[&("Store", "put")].calls, 1);
+    assert_eq!(metrics.outgoing[&("Store", "sleep")].calls, 1);
+    let metrics = ctx1.metrics();
+    assert_eq!(metrics.exported_objects, 1);
+    assert_eq!(metrics.incoming[&("Store", "put")].calls, 1);
+
+    drop(store);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn disconnected() {
+    let (ctx1, ctx2, store): (_, _, Arc<dyn Store>) = crate::connect_async(
+        Config::default_setup(),
+        Config::default_setup(),
+        create_store(),
+    );
+    let sleep = store.sleep(300);
+    ctx1.disable_garbage_collection();
+    drop(ctx1);
+    assert_eq!(sleep.await, Err(RemoteError::Disconnected));
+    assert_eq!(store.sleep(0).await, Err(RemoteError::Disconnected));
+    assert!(ctx2.is_closed());
+
+    ctx2.disable_garbage_collection();
+    drop(store);
+    ctx2.wait().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn with_context() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = tokio::task::spawn_blocking(move || {
+        let (send, recv) = tcp::connect(addr).unwrap();
+        let (ctx, store): (_, ServiceToImport<dyn Store>) =
+            Context::with_initial_service_import(Config::default_setup(), send, recv);
+        let store: Box<dyn Store> = store.into_proxy();
+        assert_eq!(block_on(store.put(4)), 4);
+        assert_eq!(store.total(), 4);
+        let account: Box<dyn Account> = store.open_account().unwrap_import().into_proxy();
+        assert_eq!(account.deposit(7), 7);
+        drop(account);
+        drop(store);
+        drop(ctx);
+    });
+
+    let (stream, _) = listener.accept().await.unwrap();
+    let (reader, writer) = stream.into_split();
+    let (send, recv) = asynchronous::framed(reader, writer, framed::DEFAULT_MAX_FRAME_SIZE);
+    let ctx = AsyncContext::with_initial_service_export(
+        Config::default_setup(),
+        send,
+        recv,
+        ServiceToExport::new(create_store()),
+    );
+    client.await.unwrap();
+    ctx.wait().await;
+}
@@ -0,0 +1,173 @@
+use remote_trait_object::raw_exchange::Skeleton;
+use remote_trait_object::transport::intra;
+use remote_trait_object::transport::TransportRecv;
+use remote_trait_object::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[service]
+pub trait Counter: Service {
+    async fn add(&self, amount: u64) -> u64;
+    async fn name(&self, prefix: &str) -> String;
+    async fn sleep(&self, ms: u64) -> Result<u64, RemoteError>;
+    async fn reset(&self);
+    /// Sleeps with the timer of `tokio`.
+    async fn wait(&self, ms: u64) -> u64;
+    fn get(&self) -> u64;
+    fn add_boxed(&self, amount: u64) -> BoxFuture<'_, u64>;
+}
+
+struct SimpleCounter {
+    value: AtomicU64,
+}
+
+impl Service for SimpleCounter {}
+
+impl Counter for SimpleCounter {
+    fn add(&self, amount: u64) -> BoxFuture<'_, u64> {
+        Box::pin(async move { self.value.fetch_add(amount, Ordering::SeqCst) + amount })
+    }
+
+    fn name(&self, prefix: &str) -> BoxFuture<'_, String> {
+        let prefix = prefix.to_owned();
+        Box::pin(async move { format!("{}counter", prefix) })
+    }
+
+    fn sleep(&self, ms: u64) -> BoxFuture<'_, Result<u64, RemoteError>> {
+        Box::pin(async move {
+            thread::sleep(Duration::from_millis(ms));
+            Ok(ms)
+        })
+    }
+
+    fn reset(&self) -> BoxFuture<'_, ()> {
+        Box::pin(async move { self.value.store(0, Ordering::SeqCst) })
+    }
+
+    fn wait(&self, ms: u64) -> BoxFuture<'_, u64> {
+        Box::pin(async move {
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            ms
+        })
+    }
+
+    fn get(&self) -> u64 {
+        self.value.load(Ordering::SeqCst)
+    }
+
+    fn add_boxed(&self, amount: u64) -> BoxFuture<'_, u64> {
+        self.add(amount)
+    }
+}
+
+fn create_counter() -> Box<dyn Counter> {
+    Box::new(SimpleCounter {
+        value: AtomicU64::new(0),
+    })
+}
+
+#[test]
+fn calls() {
+    let (ctx1, ctx2, counter): (_, _, Arc<dyn Counter>) = crate::connect(create_counter());
+    assert_eq!(block_on(counter.add(3)), 3);
+    assert_eq!(block_on(counter.add_boxed(4)), 7);
+    assert_eq!(counter.get(), 7);
+    assert_eq!(block_on(counter.name("my ")), "my counter");
+    assert_eq!(block_on(counter.sleep(10)), Ok(10));
+    block_on(counter.reset());
+    assert_eq!(counter.get(), 0);
+    drop(counter);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn requests_are_sent_without_polling() {
+    let (ctx1, ctx2, counter): (_, _, Arc<dyn Counter>) = crate::connect(create_counter());
+    let futures: Vec<_> = (0..10).map(|_| counter.add(1)).collect();
+    // Every request has been handled even though no future is polled yet.
+    while counter.get() != 10 {
+        thread::sleep(Duration::from_millis(10));
+    }
+    let mut results: Vec<_> = futures.into_iter().rev().map(block_on).collect();
+    results.sort_unstable();
+    assert_eq!(results, (1..=10).collect::<Vec<_>>());
+    drop(counter);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn concurrent_calls() {
+    let (ctx1, ctx2, counter): (_, _, Arc<dyn Counter>) = crate::connect(create_counter());
+    let sleeps: Vec<_> = (0..4).map(|_| counter.sleep(200)).collect();
+    let start = std::time::Instant::now();
+    for sleep in sleeps {
+        assert_eq!(block_on(sleep), Ok(200));
+    }
+    // The calls are handled in parallel, while this thread waits for all of them.
+    assert!(start.elapsed() < Duration::from_millis(600));
+    drop(counter);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn dropped_future() {
+    let (ctx1, ctx2, counter): (_, _, Arc<dyn Counter>) = crate::connect(create_counter());
+    drop(counter.sleep(100));
+    assert_eq!(block_on(counter.add(1)), 1);
+    drop(counter);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn disconnected() {
+    let intra::TransportEnds {
+        recv1,
+        send1,
+        recv2,
+        send2,
+    } = intra::create();
+    let (ctx, counter): (_, ServiceToImport<dyn Counter>) =
+        Context::with_initial_service_import(Config::default_setup(), send1, recv1);
+    let counter: Box<dyn Counter> = counter.into_proxy();
+    let sleep = counter.sleep(0);
+    // Receive the handshake and the request, and then break the connection without answering.
+    recv2.recv(None).unwrap();
+    recv2.recv(None).unwrap();
+    drop(send2);
+    assert_eq!(block_on(sleep), Err(RemoteError::Disconnected));
+    assert_eq!(block_on(counter.sleep(0)), Err(RemoteError::Disconnected));
+
+    ctx.disable_garbage_collection();
+    drop(counter);
+    drop(ctx);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tokio_runtime() {
+    // The exporter is created in the runtime, which drives the future of the service object.
+    let (ctx1, ctx2, counter): (_, _, Arc<dyn Counter>) = crate::connect(create_counter());
+    let counter = tokio::task::spawn_blocking(move || {
+        assert_eq!(block_on(counter.wait(10)), 10);
+        counter
+    })
+    .await
+    .unwrap();
+    drop(counter);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn from_skeleton() {
+    use remote_trait_object::macro_env::FromSkeleton;
+    let counter: Box<dyn Counter> = FromSkeleton::from_skeleton(Skeleton::new(create_counter()));
+    assert_eq!(block_on(counter.add(2)), 2);
+    assert_eq!(block_on(counter.name("local ")), "local counter");
+    assert_eq!(counter.get(), 2);
+}
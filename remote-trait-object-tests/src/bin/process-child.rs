@@ -0,0 +1,3 @@
+fn main() {
+    remote_trait_object_tests::process_child::main()
+}
@@ -0,0 +1,189 @@
+use remote_trait_object::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[service]
+pub trait Job: Service {
+    /// Works until it is done or cancelled.
+    #[pending]
+    fn run(&self, ms: u64) -> Result<u64, RemoteError>;
+    /// The time left for the call in milliseconds.
+    fn remaining(&self) -> Option<u64>;
+    /// Asks the inner job for its remaining time.
+    fn nested_remaining(&self) -> Option<u64>;
+}
+
+struct SimpleJob {
+    cancelled: Arc<AtomicU64>,
+    inner: Option<Box<dyn Job>>,
+}
+
+impl Service for SimpleJob {}
+
+impl Job for SimpleJob {
+    fn run(&self, ms: u64) -> Result<u64, RemoteError> {
+        let token = CancellationToken::current().unwrap();
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(ms) {
+            if let Err(err) = token.check() {
+                self.cancelled.fetch_add(1, Ordering::SeqCst);
+                return Err(err);
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        Ok(ms)
+    }
+
+    fn remaining(&self) -> Option<u64> {
+        let token = CancellationToken::current().unwrap();
+        token.remaining().map(|x| x.as_millis() as u64)
+    }
+
+    fn nested_remaining(&self) -> Option<u64> {
+        self.inner.as_ref().unwrap().remaining()
+    }
+}
+
+fn create_job(cancelled: &Arc<AtomicU64>, inner: Option<Box<dyn Job>>) -> Box<dyn Job> {
+    Box::new(SimpleJob {
+        cancelled: Arc::clone(cancelled),
+        inner,
+    })
+}
+
+fn job_config(call_slots: usize, call_timeout: Option<Duration>) -> Config {
+    let mut config = Config::default_setup();
+    config.call_slots = call_slots;
+    config.call_timeout = call_timeout;
+    config
+}
+
+fn wait_cancelled(cancelled: &AtomicU64, expected: u64) {
+    let start = Instant::now();
+    while cancelled.load(Ordering::SeqCst) != expected {
+        assert!(start.elapsed() < Duration::from_secs(5));
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Waits for the late response of a cancelled call, which releases its slot.
+fn wait_released(call_slots_in_use: impl Fn() -> usize) {
+    let start = Instant::now();
+    while call_slots_in_use() != 0 {
+        assert!(start.elapsed() < Duration::from_secs(5));
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn timeout() {
+    let cancelled = Arc::new(AtomicU64::new(0));
+    // A single slot must be reused after each timeout.
+    let config = job_config(1, Some(Duration::from_millis(100)));
+    let (ctx1, ctx2, job): (_, _, ServiceToImport<dyn Job>) =
+        crate::connect_with(config.clone(), config, create_job(&cancelled, None));
+    let job: Box<dyn Job> = job.into_proxy();
+    for i in 1..=3 {
+        let start = Instant::now();
+        assert_eq!(job.run(5000), Err(RemoteError::TimeOut));
+        wait_cancelled(&cancelled, i);
+        assert!(start.elapsed() < Duration::from_millis(2000));
+        wait_released(|| ctx2.metrics().call_slots_in_use);
+    }
+    assert_eq!(job.run(10), Ok(10));
+    drop(job);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn cancel_pending_reply() {
+    let cancelled = Arc::new(AtomicU64::new(0));
+    let config = job_config(1, None);
+    let (ctx1, ctx2, job): (_, _, ServiceToImport<dyn Job>) =
+        crate::connect_with(config.clone(), config, create_job(&cancelled, None));
+    let job: Box<dyn Job> = job.into_proxy();
+    let reply = job.run_pending(5000);
+    thread::sleep(Duration::from_millis(50));
+    reply.cancel();
+    wait_cancelled(&cancelled, 1);
+
+    // A dropped reply is cancelled too.
+    drop(job.run_pending(5000));
+    wait_cancelled(&cancelled, 2);
+    assert_eq!(job.run_pending(10).wait(), Ok(Ok(10)));
+    drop(job);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn deadline() {
+    let cancelled = Arc::new(AtomicU64::new(0));
+    let config = job_config(16, Some(Duration::from_millis(3000)));
+    let (ctx1, ctx2, job): (_, _, ServiceToImport<dyn Job>) =
+        crate::connect_with(config.clone(), config, create_job(&cancelled, None));
+    let job: Box<dyn Job> = job.into_proxy();
+    let remaining = job.remaining().unwrap();
+    assert!(remaining > 2000 && remaining <= 3000);
+    drop(job);
+    drop(ctx2);
+    drop(ctx1);
+
+    let config = job_config(16, None);
+    let (ctx1, ctx2, job): (_, _, ServiceToImport<dyn Job>) =
+        crate::connect_with(config.clone(), config, create_job(&cancelled, None));
+    let job: Box<dyn Job> = job.into_proxy();
+    assert_eq!(job.remaining(), None);
+    drop(job);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn nested_deadline() {
+    let cancelled = Arc::new(AtomicU64::new(0));
+    // The inner connection has no timeout of its own.
+    let config = job_config(16, None);
+    let (inner_ctx1, inner_ctx2, inner): (_, _, ServiceToImport<dyn Job>) =
+        crate::connect_with(config.clone(), config, create_job(&cancelled, None));
+    let inner: Box<dyn Job> = inner.into_proxy();
+    let config = job_config(16, Some(Duration::from_millis(3000)));
+    let (ctx1, ctx2, job): (_, _, ServiceToImport<dyn Job>) =
+        crate::connect_with(config.clone(), config, create_job(&cancelled, Some(inner)));
+    let job: Box<dyn Job> = job.into_proxy();
+    let remaining = job.nested_remaining().unwrap();
+    assert!(remaining > 2000 && remaining <= 3000);
+    drop(job);
+    drop(ctx2);
+    drop(ctx1);
+    drop(inner_ctx2);
+    drop(inner_ctx1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn async_context() {
+    let cancelled = Arc::new(AtomicU64::new(0));
+    let config = job_config(1, Some(Duration::from_millis(100)));
+    let (ctx1, ctx2, job): (_, _, Box<dyn Job>) =
+        crate::connect_async(config.clone(), config, create_job(&cancelled, None));
+    let ctx2 = Arc::new(ctx2);
+    let ctx2_ = Arc::clone(&ctx2);
+    let cancelled_ = Arc::clone(&cancelled);
+    let job = tokio::task::spawn_blocking(move || {
+        for i in 1..=3 {
+            assert_eq!(job.run(5000), Err(RemoteError::TimeOut));
+            wait_cancelled(&cancelled_, i);
+            wait_released(|| ctx2_.metrics().call_slots_in_use);
+        }
+        assert_eq!(job.run(10), Ok(10));
+        job
+    })
+    .await
+    .unwrap();
+    drop(job);
+    drop(ctx2);
+    drop(ctx1);
+}
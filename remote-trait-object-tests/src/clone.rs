@@ -0,0 +1,133 @@
+use remote_trait_object::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[service]
+pub trait Library: Service {
+    fn open_book(&self) -> ServiceRef<dyn Book>;
+}
+
+#[service]
+pub trait Book: Service {
+    fn read(&self) -> u64;
+}
+
+struct SimpleLibrary {
+    dropped: Arc<AtomicU64>,
+}
+
+impl Service for SimpleLibrary {}
+
+impl Library for SimpleLibrary {
+    fn open_book(&self) -> ServiceRef<dyn Book> {
+        ServiceRef::create_export(Box::new(SimpleBook {
+            pages: AtomicU64::new(0),
+            dropped: Arc::clone(&self.dropped),
+        }) as Box<dyn Book>)
+    }
+}
+
+struct SimpleBook {
+    pages: AtomicU64,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Service for SimpleBook {}
+
+impl Book for SimpleBook {
+    fn read(&self) -> u64 {
+        self.pages.fetch_add(1, Ordering::SeqCst) + 1
+    }
+}
+
+impl Drop for SimpleBook {
+    fn drop(&mut self) {
+        self.dropped.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn create_library(dropped: &Arc<AtomicU64>) -> Box<dyn Library> {
+    Box::new(SimpleLibrary {
+        dropped: Arc::clone(dropped),
+    })
+}
+
+#[test]
+fn clone_import() {
+    let dropped = Arc::new(AtomicU64::new(0));
+    let (ctx1, ctx2, library): (_, _, Box<dyn Library>) = crate::connect(create_library(&dropped));
+    let book = library.open_book().unwrap_import();
+    let cloned = book.try_clone().unwrap();
+    let another = cloned.try_clone().unwrap();
+    let book: Box<dyn Book> = book.into_proxy();
+    let cloned: Arc<dyn Book> = cloned.into_proxy();
+    let another: Box<dyn Book> = another.into_proxy();
+    assert_eq!(book.read(), 1);
+    assert_eq!(cloned.read(), 2);
+    assert_eq!(ctx1.metrics().exported_objects, 2);
+
+    // The service object is deleted only when all of them are dropped.
+    drop(book);
+    assert_eq!(cloned.read(), 3);
+    drop(cloned);
+    assert_eq!(another.read(), 4);
+    assert_eq!(dropped.load(Ordering::SeqCst), 0);
+    drop(another);
+    assert_eq!(dropped.load(Ordering::SeqCst), 1);
+    assert_eq!(ctx1.metrics().exported_objects, 1);
+
+    // Each book has its own references.
+    let first: Box<dyn Book> = library.open_book().unwrap_import().into_proxy();
+    let second = library.open_book().unwrap_import();
+    let second_clone: Box<dyn Book> = second.try_clone().unwrap().into_proxy();
+    let second: Box<dyn Book> = second.into_proxy();
+    drop(first);
+    assert_eq!(dropped.load(Ordering::SeqCst), 2);
+    drop(second);
+    assert_eq!(second_clone.read(), 1);
+    drop(second_clone);
+    assert_eq!(dropped.load(Ordering::SeqCst), 3);
+
+    drop(library);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn clone_initial_service() {
+    let dropped = Arc::new(AtomicU64::new(0));
+    let (ctx1, ctx2, library): (_, _, ServiceToImport<dyn Library>) = crate::connect_with(
+        Config::default_setup(),
+        Config::default_setup(),
+        create_library(&dropped),
+    );
+    let cloned: Box<dyn Library> = library.try_clone().unwrap().into_proxy();
+    let library: Box<dyn Library> = library.into_proxy();
+    drop(library);
+    let book: Box<dyn Book> = cloned.open_book().unwrap_import().into_proxy();
+    assert_eq!(book.read(), 1);
+    drop(book);
+    drop(cloned);
+    assert_eq!(ctx1.metrics().exported_objects, 0);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn clone_deleted() {
+    let dropped = Arc::new(AtomicU64::new(0));
+    let (mut ctx1, ctx2, library): (_, _, Box<dyn Library>) =
+        crate::connect(create_library(&dropped));
+    let book = library.open_book().unwrap_import();
+    ctx1.clear_service_registry();
+    assert!(matches!(
+        book.try_clone(),
+        Err(RemoteError::UnknownObject(_))
+    ));
+
+    ctx2.disable_garbage_collection();
+    drop(book);
+    drop(library);
+    drop(ctx2);
+    drop(ctx1);
+}
@@ -0,0 +1,168 @@
+use remote_trait_object::*;
+
+#[service]
+pub trait Store: Service {
+    fn buy(&self, price: u64) -> u64;
+    fn name(&self) -> String;
+    fn branch(&self) -> ServiceRef<dyn Store>;
+}
+
+/// Compatible with `Store`, since it calls the first method the same.
+#[service(no_skeleton)]
+pub trait SmallStore: Service {
+    fn buy(&self, price: &u64) -> u64;
+}
+
+/// Incompatible with `Store`, since the price is serialized differently.
+#[service(no_skeleton)]
+pub trait CheapStore: Service {
+    fn buy(&self, price: u32) -> u64;
+}
+
+/// Incompatible with `Store`, since it has a method that `Store` doesn't.
+#[service(no_skeleton)]
+pub trait BigStore: Service {
+    fn buy(&self, price: u64) -> u64;
+    fn name(&self) -> String;
+    fn branch(&self) -> ServiceRef<dyn Store>;
+    fn close(&self);
+}
+
+#[service]
+pub trait Customer: Service {
+    /// Casts the store into `CheapStore`, and returns the error.
+    fn visit(&self, store: ServiceRef<dyn Store>) -> String;
+}
+
+struct SimpleStore;
+
+impl Service for SimpleStore {}
+
+impl Store for SimpleStore {
+    fn buy(&self, price: u64) -> u64 {
+        price
+    }
+
+    fn name(&self) -> String {
+        "simple".to_owned()
+    }
+
+    fn branch(&self) -> ServiceRef<dyn Store> {
+        ServiceRef::create_export(Box::new(SimpleStore) as Box<dyn Store>)
+    }
+}
+
+struct SimpleCustomer;
+
+impl Service for SimpleCustomer {}
+
+impl Customer for SimpleCustomer {
+    fn visit(&self, store: ServiceRef<dyn Store>) -> String {
+        match store.unwrap_import().cast_service::<dyn CheapStore>() {
+            Ok(_) => "compatible".to_owned(),
+            Err(err) => err.to_string(),
+        }
+    }
+}
+
+#[test]
+fn cast_compatible() {
+    let (ctx1, ctx2, store): (_, _, Box<dyn Store>) =
+        crate::connect(Box::new(SimpleStore) as Box<dyn Store>);
+    let branch: Box<dyn SmallStore> = store
+        .branch()
+        .unwrap_import()
+        .cast_service()
+        .unwrap()
+        .into_proxy();
+    assert_eq!(branch.buy(&3), 3);
+
+    drop(branch);
+    drop(store);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn cast_incompatible() {
+    let (ctx1, ctx2, store): (_, _, Box<dyn Store>) =
+        crate::connect(Box::new(SimpleStore) as Box<dyn Store>);
+
+    let cast = store
+        .branch()
+        .unwrap_import()
+        .cast_service::<dyn CheapStore>();
+    match cast {
+        Err(RemoteError::IncompatibleService(reason)) => {
+            assert!(reason.contains("(u64) -> u64"), "{}", reason)
+        }
+        _ => panic!(),
+    }
+    let cast = store
+        .branch()
+        .unwrap_import()
+        .cast_service::<dyn BigStore>();
+    assert!(matches!(cast, Err(RemoteError::IncompatibleService(_))));
+    // The branches are dropped, instead of being leaked.
+    assert_eq!(ctx1.metrics().exported_objects, 1);
+
+    drop(store);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn cast_forwarded() {
+    let (ctx_store, ctx_host1, store): (_, _, Box<dyn Store>) =
+        crate::connect(Box::new(SimpleStore) as Box<dyn Store>);
+    let (ctx_customer, ctx_host2, customer): (_, _, Box<dyn Customer>) =
+        crate::connect(Box::new(SimpleCustomer) as Box<dyn Customer>);
+
+    // The host forwards the signature of the store, as well as its calls.
+    assert!(customer.visit(store.branch()).contains("incompatible"));
+    assert_eq!(ctx_host2.metrics().exported_objects, 1);
+    assert_eq!(ctx_store.metrics().exported_objects, 1);
+
+    drop(customer);
+    drop(ctx_host2);
+    drop(ctx_customer);
+    drop(store);
+    drop(ctx_host1);
+    drop(ctx_store);
+}
+
+#[test]
+fn initial_service_incompatible() {
+    let (ctx1, ctx2, store): (_, _, ServiceToImport<dyn CheapStore>) = crate::connect_with(
+        Config::default_setup(),
+        Config::default_setup(),
+        Box::new(SimpleStore) as Box<dyn Store>,
+    );
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert!(matches!(
+        ctx2.protocol_error(),
+        Some(ProtocolError::Handshake(_))
+    ));
+    assert!(ctx2.is_closed());
+
+    ctx2.disable_garbage_collection();
+    drop(store);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn initial_service_compatible() {
+    let (ctx1, ctx2, store): (_, _, ServiceToImport<dyn SmallStore>) = crate::connect_with(
+        Config::default_setup(),
+        Config::default_setup(),
+        Box::new(SimpleStore) as Box<dyn Store>,
+    );
+    let store: Box<dyn SmallStore> = store.into_proxy();
+    assert_eq!(store.buy(&5), 5);
+    assert_eq!(ctx2.protocol_error(), None);
+
+    drop(store);
+    drop(ctx2);
+    drop(ctx1);
+}
@@ -0,0 +1,162 @@
+use remote_trait_object::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum SleepError {
+    TooLong,
+    Remote(RemoteError),
+}
+
+impl From<RemoteError> for SleepError {
+    fn from(err: RemoteError) -> Self {
+        SleepError::Remote(err)
+    }
+}
+
+#[service]
+pub trait Sleeper: Service {
+    fn sleep(&self, ms: u64) -> Result<u64, RemoteError>;
+    #[fallible]
+    fn sleep_checked(&self, ms: u64) -> Result<u64, SleepError>;
+    fn sleep_infallible(&self, ms: u64) -> u64;
+    fn explode(&self) -> Result<(), RemoteError>;
+    fn explode_infallible(&self);
+}
+
+struct SimpleSleeper;
+
+impl Service for SimpleSleeper {}
+
+impl Sleeper for SimpleSleeper {
+    fn sleep(&self, ms: u64) -> Result<u64, RemoteError> {
+        std::thread::sleep(Duration::from_millis(ms));
+        Ok(ms)
+    }
+
+    fn sleep_checked(&self, ms: u64) -> Result<u64, SleepError> {
+        if ms > 1000 {
+            return Err(SleepError::TooLong);
+        }
+        std::thread::sleep(Duration::from_millis(ms));
+        Ok(ms)
+    }
+
+    fn sleep_infallible(&self, ms: u64) -> u64 {
+        std::thread::sleep(Duration::from_millis(ms));
+        ms
+    }
+
+    fn explode(&self) -> Result<(), RemoteError> {
+        panic!("Boom {}", 1)
+    }
+
+    fn explode_infallible(&self) {
+        panic!("Boom")
+    }
+}
+
+fn timeout_config() -> Config {
+    Config {
+        call_timeout: Some(Duration::from_millis(100)),
+        ..Config::default_setup()
+    }
+}
+
+#[test]
+fn success() {
+    let (_ctx1, _ctx2, sleeper): (_, _, Box<dyn Sleeper>) =
+        crate::connect(Box::new(SimpleSleeper) as Box<dyn Sleeper>);
+    assert_eq!(sleeper.sleep(1), Ok(1));
+    assert_eq!(sleeper.sleep_checked(1), Ok(1));
+    assert_eq!(sleeper.sleep_checked(2000), Err(SleepError::TooLong));
+    assert_eq!(sleeper.sleep_infallible(1), 1);
+}
+
+#[test]
+fn timeout() {
+    let (_ctx1, _ctx2, sleeper): (_, _, ServiceToImport<dyn Sleeper>) = crate::connect_with(
+        Config::default_setup(),
+        timeout_config(),
+        Box::new(SimpleSleeper) as Box<dyn Sleeper>,
+    );
+    let sleeper: Box<dyn Sleeper> = sleeper.into_proxy();
+    assert_eq!(sleeper.sleep(300), Err(RemoteError::TimeOut));
+    assert_eq!(
+        sleeper.sleep_checked(300),
+        Err(SleepError::Remote(RemoteError::TimeOut))
+    );
+    // The connection is still usable after a timeout.
+    assert_eq!(sleeper.sleep(1), Ok(1));
+    std::thread::sleep(Duration::from_millis(300));
+}
+
+#[test]
+fn timeout_more_than_call_slots() {
+    let config = Config {
+        call_slots: 2,
+        ..timeout_config()
+    };
+    let (_ctx1, _ctx2, sleeper): (_, _, ServiceToImport<dyn Sleeper>) = crate::connect_with(
+        config.clone(),
+        config,
+        Box::new(SimpleSleeper) as Box<dyn Sleeper>,
+    );
+    let sleeper: Box<dyn Sleeper> = sleeper.into_proxy();
+    // Each late response comes back before the next call but one, which reuses its slot.
+    for _ in 0..5 {
+        assert_eq!(sleeper.sleep(150), Err(RemoteError::TimeOut));
+    }
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(sleeper.sleep(1), Ok(1));
+}
+
+#[test]
+fn disconnected() {
+    let (ctx1, ctx2, sleeper): (_, _, Box<dyn Sleeper>) =
+        crate::connect(Box::new(SimpleSleeper) as Box<dyn Sleeper>);
+    drop(ctx1);
+    assert_eq!(sleeper.sleep(1), Err(RemoteError::Disconnected));
+    assert_eq!(
+        sleeper.sleep_checked(1),
+        Err(SleepError::Remote(RemoteError::Disconnected))
+    );
+    drop(sleeper);
+    drop(ctx2);
+}
+
+#[test]
+#[should_panic(expected = "Remote call failed")]
+fn infallible_panics() {
+    let (ctx1, _ctx2, sleeper): (_, _, Box<dyn Sleeper>) =
+        crate::connect(Box::new(SimpleSleeper) as Box<dyn Sleeper>);
+    drop(ctx1);
+    sleeper.sleep_infallible(1);
+}
+
+#[test]
+fn remote_panic() {
+    let (_ctx1, _ctx2, sleeper): (_, _, Box<dyn Sleeper>) =
+        crate::connect(Box::new(SimpleSleeper) as Box<dyn Sleeper>);
+    match sleeper.explode() {
+        Err(RemoteError::Panic {
+            trait_name,
+            message,
+            ..
+        }) => {
+            assert_eq!(trait_name, "Sleeper");
+            assert_eq!(message, "Boom 1");
+        }
+        x => panic!("Unexpected result: {:?}", x),
+    }
+    // The connection is still usable after a panic.
+    assert_eq!(sleeper.sleep(1), Ok(1));
+}
+
+#[test]
+#[should_panic(expected = "panicked on the other side: Boom")]
+fn remote_panic_infallible() {
+    let (_ctx1, _ctx2, sleeper): (_, _, Box<dyn Sleeper>) =
+        crate::connect(Box::new(SimpleSleeper) as Box<dyn Sleeper>);
+    sleeper.explode_infallible();
+}
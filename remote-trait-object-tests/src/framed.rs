@@ -0,0 +1,161 @@
+use remote_trait_object::transport::framed::{self, FramedRecv, FramedSend};
+use remote_trait_object::transport::{TransportError, TransportRecv, TransportSend};
+use remote_trait_object::*;
+use std::io::{Cursor, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[service]
+pub trait Generator: Service {
+    fn generate(&self, size: usize) -> Result<Vec<u8>, RemoteError>;
+}
+
+struct SimpleGenerator;
+
+impl Service for SimpleGenerator {}
+
+impl Generator for SimpleGenerator {
+    fn generate(&self, size: usize) -> Result<Vec<u8>, RemoteError> {
+        Ok(vec![1; size])
+    }
+}
+
+fn framed_tcp(stream: TcpStream, max_frame_size: usize) -> (FramedSend<TcpStream>, FramedRecv) {
+    let shutdown = stream.try_clone().unwrap();
+    framed::new(
+        stream.try_clone().unwrap(),
+        stream,
+        max_frame_size,
+        move || drop(shutdown.shutdown(Shutdown::Both)),
+    )
+}
+
+fn loopback() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().unwrap();
+    (server, client)
+}
+
+#[test]
+fn frames() {
+    let (a, b) = loopback();
+    let (send1, _recv1) = framed_tcp(a, 1024);
+    let (_send2, recv2) = framed_tcp(b, 1024);
+    send1.send(b"hello", None).unwrap();
+    send1.send(&[], None).unwrap();
+    assert_eq!(
+        send1.send(&[0; 1025], None),
+        Err(TransportError::FrameTooLarge {
+            size: 1025,
+            max: 1024
+        })
+    );
+    send1.send(&[0; 1024], None).unwrap();
+    assert_eq!(recv2.recv(None).unwrap(), b"hello");
+    assert_eq!(recv2.recv(None).unwrap(), Vec::<u8>::new());
+    assert_eq!(recv2.recv(None).unwrap(), vec![0; 1024]);
+    assert_eq!(
+        recv2.recv(Some(Duration::from_millis(50))),
+        Err(TransportError::TimeOut)
+    );
+}
+
+#[test]
+fn oversized_frame() {
+    let (mut a, b) = loopback();
+    let (_send, recv) = framed_tcp(b, 1024);
+    a.write_all(&2000u32.to_le_bytes()).unwrap();
+    let expected = Err(TransportError::FrameTooLarge {
+        size: 2000,
+        max: 1024,
+    });
+    assert_eq!(recv.recv(None), expected);
+    // The stream can't be recovered after that.
+    assert_eq!(recv.recv(None), expected);
+}
+
+#[test]
+fn closed() {
+    let recv = FramedRecv::new(Cursor::new(vec![1, 0, 0, 0, 7, 1]), 1024, || ());
+    assert_eq!(recv.recv(None).unwrap(), vec![7]);
+    assert_eq!(recv.recv(None), Err(TransportError::Custom));
+}
+
+#[test]
+fn termination() {
+    let (a, _b) = loopback();
+    let shutdown_called = Arc::new(AtomicBool::new(false));
+    let shutdown_called_ = Arc::clone(&shutdown_called);
+    let shutdown = a.try_clone().unwrap();
+    let recv = FramedRecv::new(a, 1024, move || {
+        shutdown_called_.store(true, Ordering::SeqCst);
+        shutdown.shutdown(Shutdown::Both).unwrap();
+    });
+    let terminator = recv.create_terminator();
+    let receiver = thread::spawn(move || recv.recv(None));
+    thread::sleep(Duration::from_millis(50));
+    terminator.terminate();
+    assert_eq!(receiver.join().unwrap(), Err(TransportError::Termination));
+    assert!(shutdown_called.load(Ordering::SeqCst));
+}
+
+/// A writer that takes a while for each write, and can't be given a timeout.
+struct SlowWriter;
+
+impl Write for SlowWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        thread::sleep(Duration::from_millis(100));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn send_timeout() {
+    let send = FramedSend::new(SlowWriter, 1024, || ());
+    assert_eq!(
+        send.send(b"hello", Some(Duration::from_millis(50))),
+        Err(TransportError::TimeOut)
+    );
+    send.send(b"hello", Some(Duration::from_secs(10))).unwrap();
+
+    // Nobody reads the other end, so the socket buffer fills up.
+    let (a, _b) = loopback();
+    let (send, _recv) = framed_tcp(a, 1024 * 1024);
+    let send = send.with_write_timeout(TcpStream::set_write_timeout);
+    let large = vec![0; 1024 * 1024];
+    let result = (0..1000)
+        .map(|_| send.send(&large, Some(Duration::from_millis(50))))
+        .find(Result::is_err);
+    assert_eq!(result, Some(Err(TransportError::TimeOut)));
+}
+
+#[test]
+fn context() {
+    let (a, b) = loopback();
+    let (send1, recv1) = framed_tcp(a, 1024);
+    let (send2, recv2) = framed_tcp(b, 1024);
+    let ctx1 = Context::with_initial_service_export(
+        Config::default_setup(),
+        send1,
+        recv1,
+        ServiceToExport::new(Box::new(SimpleGenerator) as Box<dyn Generator>),
+    );
+    let (ctx2, generator): (_, ServiceToImport<dyn Generator>) =
+        Context::with_initial_service_import(Config::default_setup(), send2, recv2);
+    let generator: Box<dyn Generator> = generator.into_proxy();
+    assert_eq!(generator.generate(10), Ok(vec![1; 10]));
+    // The response doesn't fit in a frame.
+    assert_eq!(generator.generate(2000), Err(RemoteError::InvalidData));
+    assert_eq!(generator.generate(10), Ok(vec![1; 10]));
+    drop(generator);
+    drop(ctx2);
+    drop(ctx1);
+}
@@ -0,0 +1,171 @@
+use parking_lot::Mutex;
+use remote_trait_object::raw_exchange::*;
+use remote_trait_object::transport::intra;
+use remote_trait_object::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[service]
+pub trait Store: Service {
+    fn buy(&self, price: u64) -> u64;
+    fn branch(&self) -> ServiceRef<dyn Store>;
+}
+
+#[service]
+pub trait Customer: Service {
+    fn introduce(&self, store: Handoff<dyn Store>);
+}
+
+#[derive(Default)]
+struct SimpleStore {
+    sold: Arc<AtomicU64>,
+}
+
+impl Service for SimpleStore {}
+
+impl Store for SimpleStore {
+    fn buy(&self, price: u64) -> u64 {
+        self.sold.fetch_add(price, Ordering::SeqCst) + price
+    }
+
+    fn branch(&self) -> ServiceRef<dyn Store> {
+        ServiceRef::create_export(Box::new(SimpleStore {
+            sold: Arc::clone(&self.sold),
+        }) as Box<dyn Store>)
+    }
+}
+
+/// Keeps the introduced store, so that the test can import it through whichever context it wants.
+struct SimpleCustomer {
+    introduced: Arc<Mutex<Option<Handoff<dyn Store>>>>,
+}
+
+impl Service for SimpleCustomer {}
+
+impl Customer for SimpleCustomer {
+    fn introduce(&self, store: Handoff<dyn Store>) {
+        *self.introduced.lock() = Some(store);
+    }
+}
+
+struct Parties {
+    store: Box<dyn Store>,
+    customer: Box<dyn Customer>,
+    introduced: Arc<Mutex<Option<Handoff<dyn Store>>>>,
+    /// The contexts of the store, the host and the customer, connected to the host.
+    store_host: Context,
+    host_store: Context,
+    host_customer: Context,
+    customer_host: Context,
+    /// The contexts of the store and the customer, connected to each other.
+    store_customer: Context,
+    customer_store: Context,
+}
+
+/// The host is connected to the store and the customer, which are also connected to each other.
+fn run() -> Parties {
+    let (store_host, host_store, store) =
+        crate::connect(Box::new(SimpleStore::default()) as Box<dyn Store>);
+
+    let introduced = Arc::new(Mutex::new(None));
+    let (customer_host, host_customer, customer) = crate::connect(Box::new(SimpleCustomer {
+        introduced: Arc::clone(&introduced),
+    }) as Box<dyn Customer>);
+
+    let intra::TransportEnds {
+        recv1,
+        send1,
+        recv2,
+        send2,
+    } = intra::create();
+    let store_customer = Context::new(Config::default_setup(), send1, recv1);
+    let customer_store = Context::new(Config::default_setup(), send2, recv2);
+
+    Parties {
+        store,
+        customer,
+        introduced,
+        store_host,
+        host_store,
+        host_customer,
+        customer_host,
+        store_customer,
+        customer_store,
+    }
+}
+
+impl Parties {
+    fn introduce(&self) -> Handoff<dyn Store> {
+        let branch = self.store.branch().unwrap_import();
+        self.customer.introduce(Handoff::new(branch).unwrap());
+        self.introduced.lock().take().unwrap()
+    }
+
+    fn close(self) {
+        drop(self.store);
+        drop(self.customer);
+        drop(self.customer_store);
+        drop(self.store_customer);
+        drop(self.host_customer);
+        drop(self.customer_host);
+        drop(self.host_store);
+        drop(self.store_host);
+    }
+}
+
+#[test]
+fn handoff_direct() {
+    let parties = run();
+    let handoff = parties.introduce();
+    let token = handoff.token();
+    // The host forwards it until the customer decides.
+    assert_eq!(parties.host_customer.metrics().exported_objects, 2);
+
+    let branch: Box<dyn Store> = handoff.import(Some(&parties.customer_store)).into_proxy();
+    assert_eq!(branch.buy(5), 5);
+    assert_eq!(parties.store.buy(0), 5);
+    // The host has nothing to do with it anymore.
+    assert_eq!(parties.host_customer.metrics().exported_objects, 1);
+    assert_eq!(parties.store_host.metrics().exported_objects, 1);
+    assert_eq!(parties.store_customer.metrics().exported_objects, 2);
+    assert!(parties.host_customer.metrics().incoming.is_empty());
+
+    // A token can be redeemed only once.
+    assert!(matches!(
+        import_service_from_handoff_token::<dyn Store, Box<dyn Store>>(
+            &parties.customer_store,
+            token
+        ),
+        Err(RemoteError::UnknownHandoffToken)
+    ));
+
+    drop(branch);
+    assert_eq!(parties.store_customer.metrics().exported_objects, 1);
+    parties.close();
+}
+
+#[test]
+fn handoff_fallback() {
+    let parties = run();
+    let handoff = parties.introduce();
+    let token = handoff.token();
+
+    // Without a direct connection, it goes through the host.
+    let branch: Box<dyn Store> = handoff.import(None).into_proxy();
+    assert_eq!(branch.buy(5), 5);
+    assert_eq!(parties.host_customer.metrics().incoming[&("", "")].calls, 1);
+    assert_eq!(parties.store_host.metrics().exported_objects, 2);
+    drop(branch);
+    assert_eq!(parties.host_customer.metrics().exported_objects, 1);
+    assert_eq!(parties.store_host.metrics().exported_objects, 1);
+
+    // The token is revoked through the host, so that the store doesn't keep the branch for it.
+    assert!(matches!(
+        import_service_from_handoff_token::<dyn Store, Box<dyn Store>>(
+            &parties.customer_store,
+            token
+        ),
+        Err(RemoteError::UnknownHandoffToken)
+    ));
+    parties.close();
+}
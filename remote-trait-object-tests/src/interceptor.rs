@@ -0,0 +1,262 @@
+use parking_lot::Mutex;
+use remote_trait_object::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[service]
+pub trait Counter: Service {
+    fn get(&self) -> u64;
+    fn add(&self, amount: u64) -> Result<u64, RemoteError>;
+    async fn get_async(&self) -> u64;
+    #[oneway]
+    fn reset(&self);
+}
+
+#[derive(Default)]
+struct SimpleCounter {
+    value: AtomicU64,
+}
+
+impl Service for SimpleCounter {}
+
+impl Counter for SimpleCounter {
+    fn get(&self) -> u64 {
+        self.value.load(Ordering::SeqCst)
+    }
+
+    fn add(&self, amount: u64) -> Result<u64, RemoteError> {
+        Ok(self.value.fetch_add(amount, Ordering::SeqCst) + amount)
+    }
+
+    fn get_async(&self) -> BoxFuture<'_, u64> {
+        Box::pin(async move { self.get() })
+    }
+
+    fn reset(&self) {
+        self.value.store(0, Ordering::SeqCst);
+    }
+}
+
+/// Records what it sees, and rejects the calls of a method if asked.
+#[derive(Debug)]
+struct Recorder {
+    name: &'static str,
+    log: Arc<Mutex<Vec<String>>>,
+    reject: Option<&'static str>,
+}
+
+impl Interceptor for Recorder {
+    fn before(&self, call: &InterceptedCall<'_>) -> Result<(), RemoteError> {
+        self.log.lock().push(format!(
+            "{} before {}::{} of {}",
+            self.name,
+            call.trait_name(),
+            call.method_name(),
+            call.object_id()
+        ));
+        if Some(call.method_name()) == self.reject {
+            return Err(RemoteError::UnknownMethod {
+                trait_name: call.trait_name().to_owned(),
+                method: call.method(),
+            });
+        }
+        Ok(())
+    }
+
+    fn after(&self, call: &InterceptedCall<'_>, result: Result<&[u8], &RemoteError>) {
+        self.log.lock().push(format!(
+            "{} after {} {}",
+            self.name,
+            call.method_name(),
+            if result.is_ok() { "ok" } else { "error" }
+        ));
+    }
+}
+
+fn recorder(
+    name: &'static str,
+    log: &Arc<Mutex<Vec<String>>>,
+    reject: Option<&'static str>,
+) -> Arc<dyn Interceptor> {
+    Arc::new(Recorder {
+        name,
+        log: Arc::clone(log),
+        reject,
+    })
+}
+
+fn configs(
+    client_interceptors: Vec<Arc<dyn Interceptor>>,
+    server_interceptors: Vec<Arc<dyn Interceptor>>,
+) -> (Config, Config) {
+    let exporter = Config {
+        server_interceptors,
+        ..Config::default_setup()
+    };
+    let importer = Config {
+        client_interceptors,
+        ..Config::default_setup()
+    };
+    (exporter, importer)
+}
+
+fn take(log: &Mutex<Vec<String>>) -> Vec<String> {
+    std::mem::take(&mut *log.lock())
+}
+
+#[test]
+fn both_sides() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let (exporter, importer) = configs(
+        vec![recorder("client", &log, None)],
+        vec![recorder("server", &log, None)],
+    );
+    let (ctx1, ctx2, counter): (_, _, ServiceToImport<dyn Counter>) = crate::connect_with(
+        exporter,
+        importer,
+        Box::new(SimpleCounter::default()) as Box<dyn Counter>,
+    );
+    let counter: Box<dyn Counter> = counter.into_proxy();
+    assert_eq!(counter.add(3).unwrap(), 3);
+    assert_eq!(
+        take(&log),
+        vec![
+            "client before Counter::add of 1",
+            "server before Counter::add of 1",
+            "server after add ok",
+            "client after add ok",
+        ]
+    );
+
+    assert_eq!(block_on(counter.get_async()), 3);
+    assert_eq!(
+        take(&log),
+        vec![
+            "client before Counter::get_async of 1",
+            "server before Counter::get_async of 1",
+            "server after get_async ok",
+            "client after get_async ok",
+        ]
+    );
+
+    counter.reset();
+    let start = Instant::now();
+    while log.lock().len() < 4 {
+        assert!(start.elapsed() < Duration::from_secs(5));
+        thread::sleep(Duration::from_millis(1));
+    }
+    let mut seen = take(&log);
+    // The server might handle the request before the client is done with it.
+    seen.sort();
+    assert_eq!(
+        seen,
+        vec![
+            "client after reset ok",
+            "client before Counter::reset of 1",
+            "server after reset ok",
+            "server before Counter::reset of 1",
+        ]
+    );
+
+    drop(counter);
+    // Delete requests are not intercepted.
+    assert!(log.lock().is_empty());
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn nested_order() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let (exporter, importer) = configs(
+        vec![recorder("outer", &log, None), recorder("inner", &log, None)],
+        Vec::new(),
+    );
+    let (ctx1, ctx2, counter): (_, _, ServiceToImport<dyn Counter>) = crate::connect_with(
+        exporter,
+        importer,
+        Box::new(SimpleCounter::default()) as Box<dyn Counter>,
+    );
+    let counter: Box<dyn Counter> = counter.into_proxy();
+    assert_eq!(counter.get(), 0);
+    assert_eq!(
+        take(&log),
+        vec![
+            "outer before Counter::get of 1",
+            "inner before Counter::get of 1",
+            "inner after get ok",
+            "outer after get ok",
+        ]
+    );
+    drop(counter);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn client_short_circuit() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let (exporter, importer) = configs(
+        vec![
+            recorder("outer", &log, None),
+            recorder("client", &log, Some("add")),
+        ],
+        vec![recorder("server", &log, None)],
+    );
+    let (ctx1, ctx2, counter): (_, _, ServiceToImport<dyn Counter>) = crate::connect_with(
+        exporter,
+        importer,
+        Box::new(SimpleCounter::default()) as Box<dyn Counter>,
+    );
+    let counter: Box<dyn Counter> = counter.into_proxy();
+    match counter.add(1) {
+        Err(RemoteError::UnknownMethod { trait_name, .. }) => assert_eq!(trait_name, "Counter"),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+    // Neither the request is sent nor the rejecting interceptor sees the result.
+    assert_eq!(
+        take(&log),
+        vec![
+            "outer before Counter::add of 1",
+            "client before Counter::add of 1",
+            "outer after add error",
+        ]
+    );
+    assert_eq!(counter.get(), 0);
+    drop(counter);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn server_short_circuit() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let (exporter, importer) = configs(
+        vec![recorder("client", &log, None)],
+        vec![recorder("server", &log, Some("add"))],
+    );
+    let (ctx1, ctx2, counter): (_, _, ServiceToImport<dyn Counter>) = crate::connect_with(
+        exporter,
+        importer,
+        Box::new(SimpleCounter::default()) as Box<dyn Counter>,
+    );
+    let counter: Box<dyn Counter> = counter.into_proxy();
+    assert!(matches!(
+        counter.add(1),
+        Err(RemoteError::UnknownMethod { .. })
+    ));
+    assert_eq!(
+        take(&log),
+        vec![
+            "client before Counter::add of 1",
+            "server before Counter::add of 1",
+            "client after add error",
+        ]
+    );
+    assert_eq!(counter.get(), 0);
+    drop(counter);
+    drop(ctx2);
+    drop(ctx1);
+}
@@ -0,0 +1,84 @@
+use remote_trait_object::transport::intra::{self, TransportEnds};
+use remote_trait_object::transport::{TransportError, TransportRecv, TransportSend};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn capacity_and_timeout() {
+    let TransportEnds {
+        send1,
+        recv1,
+        send2: _send2,
+        recv2,
+    } = intra::create_with_capacity(1);
+    send1.send(b"1", None).unwrap();
+    assert_eq!(
+        send1.send(b"2", Some(Duration::from_millis(50))),
+        Err(TransportError::TimeOut)
+    );
+    assert_eq!(recv2.recv(None).unwrap(), b"1");
+    assert_eq!(
+        recv2.recv(Some(Duration::from_millis(50))),
+        Err(TransportError::TimeOut)
+    );
+    assert_eq!(
+        recv1.recv(Some(Duration::from_millis(50))),
+        Err(TransportError::TimeOut)
+    );
+}
+
+#[test]
+fn disconnected() {
+    let TransportEnds {
+        send1,
+        recv1,
+        send2,
+        recv2,
+    } = intra::create();
+    drop(send2);
+    drop(recv2);
+    assert_eq!(send1.send(b"1", None), Err(TransportError::Custom));
+    assert_eq!(recv1.recv(None), Err(TransportError::Custom));
+}
+
+#[test]
+fn terminate_recv() {
+    let TransportEnds {
+        send1: _send1,
+        recv1,
+        send2: _send2,
+        recv2: _recv2,
+    } = intra::create();
+    let terminator = recv1.create_terminator();
+    let receiver = thread::spawn(move || {
+        let result = recv1.recv(None);
+        (result, recv1)
+    });
+    thread::sleep(Duration::from_millis(50));
+    terminator.terminate();
+    let (result, recv1) = receiver.join().unwrap();
+    assert_eq!(result, Err(TransportError::Termination));
+    // It stays terminated
+    assert_eq!(recv1.recv(None), Err(TransportError::Termination));
+}
+
+#[test]
+fn terminate_send() {
+    let TransportEnds {
+        send1,
+        recv1: _recv1,
+        send2: _send2,
+        recv2: _recv2,
+    } = intra::create_with_capacity(0);
+    let terminator = send1.create_terminator();
+    let sender = thread::spawn(move || {
+        let result = send1.send(b"1", None);
+        (result, send1)
+    });
+    thread::sleep(Duration::from_millis(50));
+    terminator.terminate();
+    terminator.terminate();
+    let (result, send1) = sender.join().unwrap();
+    assert_eq!(result, Err(TransportError::Termination));
+    assert_eq!(send1.send(b"2", None), Err(TransportError::Termination));
+}
@@ -1,10 +1,124 @@
-#[macro_use]
-extern crate log;
-
+#[cfg(test)]
+mod adder;
+#[cfg(test)]
+mod async_context;
+#[cfg(test)]
+mod async_service;
+#[cfg(test)]
+mod cancel;
+#[cfg(test)]
+mod clone;
+#[cfg(test)]
+mod compatibility;
+#[cfg(test)]
+mod fallible;
+#[cfg(test)]
+mod framed;
+#[cfg(test)]
+mod handoff;
+#[cfg(test)]
+mod interceptor;
+#[cfg(test)]
+mod intra;
+#[cfg(test)]
+mod meta_service;
+#[cfg(test)]
+mod metadata;
+#[cfg(test)]
+mod metrics;
+#[cfg(test)]
+mod oneway;
+#[cfg(test)]
+mod pending;
 #[cfg(test)]
 mod ping;
+pub mod process_child;
+#[cfg(test)]
+mod protocol;
+#[cfg(test)]
+mod reexport;
+#[cfg(all(test, target_os = "linux"))]
+mod shm;
+#[cfg(test)]
 mod simple;
+#[cfg(test)]
+mod tcp;
 mod test_store;
-pub mod transport;
+#[cfg(test)]
+mod trace;
+#[cfg(all(test, unix))]
+mod unix;
 
 pub use test_store::{massive_no_export, massive_with_export};
+
+#[cfg(test)]
+use remote_trait_object::raw_exchange::{ImportProxy, IntoSkeleton};
+#[cfg(test)]
+use remote_trait_object::{
+    AsyncContext, Config, Context, DescribeService, Service, ServiceToExport, ServiceToImport,
+};
+
+/// Connects two contexts with the default config, where the first one exports `service` as the initial service.
+/// The second one is returned with the proxy object of it.
+#[cfg(test)]
+fn connect<T, P>(service: impl IntoSkeleton<T>) -> (Context, Context, P)
+where
+    T: ?Sized + Service + DescribeService,
+    P: ImportProxy<T>,
+{
+    let (ctx1, ctx2, import) =
+        connect_with(Config::default_setup(), Config::default_setup(), service);
+    (ctx1, ctx2, import.into_proxy())
+}
+
+/// Connects two contexts, where the first one exports `service` as the initial service,
+/// and the second one imports it as a service of `B`, which is supposed to be compatible.
+#[cfg(test)]
+fn connect_with<A, B>(
+    exporter: Config,
+    importer: Config,
+    service: impl IntoSkeleton<A>,
+) -> (Context, Context, ServiceToImport<B>)
+where
+    A: ?Sized + Service,
+    B: ?Sized + Service + DescribeService,
+{
+    let remote_trait_object::transport::intra::TransportEnds {
+        recv1,
+        send1,
+        recv2,
+        send2,
+    } = remote_trait_object::transport::intra::create();
+    let ctx1 =
+        Context::with_initial_service_export(exporter, send1, recv1, ServiceToExport::new(service));
+    let (ctx2, import) = Context::with_initial_service_import(importer, send2, recv2);
+    (ctx1, ctx2, import)
+}
+
+/// Like [`connect()`], but connects two async contexts with the given configs over a tokio duplex stream.
+#[cfg(test)]
+fn connect_async<T, P>(
+    exporter: Config,
+    importer: Config,
+    service: impl IntoSkeleton<T>,
+) -> (AsyncContext, AsyncContext, P)
+where
+    T: ?Sized + Service + DescribeService,
+    P: ImportProxy<T>,
+{
+    use remote_trait_object::transport::{asynchronous, framed};
+
+    let (stream1, stream2) = tokio::io::duplex(64 * 1024);
+    let (read1, write1) = tokio::io::split(stream1);
+    let (read2, write2) = tokio::io::split(stream2);
+    let (send1, recv1) = asynchronous::framed(read1, write1, framed::DEFAULT_MAX_FRAME_SIZE);
+    let (send2, recv2) = asynchronous::framed(read2, write2, framed::DEFAULT_MAX_FRAME_SIZE);
+    let ctx1 = AsyncContext::with_initial_service_export(
+        exporter,
+        send1,
+        recv1,
+        ServiceToExport::new(service),
+    );
+    let (ctx2, import) = AsyncContext::with_initial_service_import(importer, send2, recv2);
+    (ctx1, ctx2, import.into_proxy())
+}
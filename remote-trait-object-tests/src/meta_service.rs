@@ -0,0 +1,102 @@
+use remote_trait_object::*;
+
+#[service]
+pub trait Store: Service {
+    fn buy(&self, price: u64) -> u64;
+    fn branch(&self) -> ServiceRef<dyn Store>;
+}
+
+struct SimpleStore;
+
+impl Service for SimpleStore {}
+
+impl Store for SimpleStore {
+    fn buy(&self, price: u64) -> u64 {
+        price
+    }
+
+    fn branch(&self) -> ServiceRef<dyn Store> {
+        ServiceRef::create_export(Box::new(SimpleStore) as Box<dyn Store>)
+    }
+}
+
+fn store_config() -> Config {
+    Config {
+        name: "store".to_owned(),
+        call_slots: 64,
+        ..Config::default_setup()
+    }
+}
+
+#[test]
+fn exported_services() {
+    let (ctx1, ctx2, store): (_, _, ServiceToImport<dyn Store>) = crate::connect_with(
+        store_config(),
+        Config::default_setup(),
+        Box::new(SimpleStore) as Box<dyn Store>,
+    );
+    let store: Box<dyn Store> = store.into_proxy();
+    let meta_service = ctx2.meta_service();
+    assert_eq!(
+        meta_service.exported_services().unwrap(),
+        vec![ExportedService {
+            id: 1,
+            trait_name: "Store".to_owned()
+        }]
+    );
+
+    let branch: Box<dyn Store> = store.branch().unwrap_import().into_proxy();
+    let services = meta_service.exported_services().unwrap();
+    assert_eq!(services.len(), 2);
+    assert!(services[0].id < services[1].id);
+    assert_eq!(services[1].trait_name, "Store");
+    assert_eq!(
+        meta_service.signature(services[1].id).unwrap(),
+        Some(<dyn Store>::signature())
+    );
+    assert_eq!(
+        meta_service.trait_signature("Store").unwrap(),
+        Some(<dyn Store>::signature())
+    );
+    assert_eq!(meta_service.trait_signature("Cart").unwrap(), None);
+    assert!(matches!(
+        meta_service.signature(1000),
+        Err(RemoteError::UnknownObject(1000))
+    ));
+
+    drop(branch);
+    assert_eq!(meta_service.exported_services().unwrap().len(), 1);
+    // The other way, there is only the null service.
+    assert_eq!(
+        ctx1.meta_service().exported_services().unwrap()[0].trait_name,
+        "NullService"
+    );
+
+    drop(store);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn info_and_ping() {
+    let (ctx1, ctx2, store): (_, _, ServiceToImport<dyn Store>) = crate::connect_with(
+        store_config(),
+        Config::default_setup(),
+        Box::new(SimpleStore) as Box<dyn Store>,
+    );
+    let store: Box<dyn Store> = store.into_proxy();
+    let info = ctx2.meta_service().info().unwrap();
+    assert_eq!(info.name, "store");
+    assert_eq!(info.protocol_version, 2);
+    assert!(!info.crate_version.is_empty());
+    assert_eq!(info.call_slots, 64);
+    assert_eq!(info.call_timeout, Config::default_setup().call_timeout);
+    assert_eq!(ctx1.meta_service().info().unwrap().call_slots, 512);
+
+    let rtt = ctx2.ping().unwrap();
+    assert!(rtt < std::time::Duration::from_secs(1));
+
+    drop(store);
+    drop(ctx2);
+    drop(ctx1);
+}
@@ -0,0 +1,184 @@
+use parking_lot::Mutex;
+use remote_trait_object::*;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What a service object has seen from `current_call()`.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Seen {
+    context_name: String,
+    object_id: u32,
+    metadata: CallMetadata,
+}
+
+#[service]
+pub trait Inspector: Service {
+    fn inspect(&self) -> Option<Seen>;
+    async fn inspect_async(&self) -> Option<Seen>;
+    #[oneway]
+    fn record(&self);
+    fn recorded(&self) -> Vec<Seen>;
+    fn open_child(&self) -> ServiceRef<dyn Inspector>;
+    /// Calls the inner inspector, passing on the metadata if asked.
+    fn inspect_inner(&self, pass_on: bool) -> Option<Seen>;
+}
+
+struct SimpleInspector {
+    recorded: Arc<Mutex<Vec<Seen>>>,
+    inner: Option<Box<dyn Inspector>>,
+}
+
+fn seen() -> Option<Seen> {
+    current_call().map(|call| Seen {
+        context_name: call.context_name().to_owned(),
+        object_id: call.object_id(),
+        metadata: call.metadata().clone(),
+    })
+}
+
+impl Service for SimpleInspector {}
+
+impl Inspector for SimpleInspector {
+    fn inspect(&self) -> Option<Seen> {
+        seen()
+    }
+
+    fn inspect_async(&self) -> BoxFuture<'_, Option<Seen>> {
+        Box::pin(async { seen() })
+    }
+
+    fn record(&self) {
+        self.recorded.lock().push(seen().unwrap());
+    }
+
+    fn recorded(&self) -> Vec<Seen> {
+        self.recorded.lock().clone()
+    }
+
+    fn open_child(&self) -> ServiceRef<dyn Inspector> {
+        ServiceRef::create_export(Box::new(SimpleInspector {
+            recorded: Arc::clone(&self.recorded),
+            inner: None,
+        }) as Box<dyn Inspector>)
+    }
+
+    fn inspect_inner(&self, pass_on: bool) -> Option<Seen> {
+        let inner = self.inner.as_ref().unwrap();
+        if pass_on {
+            let metadata = current_call().unwrap().metadata().clone();
+            with_call_metadata(metadata, || inner.inspect())
+        } else {
+            inner.inspect()
+        }
+    }
+}
+
+fn create_inspector(inner: Option<Box<dyn Inspector>>) -> Box<dyn Inspector> {
+    Box::new(SimpleInspector {
+        recorded: Default::default(),
+        inner,
+    })
+}
+
+fn named_config(name: &str) -> Config {
+    Config {
+        name: name.to_owned(),
+        ..Config::default_setup()
+    }
+}
+
+fn metadata(entries: &[(&str, &str)]) -> CallMetadata {
+    entries
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn calls() {
+    let (ctx1, ctx2, inspector): (_, _, ServiceToImport<dyn Inspector>) = crate::connect_with(
+        named_config("exporter"),
+        Config::default_setup(),
+        create_inspector(None),
+    );
+    let inspector: Box<dyn Inspector> = inspector.into_proxy();
+    let seen = inspector.inspect().unwrap();
+    assert_eq!(seen.context_name, "exporter");
+    assert!(seen.metadata.is_empty());
+
+    let given = metadata(&[("trace-id", "1234"), ("user", "alice")]);
+    let seen_with = with_call_metadata(given.clone(), || inspector.inspect()).unwrap();
+    assert_eq!(seen_with.metadata, given);
+    assert_eq!(seen_with.object_id, seen.object_id);
+
+    // The metadata is given when an async proxy sends the request, not when the future is polled.
+    let future = with_call_metadata(given.clone(), || inspector.inspect_async());
+    assert_eq!(block_on(future).unwrap().metadata, given);
+
+    let child: Box<dyn Inspector> = inspector.open_child().unwrap_import().into_proxy();
+    assert_ne!(child.inspect().unwrap().object_id, seen.object_id);
+    drop(child);
+
+    drop(inspector);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn oneway() {
+    let (ctx1, ctx2, inspector): (_, _, ServiceToImport<dyn Inspector>) = crate::connect_with(
+        named_config("exporter"),
+        Config::default_setup(),
+        create_inspector(None),
+    );
+    let inspector: Box<dyn Inspector> = inspector.into_proxy();
+    with_call_metadata(metadata(&[("event", "1")]), || inspector.record());
+    let start = Instant::now();
+    while inspector.recorded().is_empty() {
+        assert!(start.elapsed() < Duration::from_secs(5));
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(
+        inspector.recorded()[0].metadata,
+        metadata(&[("event", "1")])
+    );
+    drop(inspector);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn nested() {
+    let (inner_ctx1, inner_ctx2, inner): (_, _, ServiceToImport<dyn Inspector>) =
+        crate::connect_with(
+            named_config("inner"),
+            Config::default_setup(),
+            create_inspector(None),
+        );
+    let inner: Box<dyn Inspector> = inner.into_proxy();
+    let (ctx1, ctx2, outer): (_, _, ServiceToImport<dyn Inspector>) = crate::connect_with(
+        named_config("outer"),
+        Config::default_setup(),
+        create_inspector(Some(inner)),
+    );
+    let outer: Box<dyn Inspector> = outer.into_proxy();
+    let given = metadata(&[("user", "alice")]);
+    with_call_metadata(given.clone(), || {
+        let seen = outer.inspect_inner(false).unwrap();
+        assert_eq!(seen.context_name, "inner");
+        assert!(seen.metadata.is_empty());
+        assert_eq!(outer.inspect_inner(true).unwrap().metadata, given);
+    });
+    drop(outer);
+    drop(ctx2);
+    drop(ctx1);
+    drop(inner_ctx2);
+    drop(inner_ctx1);
+}
+
+#[test]
+fn local() {
+    let inspector = create_inspector(None);
+    assert_eq!(inspector.inspect(), None);
+}
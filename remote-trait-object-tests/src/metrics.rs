@@ -0,0 +1,121 @@
+use remote_trait_object::*;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[service]
+pub trait Echo: Service {
+    fn echo(&self, data: Vec<u8>) -> Vec<u8>;
+    fn sleep(&self, ms: u64);
+    fn crash(&self) -> Result<(), RemoteError>;
+    fn open_child(&self) -> ServiceRef<dyn Echo>;
+}
+
+struct SimpleEcho;
+
+impl Service for SimpleEcho {}
+
+impl Echo for SimpleEcho {
+    fn echo(&self, data: Vec<u8>) -> Vec<u8> {
+        data
+    }
+
+    fn sleep(&self, ms: u64) {
+        thread::sleep(Duration::from_millis(ms));
+    }
+
+    fn crash(&self) -> Result<(), RemoteError> {
+        panic!("Crashed")
+    }
+
+    fn open_child(&self) -> ServiceRef<dyn Echo> {
+        ServiceRef::create_export(Box::new(SimpleEcho) as Box<dyn Echo>)
+    }
+}
+
+#[test]
+fn calls() {
+    let (ctx1, ctx2, echo): (_, _, Box<dyn Echo>) =
+        crate::connect(Box::new(SimpleEcho) as Box<dyn Echo>);
+    for size in &[0, 10, 100] {
+        assert_eq!(echo.echo(vec![7; *size]).len(), *size);
+    }
+    assert!(matches!(echo.crash(), Err(RemoteError::Panic { .. })));
+
+    let outgoing = ctx2.metrics().outgoing;
+    let incoming = ctx1.metrics().incoming;
+    let echoed = &outgoing[&("Echo", "echo")];
+    assert_eq!(echoed.calls, 3);
+    assert_eq!(echoed.errors, 0);
+    assert_eq!(echoed.latency.count(), 3);
+    assert!(echoed.request_bytes > 110);
+    assert!(echoed.response_bytes > 110);
+    // Both sides see the same bytes.
+    assert_eq!(
+        incoming[&("Echo", "echo")].request_bytes,
+        echoed.request_bytes
+    );
+    assert_eq!(
+        incoming[&("Echo", "echo")].response_bytes,
+        echoed.response_bytes
+    );
+
+    let crashed = &outgoing[&("Echo", "crash")];
+    assert_eq!((crashed.calls, crashed.errors), (1, 1));
+    assert_eq!(incoming[&("Echo", "crash")].errors, 1);
+
+    // Each context only makes or handles the calls.
+    assert!(ctx1.metrics().outgoing.is_empty());
+    assert!(ctx2.metrics().incoming.is_empty());
+    assert_eq!(ctx2.metrics().context_name, "my rto");
+
+    drop(echo);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn objects() {
+    let (ctx1, ctx2, echo): (_, _, Box<dyn Echo>) =
+        crate::connect(Box::new(SimpleEcho) as Box<dyn Echo>);
+    assert_eq!(ctx1.metrics().exported_objects, 1);
+    assert_eq!(ctx2.metrics().imported_proxies, 1);
+
+    let child: Box<dyn Echo> = echo.open_child().unwrap_import().into_proxy();
+    assert_eq!(ctx1.metrics().exported_objects, 2);
+    assert_eq!(ctx2.metrics().imported_proxies, 2);
+
+    // A delete request is made synchronously.
+    drop(child);
+    assert_eq!(ctx1.metrics().exported_objects, 1);
+    assert_eq!(ctx2.metrics().imported_proxies, 1);
+
+    drop(echo);
+    assert_eq!(ctx2.metrics().imported_proxies, 0);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn call_slots() {
+    let (ctx1, ctx2, echo): (_, _, Box<dyn Echo>) =
+        crate::connect(Box::new(SimpleEcho) as Box<dyn Echo>);
+    assert_eq!(ctx2.metrics().call_slots_in_use, 0);
+    let echo: std::sync::Arc<dyn Echo> = echo.into();
+    let echo_ = std::sync::Arc::clone(&echo);
+    let sleeper = thread::spawn(move || echo_.sleep(200));
+
+    let start = Instant::now();
+    while ctx2.metrics().call_slots_in_use != 1 {
+        assert!(start.elapsed() < Duration::from_secs(5));
+        thread::sleep(Duration::from_millis(1));
+    }
+    sleeper.join().unwrap();
+    assert_eq!(ctx2.metrics().call_slots_in_use, 0);
+    assert!(
+        ctx2.metrics().outgoing[&("Echo", "sleep")].latency.sum() >= Duration::from_millis(200)
+    );
+
+    drop(echo);
+    drop(ctx2);
+    drop(ctx1);
+}
@@ -0,0 +1,142 @@
+use remote_trait_object::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[service]
+pub trait Sink: Service {
+    #[oneway]
+    fn notify(&self, value: u64);
+    #[oneway]
+    fn notify_slowly(&self, value: u64, ms: u64) -> ();
+    #[oneway]
+    fn try_notify(&self, value: u64) -> Result<(), RemoteError>;
+    #[pending]
+    fn sleep(&self, ms: u64);
+    fn total(&self) -> u64;
+}
+
+#[derive(Default)]
+struct SimpleSink {
+    total: AtomicU64,
+}
+
+impl Service for SimpleSink {}
+
+impl Sink for SimpleSink {
+    fn notify(&self, value: u64) {
+        self.total.fetch_add(value, Ordering::SeqCst);
+    }
+
+    fn notify_slowly(&self, value: u64, ms: u64) {
+        thread::sleep(Duration::from_millis(ms));
+        self.notify(value);
+    }
+
+    fn try_notify(&self, value: u64) -> Result<(), RemoteError> {
+        self.notify(value);
+        Ok(())
+    }
+
+    fn sleep(&self, ms: u64) {
+        thread::sleep(Duration::from_millis(ms));
+    }
+
+    fn total(&self) -> u64 {
+        self.total.load(Ordering::SeqCst)
+    }
+}
+
+/// Oneway calls may be handled in any order, so it waits until all of them are done.
+fn wait_total(sink: &dyn Sink, expected: u64) {
+    let start = Instant::now();
+    while sink.total() != expected {
+        assert!(start.elapsed() < Duration::from_secs(5));
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn calls() {
+    let (ctx1, ctx2, sink): (_, _, Box<dyn Sink>) =
+        crate::connect(Box::new(SimpleSink::default()) as Box<dyn Sink>);
+    for i in 1..=100 {
+        sink.notify(i);
+    }
+    assert_eq!(sink.try_notify(10), Ok(()));
+    wait_total(sink.as_ref(), 5060);
+    drop(sink);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn returns_without_waiting() {
+    let (ctx1, ctx2, sink): (_, _, Box<dyn Sink>) =
+        crate::connect(Box::new(SimpleSink::default()) as Box<dyn Sink>);
+    let start = Instant::now();
+    sink.notify_slowly(1, 300);
+    assert!(start.elapsed() < Duration::from_millis(200));
+    wait_total(sink.as_ref(), 1);
+    drop(sink);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn no_call_slot() {
+    let mut config = Config::default_setup();
+    config.call_slots = 1;
+    config.call_timeout = Some(Duration::from_millis(100));
+    let (ctx1, ctx2, sink): (_, _, ServiceToImport<dyn Sink>) = crate::connect_with(
+        config.clone(),
+        config,
+        Box::new(SimpleSink::default()) as Box<dyn Sink>,
+    );
+    let sink: Box<dyn Sink> = sink.into_proxy();
+    // The only slot is taken while sending notifications.
+    let sleep = sink.sleep_pending(300);
+    for _ in 0..10 {
+        sink.notify(1);
+    }
+    sleep.wait().unwrap();
+    wait_total(sink.as_ref(), 10);
+    drop(sink);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn disconnected() {
+    let (ctx1, ctx2, sink): (_, _, Box<dyn Sink>) =
+        crate::connect(Box::new(SimpleSink::default()) as Box<dyn Sink>);
+    ctx1.disable_garbage_collection();
+    drop(ctx1);
+    // A failure is not reported to the caller, unless the method is fallible.
+    sink.notify(1);
+    assert_eq!(sink.try_notify(1), Err(RemoteError::Disconnected));
+    ctx2.disable_garbage_collection();
+    drop(sink);
+    drop(ctx2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn async_context() {
+    let (ctx1, ctx2, sink): (_, _, Box<dyn Sink>) = crate::connect_async(
+        Config::default_setup(),
+        Config::default_setup(),
+        Box::new(SimpleSink::default()) as Box<dyn Sink>,
+    );
+    let sink = tokio::task::spawn_blocking(move || {
+        for i in 1..=100 {
+            sink.notify(i);
+        }
+        wait_total(sink.as_ref(), 5050);
+        sink
+    })
+    .await
+    .unwrap();
+    drop(sink);
+    drop(ctx2);
+    drop(ctx1);
+}
@@ -0,0 +1,155 @@
+use remote_trait_object::raw_exchange::Skeleton;
+use remote_trait_object::transport::intra;
+use remote_trait_object::transport::TransportRecv;
+use remote_trait_object::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[service]
+pub trait Worker: Service {
+    #[pending]
+    fn work(&self, ms: u64) -> u64;
+    #[pending]
+    fn name(&self, prefix: &str) -> String;
+    #[pending]
+    fn fail(&self) -> Result<(), RemoteError>;
+    #[pending]
+    fn reset(&mut self);
+    fn done(&self) -> u64;
+}
+
+struct SimpleWorker {
+    done: AtomicU64,
+}
+
+impl Service for SimpleWorker {}
+
+impl Worker for SimpleWorker {
+    fn work(&self, ms: u64) -> u64 {
+        thread::sleep(Duration::from_millis(ms));
+        self.done.fetch_add(1, Ordering::SeqCst);
+        ms
+    }
+
+    fn name(&self, prefix: &str) -> String {
+        format!("{}worker", prefix)
+    }
+
+    fn fail(&self) -> Result<(), RemoteError> {
+        Err(RemoteError::TimeOut)
+    }
+
+    fn reset(&mut self) {
+        self.done.store(0, Ordering::SeqCst)
+    }
+
+    fn done(&self) -> u64 {
+        self.done.load(Ordering::SeqCst)
+    }
+}
+
+fn create_worker() -> Box<dyn Worker> {
+    Box::new(SimpleWorker {
+        done: AtomicU64::new(0),
+    })
+}
+
+#[test]
+fn calls() {
+    let (ctx1, ctx2, mut worker): (_, _, Box<dyn Worker>) = crate::connect(create_worker());
+    assert_eq!(worker.work_pending(1).wait(), Ok(1));
+    assert_eq!(
+        worker.name_pending("my ").wait(),
+        Ok("my worker".to_owned())
+    );
+    assert_eq!(worker.fail_pending().wait(), Ok(Err(RemoteError::TimeOut)));
+    assert_eq!(worker.done(), 1);
+    assert_eq!(worker.reset_pending().wait(), Ok(()));
+    assert_eq!(worker.done(), 0);
+    drop(worker);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn fan_out() {
+    let (ctx1, ctx2, worker): (_, _, Box<dyn Worker>) = crate::connect(create_worker());
+    let start = Instant::now();
+    let replies: Vec<_> = (0..4).map(|_| worker.work_pending(200)).collect();
+    // Every request has been sent, so they are handled in parallel while this thread collects the results.
+    let results: Vec<_> = replies.into_iter().map(|reply| reply.wait()).collect();
+    assert_eq!(results, vec![Ok(200); 4]);
+    assert!(start.elapsed() < Duration::from_millis(600));
+    drop(worker);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn wait_timeout_and_try_get() {
+    let (ctx1, ctx2, worker): (_, _, Box<dyn Worker>) = crate::connect(create_worker());
+    let reply = worker.work_pending(200);
+    let reply = reply.try_get().unwrap_err();
+    let reply = reply.wait_timeout(Duration::from_millis(10)).unwrap_err();
+    assert_eq!(reply.wait_timeout(Duration::from_secs(5)).unwrap(), Ok(200));
+
+    let mut reply = worker.work_pending(0);
+    let result = loop {
+        match reply.try_get() {
+            Ok(result) => break result,
+            Err(pending) => reply = pending,
+        }
+        thread::sleep(Duration::from_millis(1));
+    };
+    assert_eq!(result, Ok(0));
+
+    // A dropped reply abandons the call, without affecting the following ones.
+    drop(worker.work_pending(100));
+    assert_eq!(worker.work_pending(0).wait(), Ok(0));
+    drop(worker);
+    drop(ctx2);
+    drop(ctx1);
+}
+
+#[test]
+fn disconnected() {
+    let intra::TransportEnds {
+        recv1,
+        send1,
+        recv2,
+        send2,
+    } = intra::create();
+    let (ctx, worker): (_, ServiceToImport<dyn Worker>) =
+        Context::with_initial_service_import(Config::default_setup(), send1, recv1);
+    let worker: Box<dyn Worker> = worker.into_proxy();
+    let reply = worker.work_pending(0);
+    // Receive the handshake and the request, and then break the connection without answering.
+    recv2.recv(None).unwrap();
+    recv2.recv(None).unwrap();
+    drop(send2);
+    assert_eq!(reply.wait(), Err(RemoteError::Disconnected));
+    assert_eq!(
+        worker.work_pending(0).wait(),
+        Err(RemoteError::Disconnected)
+    );
+
+    ctx.disable_garbage_collection();
+    drop(worker);
+    drop(ctx);
+}
+
+#[test]
+fn from_skeleton() {
+    use remote_trait_object::macro_env::FromSkeleton;
+    let worker: Box<dyn Worker> = FromSkeleton::from_skeleton(Skeleton::new(create_worker()));
+    assert_eq!(worker.work_pending(0).wait(), Ok(0));
+    assert_eq!(worker.done(), 1);
+
+    // A local service object answers right away.
+    let worker = create_worker();
+    assert_eq!(
+        worker.name_pending("local ").try_get().unwrap(),
+        Ok("local worker".to_owned())
+    );
+}
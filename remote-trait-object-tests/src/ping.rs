@@ -51,12 +51,12 @@ fn run(
     (Context, ServiceToImport<dyn Hello>),
     (Context, ServiceToImport<dyn Hello>),
 ) {
-    let crate::transport::TransportEnds {
+    let remote_trait_object::transport::intra::TransportEnds {
         recv1,
         send1,
         recv2,
         send2,
-    } = crate::transport::create();
+    } = remote_trait_object::transport::intra::create();
     (
         Context::with_initial_service(
             Config::default_setup(),
@@ -0,0 +1,35 @@
+//! A service provider that runs as a child process, which is used to test `transport::process`.
+
+use remote_trait_object::transport::process;
+use remote_trait_object::*;
+
+#[service]
+pub trait Calculator: Service {
+    fn add(&self, a: i64, b: i64) -> i64;
+    fn exit(&self, code: i32) -> Result<(), RemoteError>;
+}
+
+struct SimpleCalculator;
+
+impl Service for SimpleCalculator {}
+
+impl Calculator for SimpleCalculator {
+    fn add(&self, a: i64, b: i64) -> i64 {
+        a + b
+    }
+
+    fn exit(&self, code: i32) -> Result<(), RemoteError> {
+        std::process::exit(code)
+    }
+}
+
+pub fn main() {
+    let context = process::serve_stdio(
+        Config::default_setup(),
+        ServiceToExport::new(Box::new(SimpleCalculator) as Box<dyn Calculator>),
+    );
+    if context.wait(None).is_err() {
+        eprintln!("Failed to wait for the parent");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,352 @@
+//! Tests that talk to a context with raw packets, playing a broken or malicious peer.
+
+use remote_trait_object::transport::intra::{IntraRecv, IntraSend};
+use remote_trait_object::transport::{Terminate, TransportError, TransportRecv, TransportSend};
+use remote_trait_object::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+const REQUEST: u8 = 1;
+const RESPONSE: u8 = 1 << 1;
+const ERROR: u8 = 1 << 2;
+const CANCEL: u8 = 1 << 4;
+const CONTROL: u8 = 1 << 5;
+
+#[service]
+pub trait Echo: Service {
+    fn echo(&self, x: u32) -> u32;
+    fn try_echo(&self, x: u32) -> Result<u32, RemoteError>;
+}
+
+/// Method id of `Echo::echo`, as the macro has generated it.
+fn echo_method() -> u32 {
+    <dyn Echo>::signature().methods[0].id
+}
+
+struct SimpleEcho;
+
+impl Service for SimpleEcho {}
+
+impl Echo for SimpleEcho {
+    fn echo(&self, x: u32) -> u32 {
+        x
+    }
+
+    fn try_echo(&self, x: u32) -> Result<u32, RemoteError> {
+        Ok(x)
+    }
+}
+
+/// Method id of `Echo::try_echo`.
+fn try_echo_method() -> u32 {
+    <dyn Echo>::signature().methods[1].id
+}
+
+fn packet(flags: u8, slot: u32, object_id: u32, method: u32, data: &[u8]) -> Vec<u8> {
+    let mut buffer =
+        bincode::serialize(&(2u8, flags, slot, object_id, method, data.len() as u32)).unwrap();
+    buffer.extend_from_slice(data);
+    buffer
+}
+
+fn handshake() -> Vec<u8> {
+    packet(CONTROL, u32::MAX, 0, 0, b"remote-trait-object")
+}
+
+/// Checks the handshake sent by a context, which carries the signature of its initial service.
+fn assert_handshake(received: &[u8], initial_service: &ServiceSignature) {
+    let (version, flags): (u8, u8) = bincode::deserialize(received).unwrap();
+    assert_eq!(version, 2);
+    assert_ne!(flags & CONTROL, 0);
+    let mut data = b"remote-trait-object".to_vec();
+    data.extend(bincode::serialize(initial_service).unwrap());
+    assert_eq!(&received[18..], &data[..]);
+}
+
+fn create_echo_context() -> (Context, IntraSend, IntraRecv) {
+    let remote_trait_object::transport::intra::TransportEnds {
+        recv1,
+        send1,
+        recv2,
+        send2,
+    } = remote_trait_object::transport::intra::create();
+    let ctx = Context::with_initial_service_export(
+        Config::default_setup(),
+        send1,
+        recv1,
+        ServiceToExport::new(Box::new(SimpleEcho) as Box<dyn Echo>),
+    );
+    assert_handshake(&recv2.recv(None).unwrap(), &<dyn Echo>::signature());
+    (ctx, send2, recv2)
+}
+
+/// Sends a request and returns the header fields and the data of the response.
+fn call(
+    send: &IntraSend,
+    recv: &IntraRecv,
+    object_id: u32,
+    method: u32,
+    args: &[u8],
+) -> (u8, Vec<u8>) {
+    send.send(&packet(REQUEST, 3, object_id, method, args), None)
+        .unwrap();
+    let response = recv.recv(Some(Duration::from_secs(1))).unwrap();
+    let (_, flags, slot, _, _, length): (u8, u8, u32, u32, u32, u32) =
+        bincode::deserialize(&response).unwrap();
+    assert_ne!(flags & RESPONSE, 0);
+    assert_eq!(slot, 3);
+    assert_eq!(length as usize, response.len() - 18);
+    (flags, response[18..].to_vec())
+}
+
+fn call_error(
+    send: &IntraSend,
+    recv: &IntraRecv,
+    object_id: u32,
+    method: u32,
+    args: &[u8],
+) -> RemoteError {
+    let (flags, data) = call(send, recv, object_id, method, args);
+    assert_ne!(flags & ERROR, 0);
+    bincode::deserialize(&data).unwrap()
+}
+
+#[test]
+fn malformed_packets() {
+    let (ctx, send, recv) = create_echo_context();
+    send.send(&handshake(), None).unwrap();
+
+    send.send(&[1, 2, 3], None).unwrap();
+    let mut wrong_length = packet(REQUEST, 3, 1, echo_method(), &[1, 2]);
+    wrong_length.pop();
+    send.send(&wrong_length, None).unwrap();
+    send.send(&packet(REQUEST | RESPONSE, 3, 1, echo_method(), &[]), None)
+        .unwrap();
+    send.send(&packet(RESPONSE, 9999, 1, echo_method(), &[]), None)
+        .unwrap();
+
+    // The context is still working
+    let args = serde_cbor::to_vec(&(7u32,)).unwrap();
+    let (flags, data) = call(&send, &recv, 1, echo_method(), &args);
+    assert_eq!(flags & ERROR, 0);
+    assert_eq!(serde_cbor::from_slice::<u32>(&data).unwrap(), 7);
+
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(ctx.protocol_error(), Some(ProtocolError::InvalidSlot(9999)));
+    drop(ctx);
+}
+
+#[test]
+fn invalid_calls() {
+    let (ctx, send, recv) = create_echo_context();
+    send.send(&handshake(), None).unwrap();
+
+    let args = serde_cbor::to_vec(&(7u32,)).unwrap();
+    assert_eq!(
+        call_error(&send, &recv, 77, echo_method(), &args),
+        RemoteError::UnknownObject(77)
+    );
+    assert_eq!(
+        call_error(&send, &recv, 1, 12345, &args),
+        RemoteError::UnknownMethod {
+            trait_name: "Echo".to_owned(),
+            method: 12345
+        }
+    );
+    assert_eq!(
+        call_error(&send, &recv, 1, echo_method(), &[0xff, 0xff]),
+        RemoteError::InvalidData
+    );
+    // Delete request of an unknown object
+    assert_eq!(
+        call_error(&send, &recv, 77, u32::MAX, &[]),
+        RemoteError::UnknownObject(77)
+    );
+    // Delete and retain requests of the meta service, which is never deleted
+    assert_eq!(
+        call_error(&send, &recv, 0, u32::MAX, &[]),
+        RemoteError::UnknownObject(0)
+    );
+    assert_eq!(
+        call_error(&send, &recv, 0, u32::MAX - 1, &[]),
+        RemoteError::UnknownObject(0)
+    );
+    assert_eq!(ctx.protocol_error(), None);
+    drop(ctx);
+}
+
+#[test]
+fn protocol_mismatch() {
+    let (ctx, send, _recv) = create_echo_context();
+    let (ctx2, echo): (_, ServiceToImport<dyn Echo>) = {
+        let remote_trait_object::transport::intra::TransportEnds {
+            recv1,
+            send1,
+            recv2: _,
+            send2,
+        } = remote_trait_object::transport::intra::create();
+        // A packet of the protocol version 1, which has no handshake.
+        send2.send(&[0u8; 12], None).unwrap();
+        Context::with_initial_service_import(Config::default_setup(), send1, recv1)
+    };
+    let echo: Box<dyn Echo> = echo.into_proxy();
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(matches!(
+        ctx2.protocol_error(),
+        Some(ProtocolError::Handshake(_))
+    ));
+    ctx2.disable_garbage_collection();
+    drop(echo);
+    drop(ctx2);
+
+    // The same for a context that has already sent its handshake.
+    send.send(&[0u8; 12], None).unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(matches!(
+        ctx.protocol_error(),
+        Some(ProtocolError::Handshake(_))
+    ));
+    drop(ctx);
+}
+
+#[test]
+fn incompatible_initial_service() {
+    let remote_trait_object::transport::intra::TransportEnds {
+        recv1,
+        send1,
+        recv2: _,
+        send2,
+    } = remote_trait_object::transport::intra::create();
+    // An echo that takes `u64` instead.
+    let exported = ServiceSignature::new(
+        "Echo",
+        vec![MethodSignature::new(echo_method(), "echo", &["u64"], "u64")],
+    );
+    let mut data = b"remote-trait-object".to_vec();
+    data.extend(bincode::serialize(&exported).unwrap());
+    send2
+        .send(&packet(CONTROL, u32::MAX, 0, 0, &data), None)
+        .unwrap();
+    let (ctx, echo): (_, ServiceToImport<dyn Echo>) =
+        Context::with_initial_service_import(Config::default_setup(), send1, recv1);
+    let echo: Box<dyn Echo> = echo.into_proxy();
+    std::thread::sleep(Duration::from_millis(100));
+    match ctx.protocol_error() {
+        Some(ProtocolError::Handshake(reason)) => assert!(reason.contains("echo"), "{}", reason),
+        other => panic!("{:?}", other),
+    }
+    assert!(ctx.is_closed());
+    assert_eq!(echo.try_echo(1), Err(RemoteError::Disconnected));
+    ctx.disable_garbage_collection();
+    drop(echo);
+    drop(ctx);
+}
+
+#[test]
+fn broken_connection() {
+    let remote_trait_object::transport::intra::TransportEnds {
+        recv1,
+        send1,
+        recv2,
+        send2,
+    } = remote_trait_object::transport::intra::create();
+    let config = Config {
+        call_timeout: Some(Duration::from_secs(10)),
+        ..Config::default_setup()
+    };
+    let (ctx, echo): (_, ServiceToImport<dyn Echo>) =
+        Context::with_initial_service_import(config, send1, recv1);
+    let echo: Arc<dyn Echo> = echo.into_proxy();
+    assert_handshake(
+        &recv2.recv(None).unwrap(),
+        &ServiceSignature::new("NullService", vec![]),
+    );
+    send2.send(&handshake(), None).unwrap();
+
+    let echo_ = Arc::clone(&echo);
+    let caller = std::thread::spawn(move || echo_.try_echo(1));
+    // Wait until the request arrives, and then break the connection without answering it.
+    recv2.recv(None).unwrap();
+    let start = std::time::Instant::now();
+    drop(send2);
+    assert_eq!(caller.join().unwrap(), Err(RemoteError::Disconnected));
+    assert_eq!(echo.try_echo(2), Err(RemoteError::Disconnected));
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    ctx.disable_garbage_collection();
+    drop(echo);
+    drop(ctx);
+}
+
+/// Answers a request of `Echo::try_echo` with the given value.
+fn answer_try_echo(send: &IntraSend, request: &[u8], x: u32) {
+    let (_, flags, slot, object_id, method): (u8, u8, u32, u32, u32) =
+        bincode::deserialize(request).unwrap();
+    assert_ne!(flags & REQUEST, 0);
+    assert_eq!(method, try_echo_method());
+    let data = serde_cbor::to_vec(&Ok::<u32, RemoteError>(x)).unwrap();
+    send.send(&packet(RESPONSE, slot, object_id, method, &data), None)
+        .unwrap();
+}
+
+/// Holds back cancellations for a while, so that a late response arrives while one is being sent.
+#[derive(Debug)]
+struct SlowCancel(IntraSend);
+
+impl TransportSend for SlowCancel {
+    fn send(&self, data: &[u8], timeout: Option<Duration>) -> Result<(), TransportError> {
+        if data[1] & CANCEL != 0 {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        self.0.send(data, timeout)
+    }
+
+    fn create_terminator(&self) -> Box<dyn Terminate> {
+        self.0.create_terminator()
+    }
+}
+
+#[test]
+fn late_response() {
+    let remote_trait_object::transport::intra::TransportEnds {
+        recv1,
+        send1,
+        recv2,
+        send2,
+    } = remote_trait_object::transport::intra::create();
+    // A single slot, which the timed-out call and the next one take in turn.
+    let config = Config {
+        call_slots: 1,
+        call_timeout: Some(Duration::from_millis(100)),
+        ..Config::default_setup()
+    };
+    let (ctx, echo): (_, ServiceToImport<dyn Echo>) =
+        Context::with_initial_service_import(config, SlowCancel(send1), recv1);
+    let echo: Arc<dyn Echo> = echo.into_proxy();
+    assert_handshake(
+        &recv2.recv(None).unwrap(),
+        &ServiceSignature::new("NullService", vec![]),
+    );
+    send2.send(&handshake(), None).unwrap();
+
+    let echo_ = Arc::clone(&echo);
+    let first = std::thread::spawn(move || echo_.try_echo(1));
+    let request = recv2.recv(None).unwrap();
+    // Answer after the timeout, while the cancellation is being sent.
+    std::thread::sleep(Duration::from_millis(150));
+    let echo_ = Arc::clone(&echo);
+    let second = std::thread::spawn(move || echo_.try_echo(2));
+    answer_try_echo(&send2, &request, 1);
+
+    // The cancellation must come before the next request on the slot, which it would cancel otherwise.
+    let cancel = recv2.recv(Some(Duration::from_secs(1))).unwrap();
+    assert_ne!(cancel[1] & CANCEL, 0);
+    let request = recv2.recv(Some(Duration::from_secs(1))).unwrap();
+    answer_try_echo(&send2, &request, 2);
+    assert_eq!(first.join().unwrap(), Err(RemoteError::TimeOut));
+    assert_eq!(second.join().unwrap(), Ok(2));
+
+    ctx.disable_garbage_collection();
+    drop(echo);
+    drop(ctx);
+}
@@ -0,0 +1,182 @@
+use parking_lot::Mutex;
+use remote_trait_object::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[service]
+pub trait Store: Service {
+    fn buy(&self, price: u64) -> u64;
+    /// Returns the `user` of the call metadata.
+    fn customer(&self) -> String;
+    fn branch(&self) -> ServiceRef<dyn Store>;
+    fn try_branch(&self) -> Result<ServiceRef<dyn Store>, RemoteError>;
+    /// Buys at another store.
+    fn refer(&self, store: Handoff<dyn Store>) -> Result<u64, RemoteError>;
+}
+
+#[service]
+pub trait Customer: Service {
+    /// Buys at the store, and returns what the store has seen as the customer.
+    fn visit(&self, store: ServiceRef<dyn Store>) -> String;
+    /// Buys at a branch of the store.
+    fn visit_branch(&self, store: ServiceRef<dyn Store>) -> Result<u64, RemoteError>;
+    /// Has the store buy at the other one.
+    fn refer(
+        &self,
+        store: ServiceRef<dyn Store>,
+        other: ServiceRef<dyn Store>,
+    ) -> Result<u64, RemoteError>;
+    fn keep(&self, store: ServiceRef<dyn Store>);
+    fn leave(&self);
+}
+
+#[derive(Default)]
+struct SimpleStore {
+    sold: Arc<AtomicU64>,
+}
+
+impl Service for SimpleStore {}
+
+impl Store for SimpleStore {
+    fn buy(&self, price: u64) -> u64 {
+        self.sold.fetch_add(price, Ordering::SeqCst) + price
+    }
+
+    fn customer(&self) -> String {
+        current_call().unwrap().metadata()["user"].clone()
+    }
+
+    fn branch(&self) -> ServiceRef<dyn Store> {
+        ServiceRef::create_export(Box::new(SimpleStore {
+            sold: Arc::clone(&self.sold),
+        }) as Box<dyn Store>)
+    }
+
+    fn try_branch(&self) -> Result<ServiceRef<dyn Store>, RemoteError> {
+        Ok(self.branch())
+    }
+
+    fn refer(&self, store: Handoff<dyn Store>) -> Result<u64, RemoteError> {
+        let store: Box<dyn Store> = store.import(None).into_proxy();
+        Ok(store.buy(10))
+    }
+}
+
+#[derive(Default)]
+struct SimpleCustomer {
+    kept: Mutex<Option<Box<dyn Store>>>,
+}
+
+impl Service for SimpleCustomer {}
+
+impl Customer for SimpleCustomer {
+    fn visit(&self, store: ServiceRef<dyn Store>) -> String {
+        let store: Box<dyn Store> = store.unwrap_import().into_proxy();
+        store.buy(10);
+        let mut metadata = CallMetadata::new();
+        metadata.insert("user".to_owned(), "bob".to_owned());
+        with_call_metadata(metadata, || store.customer())
+    }
+
+    fn visit_branch(&self, store: ServiceRef<dyn Store>) -> Result<u64, RemoteError> {
+        let store: Box<dyn Store> = store.unwrap_import().into_proxy();
+        let branch: Box<dyn Store> = store.try_branch()?.unwrap_import().into_proxy();
+        Ok(branch.buy(10))
+    }
+
+    fn refer(
+        &self,
+        store: ServiceRef<dyn Store>,
+        other: ServiceRef<dyn Store>,
+    ) -> Result<u64, RemoteError> {
+        let other = Handoff::new(other.unwrap_import())?;
+        let store: Box<dyn Store> = store.unwrap_import().into_proxy();
+        store.refer(other)
+    }
+
+    fn keep(&self, store: ServiceRef<dyn Store>) {
+        *self.kept.lock() = Some(store.unwrap_import().into_proxy());
+    }
+
+    fn leave(&self) {
+        self.kept.lock().take();
+    }
+}
+
+#[test]
+fn reexport() {
+    let (ctx_store, ctx_host1, store): (_, _, Box<dyn Store>) =
+        crate::connect(Box::new(SimpleStore::default()) as Box<dyn Store>);
+    let (ctx_customer, ctx_host2, customer): (_, _, Box<dyn Customer>) =
+        crate::connect(Box::new(SimpleCustomer::default()) as Box<dyn Customer>);
+    assert_eq!(ctx_host2.metrics().exported_objects, 1);
+
+    // The host passes the branch on without importing it.
+    let branch = store.branch();
+    assert_eq!(ctx_store.metrics().exported_objects, 2);
+    assert_eq!(customer.visit(branch), "bob");
+    assert_eq!(store.buy(0), 10);
+    // The customer has dropped it, and so has the host.
+    assert_eq!(ctx_host2.metrics().exported_objects, 1);
+    assert_eq!(ctx_store.metrics().exported_objects, 1);
+
+    // It can be wrapped as a local service, too.
+    let branch = ServiceRef::create_export(store.branch().unwrap_import());
+    assert_eq!(customer.visit(branch), "bob");
+    assert_eq!(store.buy(0), 20);
+    assert_eq!(ctx_store.metrics().exported_objects, 1);
+
+    drop(customer);
+    drop(ctx_host2);
+    drop(ctx_customer);
+    drop(store);
+    drop(ctx_host1);
+    drop(ctx_store);
+}
+
+#[test]
+fn nested_service() {
+    let (ctx_store, ctx_host1, store): (_, _, Box<dyn Store>) =
+        crate::connect(Box::new(SimpleStore::default()) as Box<dyn Store>);
+    let (ctx_customer, ctx_host2, customer): (_, _, Box<dyn Customer>) =
+        crate::connect(Box::new(SimpleCustomer::default()) as Box<dyn Customer>);
+    // The branch would have to be forwarded by the host again, which the relay can't do.
+    assert_eq!(
+        customer.visit_branch(store.branch()),
+        Err(RemoteError::InvalidData)
+    );
+    // So would the fallback of a handoff.
+    assert_eq!(
+        customer.refer(store.branch(), store.branch()),
+        Err(RemoteError::InvalidData)
+    );
+    assert_eq!(customer.visit(store.branch()), "bob");
+
+    drop(customer);
+    drop(ctx_host2);
+    drop(ctx_customer);
+    drop(store);
+    drop(ctx_host1);
+    drop(ctx_store);
+}
+
+#[test]
+fn reexport_outlives_exporter() {
+    let (ctx_store, ctx_host1, store): (_, _, Box<dyn Store>) =
+        crate::connect(Box::new(SimpleStore::default()) as Box<dyn Store>);
+    let (ctx_customer, ctx_host2, customer): (_, _, Box<dyn Customer>) =
+        crate::connect(Box::new(SimpleCustomer::default()) as Box<dyn Customer>);
+    customer.keep(store.branch());
+    assert_eq!(ctx_host2.metrics().exported_objects, 2);
+
+    // The store goes away first, so nobody hears that the customer leaves.
+    drop(store);
+    drop(ctx_host1);
+    drop(ctx_store);
+    customer.leave();
+    assert_eq!(ctx_host2.metrics().exported_objects, 1);
+
+    drop(customer);
+    drop(ctx_host2);
+    drop(ctx_customer);
+}
@@ -0,0 +1,137 @@
+use remote_trait_object::transport::shm;
+use remote_trait_object::transport::{TransportError, TransportRecv, TransportSend};
+use std::fs::OpenOptions;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn wrap_around() {
+    let ((send1, _recv1), (_send2, recv2)) = shm::pair(128).unwrap();
+    for i in 0..100u8 {
+        let data = vec![i; 50];
+        send1.send(&data, None).unwrap();
+        assert_eq!(recv2.recv(None).unwrap(), data);
+    }
+    send1.send(&[], None).unwrap();
+    assert_eq!(recv2.recv(None).unwrap(), Vec::<u8>::new());
+}
+
+#[test]
+fn limits() {
+    let ((send1, _recv1), (_send2, recv2)) = shm::pair(128).unwrap();
+    assert!(shm::pair(100).is_err());
+    assert_eq!(
+        send1.send(&[0; 125], None),
+        Err(TransportError::FrameTooLarge {
+            size: 125,
+            max: 124
+        })
+    );
+    send1.send(&[1; 124], None).unwrap();
+    assert_eq!(
+        send1.send(&[2], Some(Duration::from_millis(50))),
+        Err(TransportError::TimeOut)
+    );
+    assert_eq!(recv2.recv(None).unwrap(), vec![1; 124]);
+    assert_eq!(
+        recv2.recv(Some(Duration::from_millis(50))),
+        Err(TransportError::TimeOut)
+    );
+}
+
+#[test]
+fn blocking() {
+    let ((send1, _recv1), (_send2, recv2)) = shm::pair(1024).unwrap();
+    let receiver = thread::spawn(move || {
+        (0..1000u32)
+            .map(|_| recv2.recv(None).unwrap())
+            .collect::<Vec<_>>()
+    });
+    for i in 0..1000u32 {
+        send1.send(&i.to_le_bytes().repeat(20), None).unwrap();
+    }
+    for (i, data) in receiver.join().unwrap().into_iter().enumerate() {
+        assert_eq!(data, (i as u32).to_le_bytes().repeat(20));
+    }
+}
+
+#[test]
+fn termination() {
+    let ((send1, recv1), (_send2, _recv2)) = shm::pair(128).unwrap();
+    let terminator = recv1.create_terminator();
+    let receiver = thread::spawn(move || recv1.recv(None));
+    thread::sleep(Duration::from_millis(50));
+    terminator.terminate();
+    assert_eq!(receiver.join().unwrap(), Err(TransportError::Termination));
+
+    send1.send(&[0; 124], None).unwrap();
+    let terminator = send1.create_terminator();
+    let sender = thread::spawn(move || send1.send(&[0], None));
+    thread::sleep(Duration::from_millis(50));
+    terminator.terminate();
+    assert_eq!(sender.join().unwrap(), Err(TransportError::Termination));
+}
+
+#[test]
+fn closed() {
+    let ((send1, recv1), (send2, recv2)) = shm::pair(128).unwrap();
+    send2.send(b"last", None).unwrap();
+    drop(send2);
+    drop(recv2);
+    assert_eq!(recv1.recv(None).unwrap(), b"last");
+    assert_eq!(recv1.recv(None), Err(TransportError::Custom));
+    assert_eq!(send1.send(b"hello", None), Err(TransportError::Custom));
+}
+
+#[test]
+fn file() {
+    let path = std::env::temp_dir().join(format!("rto-shm-test-{}", std::process::id()));
+    let (send1, recv1) = shm::create(&path, 4096).unwrap();
+    let (send2, recv2) = shm::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    send1.send(b"hello", None).unwrap();
+    assert_eq!(recv2.recv(None).unwrap(), b"hello");
+    send2.send(b"world", None).unwrap();
+    assert_eq!(recv1.recv(None).unwrap(), b"world");
+}
+
+/// Overwrites the file behind the mapping, as a misbehaving peer would do.
+fn corrupt(path: &Path, offset: u64, bytes: &[u8]) {
+    let file = OpenOptions::new().write(true).open(path).unwrap();
+    file.write_all_at(bytes, offset).unwrap();
+}
+
+#[test]
+fn corrupted() {
+    // The first ring, from the creator to the opener, has its `head` at 64, `tail` at 72, and data from 128.
+    let cases: [(u64, &[u8]); 3] = [
+        // The tail is ahead of the head.
+        (72, &1000u64.to_le_bytes()),
+        // More than the capacity is in the ring.
+        (64, &1000u64.to_le_bytes()),
+        // The length of the frame exceeds what is in the ring.
+        (128, &100u32.to_le_bytes()),
+    ];
+    for (offset, bytes) in cases.iter() {
+        let path = std::env::temp_dir().join(format!("rto-shm-corrupted-{}", std::process::id()));
+        let (send1, _recv1) = shm::create(&path, 128).unwrap();
+        let (_send2, recv2) = shm::open(&path).unwrap();
+        send1.send(&[0; 8], None).unwrap();
+        corrupt(&path, *offset, bytes);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(recv2.recv(None), Err(TransportError::Custom));
+        // The ring is closed for both sides.
+        assert_eq!(recv2.recv(None), Err(TransportError::Custom));
+        assert_eq!(send1.send(b"hello", None), Err(TransportError::Custom));
+    }
+}
+
+#[test]
+fn context() {
+    let ((send1, recv1), (send2, recv2)) = shm::pair(shm::DEFAULT_CAPACITY).unwrap();
+    crate::adder::check_context(send1, recv1, send2, recv2);
+}
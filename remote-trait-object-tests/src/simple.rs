@@ -37,12 +37,12 @@ impl PizzaStore for SomePizzaStore {
 
 #[test]
 fn test() {
-    let crate::transport::TransportEnds {
+    let remote_trait_object::transport::intra::TransportEnds {
         recv1,
         send1,
         recv2,
         send2,
-    } = crate::transport::create();
+    } = remote_trait_object::transport::intra::create();
 
     let _context_pizza_town = Context::with_initial_service_export(
         Config::default_setup(),
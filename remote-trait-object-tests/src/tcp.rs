@@ -0,0 +1,104 @@
+use remote_trait_object::transport::tcp;
+use remote_trait_object::transport::{TransportError, TransportRecv, TransportSend};
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+fn loopback() -> ((tcp::TcpSend, tcp::TcpRecv), (tcp::TcpSend, tcp::TcpRecv)) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = thread::spawn(move || tcp::connect(addr).unwrap());
+    let server = tcp::accept(&listener).unwrap();
+    (server, client.join().unwrap())
+}
+
+#[test]
+fn frames() {
+    let ((send1, _recv1), (_send2, recv2)) = loopback();
+    let large = vec![7u8; 1024 * 1024];
+    send1.send(b"hello", None).unwrap();
+    send1.send(&[], None).unwrap();
+    send1.send(&large, None).unwrap();
+    assert_eq!(recv2.recv(None).unwrap(), b"hello");
+    assert_eq!(recv2.recv(None).unwrap(), Vec::<u8>::new());
+    assert_eq!(recv2.recv(None).unwrap(), large);
+}
+
+#[test]
+fn max_frame_size() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut raw = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (stream, _) = listener.accept().unwrap();
+    let (send, recv) = tcp::split_with_max_frame_size(stream, 1024).unwrap();
+
+    assert_eq!(
+        send.send(&[0; 1025], None),
+        Err(TransportError::FrameTooLarge {
+            size: 1025,
+            max: 1024
+        })
+    );
+    send.send(&[0; 1024], None).unwrap();
+    // The peer can't make it allocate more than the maximum.
+    raw.write_all(&u32::MAX.to_le_bytes()).unwrap();
+    assert_eq!(
+        recv.recv(None),
+        Err(TransportError::FrameTooLarge {
+            size: u32::MAX as usize,
+            max: 1024
+        })
+    );
+}
+
+#[test]
+fn timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut raw = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (_send, recv) = tcp::accept(&listener).unwrap();
+
+    assert_eq!(
+        recv.recv(Some(Duration::from_millis(50))),
+        Err(TransportError::TimeOut)
+    );
+    // A frame that is cut by a timeout is continued in the next recv().
+    raw.write_all(&[3, 0, 0, 0, 1]).unwrap();
+    assert_eq!(
+        recv.recv(Some(Duration::from_millis(50))),
+        Err(TransportError::TimeOut)
+    );
+    raw.write_all(&[2, 3]).unwrap();
+    assert_eq!(
+        recv.recv(Some(Duration::from_millis(1000))).unwrap(),
+        vec![1, 2, 3]
+    );
+}
+
+#[test]
+fn termination() {
+    let ((_send1, recv1), (send2, recv2)) = loopback();
+    let terminator = recv1.create_terminator();
+    let receiver = thread::spawn(move || recv1.recv(None));
+    thread::sleep(Duration::from_millis(50));
+    terminator.terminate();
+    assert_eq!(receiver.join().unwrap(), Err(TransportError::Termination));
+
+    send2.create_terminator().terminate();
+    assert_eq!(send2.send(b"hello", None), Err(TransportError::Termination));
+    drop(recv2);
+}
+
+#[test]
+fn closed() {
+    let ((send1, recv1), (send2, recv2)) = loopback();
+    drop(send2);
+    drop(recv2);
+    assert_eq!(recv1.recv(None), Err(TransportError::Custom));
+    drop(send1);
+}
+
+#[test]
+fn context() {
+    let ((send1, recv1), (send2, recv2)) = loopback();
+    crate::adder::check_context(send1, recv1, send2, recv2);
+}
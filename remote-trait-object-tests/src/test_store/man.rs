@@ -20,12 +20,12 @@ impl CreditCard for MyCreditCard {
 impl Service for MyCreditCard {}
 
 fn test_runner(f: impl Fn(Box<dyn Store>)) {
-    let crate::transport::TransportEnds {
+    let remote_trait_object::transport::intra::TransportEnds {
         recv1,
         send1,
         recv2,
         send2,
-    } = crate::transport::create();
+    } = remote_trait_object::transport::intra::create();
     let store_runner = std::thread::Builder::new()
         .name("Store Runner".to_owned())
         .spawn(move || run_store((send2, recv2)))
@@ -42,6 +42,31 @@ fn test_runner(f: impl Fn(Box<dyn Store>)) {
     store_runner.join().unwrap();
 }
 
+pub fn massive_no_export(n: usize) {
+    fn f(n: usize, store: Box<dyn Store>) {
+        for _ in 0..n {
+            assert_eq!(
+                store.order_pizza(Pizza::Pepperoni, 13),
+                "Here's a delicious pepperoni pizza"
+            );
+        }
+    }
+    test_runner(|store: Box<dyn Store>| f(n, store));
+}
+
+pub fn massive_with_export(n: usize) {
+    fn f(n: usize, store: Box<dyn Store>) {
+        for _ in 0..n {
+            let card = Box::new(MyCreditCard { balance: 13 }) as Box<dyn CreditCard>;
+            assert_eq!(
+                store.order_pizza_credit_card(Pizza::Pepperoni, ServiceRef::create_export(card)),
+                "Here's a delicious pepperoni pizza"
+            );
+        }
+    }
+    test_runner(|store: Box<dyn Store>| f(n, store));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -133,12 +158,12 @@ mod tests {
 
     #[test]
     fn drop_service_which_holds_remote() {
-        let crate::transport::TransportEnds {
+        let remote_trait_object::transport::intra::TransportEnds {
             recv1,
             send1,
             recv2,
             send2,
-        } = crate::transport::create();
+        } = remote_trait_object::transport::intra::create();
 
         let store_runner = std::thread::Builder::new()
             .name("Store Runner".to_owned())
@@ -162,12 +187,12 @@ mod tests {
 
     #[test]
     fn credit_card_cast() {
-        let crate::transport::TransportEnds {
+        let remote_trait_object::transport::intra::TransportEnds {
             recv1,
             send1,
             recv2,
             send2,
-        } = crate::transport::create();
+        } = remote_trait_object::transport::intra::create();
         let store_runner = std::thread::Builder::new()
             .name("Store Runner".to_owned())
             .spawn(move || run_store((send2, recv2)))
@@ -215,28 +240,3 @@ mod tests {
         );
     }
 }
-
-pub fn massive_no_export(n: usize) {
-    fn f(n: usize, store: Box<dyn Store>) {
-        for _ in 0..n {
-            assert_eq!(
-                store.order_pizza(Pizza::Pepperoni, 13),
-                "Here's a delicious pepperoni pizza"
-            );
-        }
-    }
-    test_runner(|store: Box<dyn Store>| f(n, store));
-}
-
-pub fn massive_with_export(n: usize) {
-    fn f(n: usize, store: Box<dyn Store>) {
-        for _ in 0..n {
-            let card = Box::new(MyCreditCard { balance: 13 }) as Box<dyn CreditCard>;
-            assert_eq!(
-                store.order_pizza_credit_card(Pizza::Pepperoni, ServiceRef::create_export(card)),
-                "Here's a delicious pepperoni pizza"
-            );
-        }
-    }
-    test_runner(|store: Box<dyn Store>| f(n, store));
-}
@@ -1,5 +1,5 @@
 use super::types::*;
-use crate::transport::{IntraRecv, IntraSend};
+use remote_trait_object::transport::intra::{IntraRecv, IntraSend};
 use remote_trait_object::*;
 
 struct MyPizzaStore {
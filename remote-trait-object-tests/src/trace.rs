@@ -0,0 +1,161 @@
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use remote_trait_object::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+#[service]
+pub trait Cafe: Service {
+    /// Pays with the wallet, and returns the trace id that the cafe has seen.
+    fn order(&self, wallet: ServiceRef<dyn Wallet>) -> String;
+}
+
+#[service]
+pub trait Wallet: Service {
+    fn pay(&self, amount: u64);
+}
+
+struct SimpleCafe;
+
+impl Service for SimpleCafe {}
+
+impl Cafe for SimpleCafe {
+    fn order(&self, wallet: ServiceRef<dyn Wallet>) -> String {
+        let wallet: Box<dyn Wallet> = wallet.unwrap_import().into_proxy();
+        wallet.pay(5);
+        format!("{:032x}", TraceContext::current().unwrap().trace_id())
+    }
+}
+
+struct SimpleWallet {
+    seen: Arc<Mutex<Option<TraceContext>>>,
+}
+
+impl Service for SimpleWallet {}
+
+impl Wallet for SimpleWallet {
+    fn pay(&self, _amount: u64) {
+        *self.seen.lock() = TraceContext::current();
+    }
+}
+
+/// The fields of a span, which are formatted.
+type Fields = HashMap<&'static str, String>;
+
+#[derive(Default)]
+struct FieldVisitor(Fields);
+
+impl tracing::field::Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        self.0.insert(field.name(), value.to_owned());
+    }
+}
+
+/// A subscriber that records every span, for all tests in this process.
+struct SpanRecorder {
+    next_id: AtomicU64,
+}
+
+static SPANS: Lazy<Mutex<Vec<(&'static str, Fields)>>> = Lazy::new(Default::default);
+
+impl Subscriber for SpanRecorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut visitor = FieldVisitor::default();
+        span.record(&mut visitor);
+        SPANS.lock().push((span.metadata().name(), visitor.0));
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+fn spans_of(trace_id: &str) -> Vec<(&'static str, Fields)> {
+    SPANS
+        .lock()
+        .iter()
+        .filter(|(_, fields)| fields.get("trace_id").map(String::as_str) == Some(trace_id))
+        .cloned()
+        .collect()
+}
+
+fn init_subscriber() {
+    static SUBSCRIBER: std::sync::Once = std::sync::Once::new();
+    SUBSCRIBER.call_once(|| {
+        tracing::subscriber::set_global_default(SpanRecorder {
+            next_id: AtomicU64::new(1),
+        })
+        .unwrap()
+    });
+}
+
+#[test]
+fn nested_calls() {
+    init_subscriber();
+    let (ctx1, ctx2, cafe): (_, _, Box<dyn Cafe>) =
+        crate::connect(Box::new(SimpleCafe) as Box<dyn Cafe>);
+    let seen = Arc::new(Mutex::new(None));
+    let wallet = Box::new(SimpleWallet {
+        seen: Arc::clone(&seen),
+    }) as Box<dyn Wallet>;
+    let trace_id = cafe.order(ServiceRef::create_export(wallet));
+    assert_eq!(
+        format!("{:032x}", seen.lock().unwrap().trace_id()),
+        trace_id
+    );
+    // Nobody is handling a call here.
+    assert_eq!(TraceContext::current(), None);
+
+    // The callback is made while the cafe handles the order, so all of them form a tree.
+    let spans = spans_of(&trace_id);
+    let names: Vec<_> = spans
+        .iter()
+        .map(|(name, fields)| (*name, fields["otel.name"].as_str()))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("remote_call", "Cafe::order"),
+            ("handle_call", "Cafe::order"),
+            ("remote_call", "Wallet::pay"),
+            ("handle_call", "Wallet::pay"),
+        ]
+    );
+    assert!(!spans[0].1.contains_key("parent_span_id"));
+    for pair in spans.windows(2) {
+        assert_eq!(pair[1].1["parent_span_id"], pair[0].1["span_id"]);
+    }
+    assert_eq!(
+        format!("{:016x}", seen.lock().unwrap().span_id()),
+        spans[3].1["span_id"]
+    );
+
+    // Another call starts another trace.
+    let wallet = Box::new(SimpleWallet {
+        seen: Arc::clone(&seen),
+    }) as Box<dyn Wallet>;
+    assert_ne!(cafe.order(ServiceRef::create_export(wallet)), trace_id);
+
+    drop(cafe);
+    drop(ctx2);
+    drop(ctx1);
+}
@@ -0,0 +1,99 @@
+use crate::adder::{create_adder, Adder};
+use remote_trait_object::transport::unix;
+use remote_trait_object::transport::{TransportError, TransportRecv, TransportSend};
+use remote_trait_object::*;
+use std::os::unix::io::{AsRawFd, IntoRawFd};
+use std::os::unix::net::UnixStream;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn socketpair() {
+    let ((send1, recv1), (send2, recv2)) = unix::pair().unwrap();
+    send1.send(b"hello", None).unwrap();
+    assert_eq!(recv2.recv(None).unwrap(), b"hello");
+    send2.send(&[], None).unwrap();
+    assert_eq!(recv1.recv(None).unwrap(), Vec::<u8>::new());
+    assert_eq!(
+        recv1.recv(Some(Duration::from_millis(50))),
+        Err(TransportError::TimeOut)
+    );
+}
+
+#[test]
+fn raw_fd() {
+    let (a, b) = UnixStream::pair().unwrap();
+    let (send1, _recv1) = unix::split(a).unwrap();
+    let (_send2, recv2) = unsafe { unix::from_fd_arg(&b.into_raw_fd().to_string()).unwrap() };
+    send1.send(b"hello", None).unwrap();
+    assert_eq!(recv2.recv(None).unwrap(), b"hello");
+
+    assert!(unsafe { unix::from_fd_arg("not a number") }.is_err());
+    assert!(unsafe { unix::from_env("REMOTE_TRAIT_OBJECT_TESTS_NO_SUCH_VAR") }.is_err());
+}
+
+#[test]
+fn max_frame_size() {
+    let (a, b) = UnixStream::pair().unwrap();
+    let (send1, _recv1) = unix::split(a).unwrap();
+    let (_send2, recv2) = unix::split_with_max_frame_size(b, 1024).unwrap();
+    send1.send(&[0; 1025], None).unwrap();
+    assert_eq!(
+        recv2.recv(None),
+        Err(TransportError::FrameTooLarge {
+            size: 1025,
+            max: 1024
+        })
+    );
+}
+
+#[test]
+fn termination() {
+    let ((_send1, recv1), (_send2, _recv2)) = unix::pair().unwrap();
+    let terminator = recv1.create_terminator();
+    let receiver = thread::spawn(move || recv1.recv(None));
+    thread::sleep(Duration::from_millis(50));
+    terminator.terminate();
+    assert_eq!(receiver.join().unwrap(), Err(TransportError::Termination));
+}
+
+#[test]
+fn context() {
+    let ((send1, recv1), (send2, recv2)) = unix::pair().unwrap();
+    crate::adder::check_context(send1, recv1, send2, recv2);
+}
+
+/// The body of the child process of `child_process`, which does nothing when run as an ordinary test.
+#[test]
+fn child() {
+    let (send, recv) = match unsafe { unix::from_env(unix::FD_ENV_VAR) } {
+        Ok(x) => x,
+        Err(_) => return,
+    };
+    let ctx =
+        Context::with_initial_service_export(Config::default_setup(), send, recv, create_adder());
+    assert!(ctx.wait(None).is_ok());
+}
+
+#[test]
+fn child_process() {
+    let (host_end, child_end) = UnixStream::pair().unwrap();
+    unix::set_inheritable(&child_end).unwrap();
+    let mut child = Command::new(std::env::current_exe().unwrap())
+        .args(["unix::child", "--exact", "--test-threads=1"])
+        .stdout(Stdio::null())
+        .env(unix::FD_ENV_VAR, child_end.as_raw_fd().to_string())
+        .spawn()
+        .unwrap();
+    drop(child_end);
+
+    let (send, recv) = unix::split(host_end).unwrap();
+    let (ctx, adder): (_, ServiceToImport<dyn Adder>) =
+        Context::with_initial_service_import(Config::default_setup(), send, recv);
+    let adder: Box<dyn Adder> = adder.into_proxy();
+    assert_eq!(adder.add(1, 2), 3);
+    drop(adder);
+    drop(ctx);
+    assert!(child.wait().unwrap().success());
+}
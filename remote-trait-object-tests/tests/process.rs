@@ -0,0 +1,50 @@
+use remote_trait_object::transport::process;
+use remote_trait_object::transport::{TransportError, TransportRecv, TransportSend};
+use remote_trait_object::*;
+use remote_trait_object_tests::process_child::Calculator;
+use std::process::Command;
+use std::time::Duration;
+
+fn child() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_process-child"))
+}
+
+#[test]
+fn call() {
+    let (ctx, calculator, mut child): (_, ServiceToImport<dyn Calculator>, _) =
+        process::spawn(&mut child(), Config::default_setup()).unwrap();
+    let calculator: Box<dyn Calculator> = calculator.into_proxy();
+    assert_eq!(calculator.add(1, 2), 3);
+    assert_eq!(calculator.add(-1, 2), 1);
+    drop(calculator);
+    assert!(!ctx.is_closed());
+    drop(ctx);
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn child_exit() {
+    let (ctx, calculator, mut child): (_, ServiceToImport<dyn Calculator>, _) =
+        process::spawn(&mut child(), Config::default_setup()).unwrap();
+    let calculator: Box<dyn Calculator> = calculator.into_proxy();
+    assert_eq!(calculator.add(1, 2), 3);
+    assert_eq!(calculator.exit(3), Err(RemoteError::Disconnected));
+    assert_eq!(child.wait().unwrap().code(), Some(3));
+
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(ctx.is_closed());
+    assert_eq!(calculator.exit(3), Err(RemoteError::Disconnected));
+    ctx.disable_garbage_collection();
+    drop(calculator);
+    drop(ctx);
+}
+
+#[test]
+fn terminate() {
+    let (mut child, send, recv) = process::spawn_transport(&mut child()).unwrap();
+    recv.create_terminator().terminate();
+    assert_eq!(recv.recv(None), Err(TransportError::Termination));
+    // The child sees its stdin closed, and exits.
+    assert!(child.wait().unwrap().success());
+    assert_eq!(send.send(b"hello", None), Err(TransportError::Custom));
+}
@@ -0,0 +1,656 @@
+use crate::call::CallInfo;
+use crate::cancel::{self, CancellationToken};
+use crate::context::PacketForward;
+use crate::forwarder::{
+    ServiceForwarder, ServiceObjectId, DELETE_REQUEST, INITIAL_SERVICE_OBJECT_ID,
+    META_SERVICE_OBJECT_ID,
+};
+use crate::interceptor::Interceptor;
+use crate::meta_service::{MetaService, MetaServiceImpl};
+use crate::metrics::{Metrics, MetricsRecorder};
+use crate::packet::{
+    Packet, PacketFlags, PacketView, ProtocolError, ProtocolErrorReporter, SlotId,
+};
+use crate::port::server::{create_response, OngoingCalls};
+use crate::port::Port;
+use crate::service::{BoxFuture, DescribeService, Dispatch, RemoteError, ServiceSignature};
+use crate::transport::asynchronous::{AsyncTransportRecv, AsyncTransportSend};
+use crate::transport::multiplex::{Forward, ForwardResult};
+use crate::transport::TransportError;
+use crate::{raw_exchange::*, Config, Service, ServiceToExport, ServiceToImport};
+use crossbeam::channel::{self, RecvTimeoutError};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// Where the response to a call is delivered.
+enum Waiter {
+    Blocking(channel::Sender<Result<Packet, RemoteError>>),
+    Async(oneshot::Sender<Result<Packet, RemoteError>>),
+    /// The caller has given up the call, or never waits for it like a delete request.
+    /// The slot is not reused until the response arrives.
+    Abandoned,
+}
+
+impl Waiter {
+    fn deliver(self, result: Result<Packet, RemoteError>) {
+        let result = result.and_then(|packet| {
+            if packet.view().is_error() {
+                Err(packet.view().error())
+            } else {
+                Ok(packet)
+            }
+        });
+        // The caller might have given up waiting.
+        match self {
+            Waiter::Blocking(send) => drop(send.send(result)),
+            Waiter::Async(send) => drop(send.send(result)),
+            Waiter::Abandoned => {
+                if let Err(err) = result {
+                    debug!("Abandoned call failed: {}", err);
+                }
+            }
+        }
+    }
+}
+
+/// Call slots that are waiting for their responses, and the error that has closed the connection.
+struct Slots {
+    free: Vec<u32>,
+    /// Each call has a unique number, to tell it from a later call that reuses the slot.
+    pending: HashMap<u32, PendingSlot>,
+    next_call: u64,
+    failure: Option<TransportError>,
+}
+
+struct PendingSlot {
+    call: u64,
+    waiter: Waiter,
+    /// The packet that cancels the call.
+    cancel: Packet,
+}
+
+/// The port of an [`AsyncContext`], which is shared by its proxy objects and tasks.
+struct AsyncPort {
+    config: Config,
+    registry: Arc<ServiceForwarder>,
+    slots: Mutex<Slots>,
+    outgoing: mpsc::UnboundedSender<Packet>,
+    protocol_error: ProtocolErrorReporter,
+    /// Calls from the other end that are being handled.
+    ongoing: OngoingCalls,
+    no_drop: AtomicBool,
+    weak_self: Weak<AsyncPort>,
+}
+
+impl fmt::Debug for AsyncPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncPort")
+            .field("name", &self.config.name)
+            .field("registry", &self.registry)
+            .finish()
+    }
+}
+
+/// A pending call made by `AsyncPort::call_async()`, which is cancelled if dropped before the response.
+struct PendingCall {
+    port: Weak<AsyncPort>,
+    slot: u32,
+    call: u64,
+    done: bool,
+}
+
+impl PendingCall {
+    fn finish(&mut self) {
+        self.done = true;
+    }
+}
+
+impl Drop for PendingCall {
+    fn drop(&mut self) {
+        if !self.done {
+            if let Some(port) = self.port.upgrade() {
+                port.abandon(self.slot, self.call);
+            }
+        }
+    }
+}
+
+impl AsyncPort {
+    /// Takes a free slot and queues the request with it, giving it the deadline.
+    /// Returns the slot and the call number.
+    fn start_call(
+        &self,
+        packet: PacketView,
+        waiter: Waiter,
+        deadline: Option<Duration>,
+    ) -> Result<(u32, u64), RemoteError> {
+        if deadline == Some(Duration::from_secs(0)) {
+            return Err(RemoteError::TimeOut);
+        }
+        let mut packet = packet.to_owned();
+        if let Some(deadline) = deadline {
+            packet.set_deadline(deadline);
+        }
+        let (slot, call) = {
+            let mut slots = self.slots.lock();
+            if let Some(err) = slots.failure.clone() {
+                return Err(err.into());
+            }
+            let slot = slots.free.pop().ok_or(RemoteError::TooManyCalls)?;
+            let call = slots.next_call;
+            slots.next_call += 1;
+            packet.set_slot(SlotId::new(slot));
+            let cancel = Packet::new_cancel(packet.view());
+            slots.pending.insert(
+                slot,
+                PendingSlot {
+                    call,
+                    waiter,
+                    cancel,
+                },
+            );
+            (slot, call)
+        };
+        if self.outgoing.send(packet).is_err() {
+            // The context is being dropped.
+            self.complete(slot, Err(RemoteError::Disconnected));
+            return Err(RemoteError::Disconnected);
+        }
+        Ok((slot, call))
+    }
+
+    /// Delivers the response to the slot, which becomes free again.
+    fn complete(&self, slot: u32, result: Result<Packet, RemoteError>) {
+        let pending = {
+            let mut slots = self.slots.lock();
+            let pending = slots.pending.remove(&slot);
+            if pending.is_some() {
+                slots.free.push(slot);
+            }
+            pending
+        };
+        match pending {
+            Some(pending) => pending.waiter.deliver(result),
+            None => self.protocol_error.report(ProtocolError::InvalidSlot(slot)),
+        }
+    }
+
+    /// Cancels the call, whose slot is reused once the response arrives.
+    fn abandon(&self, slot: u32, call: u64) {
+        let mut slots = self.slots.lock();
+        if let Some(pending) = slots.pending.get_mut(&slot) {
+            if pending.call == call {
+                debug!("{} is cancelled", SlotId::new(slot));
+                pending.waiter = Waiter::Abandoned;
+                // This is queued under the lock, so that it goes out before any request of the next call on the slot.
+                // The context may be being dropped.
+                let _ = self.outgoing.send(pending.cancel.view().to_owned());
+            }
+        }
+    }
+
+    /// Fails all pending calls, and makes later calls fail immediately.
+    fn close(&self, err: TransportError) {
+        let pending = {
+            let mut slots = self.slots.lock();
+            if slots.failure.is_none() {
+                slots.failure = Some(err.clone());
+            }
+            std::mem::take(&mut slots.pending)
+        };
+        if err != TransportError::Termination {
+            debug!("Connection is closed: {:?}", err);
+        }
+        for (_, pending) in pending {
+            pending.waiter.deliver(Err(err.clone().into()));
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        match &self.slots.lock().failure {
+            Some(err) => *err != TransportError::Termination,
+            None => false,
+        }
+    }
+
+    fn handle_request(&self, request: Packet, call: CallInfo) {
+        let token = call.cancellation_token().clone();
+        let result = {
+            let _guard = call.enter();
+            self.registry.forward_and_call(request.view())
+        };
+        if request.view().is_oneway() {
+            // Nobody waits for the response.
+            if let Err(err) = result {
+                warn!("{} failed: {}", request, err);
+            }
+            return;
+        }
+        if !self.ongoing.finish(request.view(), &token) {
+            debug!("Result of the cancelled {} is discarded", request);
+            return;
+        }
+        let response = create_response(request.view(), result);
+        // The connection may have been closed while handling it.
+        let _ = self.outgoing.send(response);
+    }
+}
+
+impl Port for AsyncPort {
+    fn call(&self, packet: PacketView) -> Result<Packet, RemoteError> {
+        let (send, recv) = channel::bounded(1);
+        let timeout = cancel::inherited_timeout(self.config.call_timeout);
+        let (slot, call) = self.start_call(packet, Waiter::Blocking(send), timeout)?;
+        if let Some(timeout) = timeout {
+            match recv.recv_timeout(timeout) {
+                Ok(result) => result,
+                Err(RecvTimeoutError::Timeout) => {
+                    self.abandon(slot, call);
+                    Err(RemoteError::TimeOut)
+                }
+                Err(RecvTimeoutError::Disconnected) => Err(RemoteError::Disconnected),
+            }
+        } else {
+            recv.recv().unwrap_or(Err(RemoteError::Disconnected))
+        }
+    }
+
+    fn call_async(&self, packet: PacketView) -> BoxFuture<'static, Result<Packet, RemoteError>> {
+        let (send, recv) = oneshot::channel();
+        let deadline = cancel::inherited_timeout(None);
+        let (slot, call) = match self.start_call(packet, Waiter::Async(send), deadline) {
+            Ok(x) => x,
+            Err(err) => return Box::pin(std::future::ready(Err(err))),
+        };
+        let mut pending = PendingCall {
+            port: Weak::clone(&self.weak_self),
+            slot,
+            call,
+            done: false,
+        };
+        Box::pin(async move {
+            let result = recv.await.unwrap_or(Err(RemoteError::Disconnected));
+            pending.finish();
+            result
+        })
+    }
+
+    fn send_oneway(&self, packet: PacketView) -> Result<(), RemoteError> {
+        if let Some(err) = self.slots.lock().failure.clone() {
+            return Err(err.into());
+        }
+        let mut packet = packet.to_owned();
+        packet.insert_flags(PacketFlags::ONEWAY);
+        // The context is being dropped if it fails.
+        self.outgoing
+            .send(packet)
+            .map_err(|_| RemoteError::Disconnected)
+    }
+
+    fn client_interceptors(&self) -> &[Arc<dyn Interceptor>] {
+        &self.config.client_interceptors
+    }
+
+    fn metrics(&self) -> Option<Arc<MetricsRecorder>> {
+        Some(Arc::clone(self.registry.metrics()))
+    }
+
+    fn delete_request(&self, id: ServiceObjectId) {
+        if self.no_drop.load(Ordering::SeqCst) {
+            return;
+        }
+        // Nobody waits for the response, so that dropping a proxy object never blocks a task.
+        let packet = Packet::new_request(id, DELETE_REQUEST, &[]);
+        if let Err(err) = self.start_call(packet.view(), Waiter::Abandoned, None) {
+            warn!("Failed to request delete of {}: {}", id, err);
+        }
+    }
+
+    fn register_service(&self, service_object: Arc<dyn Dispatch>) -> HandleToExchange {
+        HandleToExchange(self.registry.register_service_object(service_object))
+    }
+
+    fn registry(&self) -> Option<Arc<ServiceForwarder>> {
+        Some(Arc::clone(&self.registry))
+    }
+}
+
+async fn receive<R: AsyncTransportRecv>(
+    port: &Arc<AsyncPort>,
+    transport_recv: &mut R,
+    initial_service: ServiceSignature,
+) -> TransportError {
+    // The first packet must be a handshake, so that we never misinterpret packets of another protocol version.
+    let handshake = match transport_recv.recv().await {
+        Err(err) => return err,
+        Ok(data) => data,
+    };
+    if let Err(reason) = PacketView::new(&handshake).check_handshake(&initial_service) {
+        port.protocol_error.report(ProtocolError::Handshake(reason));
+        return TransportError::Custom;
+    }
+
+    loop {
+        let message = match transport_recv.recv().await {
+            Err(err) => return err,
+            Ok(data) => data,
+        };
+        if let Err(err) = PacketView::validate(&message) {
+            port.protocol_error.report(err);
+            continue;
+        }
+        let packet = Packet::new_from_buffer(message);
+        trace!("Receive message in async context {}", packet);
+        match PacketForward::forward(&packet.view()) {
+            ForwardResult::Request => {
+                // This must be done here, not to miss a cancellation that follows right after.
+                let token = if packet.view().is_oneway() {
+                    CancellationToken::new(None)
+                } else {
+                    port.ongoing.start(packet.view())
+                };
+                let call = CallInfo::new(&port.config.name, packet.view(), token);
+                // A service object may block, so it is called on a thread for blocking operations.
+                let port = Arc::clone(port);
+                tokio::task::spawn_blocking(move || port.handle_request(packet, call));
+            }
+            ForwardResult::Cancel => {
+                if let Some(response) = port.ongoing.cancel(packet.view()) {
+                    let _ = port.outgoing.send(response);
+                }
+            }
+            ForwardResult::Response => {
+                let slot = packet.view().slot().as_raw();
+                port.complete(slot, Ok(packet));
+            }
+            ForwardResult::Control => debug!("Unexpected control packet {}", packet),
+        }
+    }
+}
+
+async fn receive_loop<R: AsyncTransportRecv>(
+    port: Arc<AsyncPort>,
+    mut transport_recv: R,
+    initial_service: ServiceSignature,
+) {
+    let err = receive(&port, &mut transport_recv, initial_service).await;
+    port.close(err);
+}
+
+async fn send_loop<S: AsyncTransportSend>(
+    port: Arc<AsyncPort>,
+    transport_send: S,
+    mut outgoing: mpsc::UnboundedReceiver<Packet>,
+) {
+    while let Some(packet) = outgoing.recv().await {
+        let err = match transport_send.send(packet.buffer()).await {
+            Ok(()) => continue,
+            Err(err) => err,
+        };
+        if packet.view().is_oneway() || packet.view().is_cancel() {
+            warn!("{} couldn't be sent: {:?}", packet, err);
+        } else if !packet.view().flags().contains(PacketFlags::RESPONSE) {
+            // Let the caller know, instead of leaving it waiting for the timeout.
+            port.complete(packet.view().slot().as_raw(), Err(err.into()));
+        } else if let TransportError::FrameTooLarge { .. } = err {
+            warn!("Response of {} couldn't be sent: {:?}", packet, err);
+            let error_packet = create_response(packet.view(), Err(RemoteError::from(err)));
+            let _ = transport_send.send(error_packet.buffer()).await;
+        }
+    }
+}
+
+/// One end of a `remote-trait-object` connection, which runs on a [tokio](https://tokio.rs) runtime.
+///
+/// It works just like [`Context`], but over an [`AsyncTransportSend`] and an [`AsyncTransportRecv`].
+/// Instead of spawning its own threads, it receives and sends packets in two tasks,
+/// and handles each incoming call with `tokio::task::spawn_blocking()`, since service objects may block.
+/// Thus [`Config::thread_pool`] is not used.
+///
+/// Both ends don't have to be the same kind. An `AsyncContext` can talk to a [`Context`] over a compatible transport.
+///
+/// Proxy objects imported from it can be used both in and out of the runtime.
+/// Prefer `async fn` methods in a task, since a blocking method blocks the thread that runs the task.
+/// The differences from [`Context`] are
+/// - A call fails with [`RemoteError::TooManyCalls`] immediately if all [`Config::call_slots`] are in use.
+/// - Dropping a proxy object doesn't wait for the response to its _delete request_.
+///
+/// All constructors must be called within a tokio runtime, which keeps running the tasks.
+///
+/// [`Context`]: ./struct.Context.html
+/// [`AsyncTransportSend`]: ./transport/asynchronous/trait.AsyncTransportSend.html
+/// [`AsyncTransportRecv`]: ./transport/asynchronous/trait.AsyncTransportRecv.html
+/// [`Config::thread_pool`]: ./struct.Config.html#structfield.thread_pool
+/// [`Config::call_slots`]: ./struct.Config.html#structfield.call_slots
+/// [`RemoteError::TooManyCalls`]: ./enum.RemoteError.html#variant.TooManyCalls
+pub struct AsyncContext {
+    port: Arc<AsyncPort>,
+    receiver: Option<JoinHandle<()>>,
+    sender: JoinHandle<()>,
+    meta_service: Option<Box<dyn MetaService>>,
+    cleaned: bool,
+}
+
+impl fmt::Debug for AsyncContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncContext")
+            .field("config", &self.port.config)
+            .finish()
+    }
+}
+
+impl AsyncContext {
+    /// Creates a new context without any initial services.
+    ///
+    /// See [`Context::new()`](./struct.Context.html#method.new).
+    pub fn new<S: AsyncTransportSend + 'static, R: AsyncTransportRecv + 'static>(
+        config: Config,
+        transport_send: S,
+        transport_recv: R,
+    ) -> Self {
+        let null_to_export = crate::service::create_null_service();
+        let (ctx, _null_to_import): (Self, ServiceToImport<dyn crate::service::NullService>) =
+            Self::with_initial_service(
+                config,
+                transport_send,
+                transport_recv,
+                ServiceToExport::new(null_to_export),
+            );
+        ctx
+    }
+
+    /// Creates a new context only exporting a service, but importing nothing.
+    ///
+    /// See [`Context::with_initial_service_export()`](./struct.Context.html#method.with_initial_service_export).
+    pub fn with_initial_service_export<
+        S: AsyncTransportSend + 'static,
+        R: AsyncTransportRecv + 'static,
+        A: ?Sized + Service,
+    >(
+        config: Config,
+        transport_send: S,
+        transport_recv: R,
+        initial_service: ServiceToExport<A>,
+    ) -> Self {
+        let (ctx, _null_to_import): (Self, ServiceToImport<dyn crate::service::NullService>) =
+            Self::with_initial_service(config, transport_send, transport_recv, initial_service);
+        ctx
+    }
+
+    /// Creates a new context only importing a service, but exporting nothing.
+    ///
+    /// See [`Context::with_initial_service_import()`](./struct.Context.html#method.with_initial_service_import).
+    pub fn with_initial_service_import<
+        S: AsyncTransportSend + 'static,
+        R: AsyncTransportRecv + 'static,
+        B: ?Sized + Service + DescribeService,
+    >(
+        config: Config,
+        transport_send: S,
+        transport_recv: R,
+    ) -> (Self, ServiceToImport<B>) {
+        let null_to_export = crate::service::create_null_service();
+        Self::with_initial_service(
+            config,
+            transport_send,
+            transport_recv,
+            ServiceToExport::new(null_to_export),
+        )
+    }
+
+    /// Creates a new context exchanging two services, one for export and one for import.
+    ///
+    /// See [`Context::with_initial_service()`](./struct.Context.html#method.with_initial_service).
+    pub fn with_initial_service<
+        S: AsyncTransportSend + 'static,
+        R: AsyncTransportRecv + 'static,
+        A: ?Sized + Service,
+        B: ?Sized + Service + DescribeService,
+    >(
+        config: Config,
+        transport_send: S,
+        transport_recv: R,
+        initial_service: ServiceToExport<A>,
+    ) -> (Self, ServiceToImport<B>) {
+        let initial_service = initial_service.get_raw_export();
+        let (outgoing, outgoing_recv) = mpsc::unbounded_channel();
+        // The other end's multiplexer expects this before any other packets.
+        outgoing
+            .send(Packet::new_handshake(
+                initial_service.raw.signature().as_ref(),
+            ))
+            .expect("The receiver is alive");
+
+        let registry = Arc::new(ServiceForwarder::new(
+            config.clone(),
+            (Box::new(MetaServiceImpl::new()) as Box<dyn MetaService>).into_skeleton(),
+            initial_service,
+        ));
+        let port = Arc::new_cyclic(|weak_self| AsyncPort {
+            slots: Mutex::new(Slots {
+                free: (0..config.call_slots as u32).rev().collect(),
+                pending: HashMap::new(),
+                next_call: 0,
+                failure: None,
+            }),
+            config,
+            registry,
+            outgoing,
+            protocol_error: ProtocolErrorReporter::default(),
+            ongoing: OngoingCalls::default(),
+            no_drop: AtomicBool::new(false),
+            weak_self: Weak::clone(weak_self),
+        });
+        let port_weak = Arc::downgrade(&port) as Weak<dyn Port>;
+        port.registry.set_port(Weak::clone(&port_weak));
+
+        let receiver = tokio::spawn(receive_loop(
+            Arc::clone(&port),
+            transport_recv,
+            B::signature(),
+        ));
+        let sender = tokio::spawn(send_loop(Arc::clone(&port), transport_send, outgoing_recv));
+
+        let meta_service = <Box<dyn MetaService> as ImportProxy<dyn MetaService>>::import_proxy(
+            Weak::clone(&port_weak),
+            HandleToExchange(META_SERVICE_OBJECT_ID),
+        );
+        let ctx = AsyncContext {
+            port,
+            receiver: Some(receiver),
+            sender,
+            meta_service: Some(meta_service),
+            cleaned: false,
+        };
+        let initial_service = ServiceToImport::from_raw_import(
+            HandleToExchange(INITIAL_SERVICE_OBJECT_ID),
+            port_weak,
+        );
+        (ctx, initial_service)
+    }
+
+    /// Returns the latest violation of the protocol by the other end, if any.
+    pub fn protocol_error(&self) -> Option<ProtocolError> {
+        self.port.protocol_error.last()
+    }
+
+    /// Returns whether the connection has been closed by the other end, or broken by an error of the transport.
+    pub fn is_closed(&self) -> bool {
+        self.port.is_closed()
+    }
+
+    /// Returns the proxy object of the other end's [`MetaService`].
+    ///
+    /// Its methods block, so they should be called out of the async tasks.
+    /// See [`Context::meta_service()`](./struct.Context.html#method.meta_service).
+    ///
+    /// [`MetaService`]: ./trait.MetaService.html
+    pub fn meta_service(&self) -> &dyn MetaService {
+        self.meta_service
+            .as_deref()
+            .expect("It becomes None only when the context is dropped.")
+    }
+
+    /// Returns a snapshot of the metrics of this context.
+    ///
+    /// See [`Context::metrics()`](./struct.Context.html#method.metrics).
+    pub fn metrics(&self) -> Metrics {
+        let mut metrics = self.port.registry.metrics().snapshot();
+        metrics.exported_objects = self.port.registry.exported_objects();
+        metrics.call_slots_in_use = self.port.slots.lock().pending.len();
+        metrics
+    }
+
+    /// Clears all service objects in its registry.
+    ///
+    /// See [`Context::clear_service_registry()`](./struct.Context.html#method.clear_service_registry).
+    pub fn clear_service_registry(&mut self) {
+        self.port.registry.clear();
+    }
+
+    /// Disables all _delete request_ from this end to the other end.
+    ///
+    /// See [`Context::disable_garbage_collection()`](./struct.Context.html#method.disable_garbage_collection).
+    pub fn disable_garbage_collection(&self) {
+        self.port.no_drop.store(true, Ordering::SeqCst);
+    }
+
+    /// Waits until the transport is closed.
+    pub async fn wait(mut self) {
+        let receiver = self
+            .receiver
+            .take()
+            .expect("It becomes None only when the context is dropped.");
+        // It never panics nor is cancelled unless the context is dropped.
+        let _ = receiver.await;
+        self.clean();
+    }
+
+    fn clean(&mut self) {
+        self.port.no_drop.store(true, Ordering::SeqCst);
+        // Registered services might hold proxy objects, which refer to this context's port.
+        self.port.registry.clear();
+        drop(self.meta_service.take());
+        self.cleaned = true;
+    }
+}
+
+impl Drop for AsyncContext {
+    /// This will delete all service objects after calling `disable_garbage_collection()` internally.
+    fn drop(&mut self) {
+        if let Some(receiver) = self.receiver.take() {
+            receiver.abort();
+        }
+        if !self.cleaned {
+            self.clean();
+        }
+        self.sender.abort();
+        self.port.close(TransportError::Termination);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::cancel::CancellationToken;
+use crate::forwarder::ServiceObjectId;
+use crate::packet::PacketView;
+use crate::service::MethodId;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Out-of-band data that is carried with a remote call, like a trace id or an auth token.
+pub type CallMetadata = BTreeMap<String, String>;
+
+thread_local!(static CURRENT: RefCell<Option<Arc<CallInfo>>> = const { RefCell::new(None) });
+thread_local!(static OUTGOING: RefCell<CallMetadata> = const { RefCell::new(BTreeMap::new()) });
+
+/// Information about the remote call that the current thread is handling, which is given by [`current_call()`].
+///
+/// [`current_call()`]: ./fn.current_call.html
+#[derive(Debug)]
+pub struct CallInfo {
+    context_name: String,
+    object_id: ServiceObjectId,
+    method: MethodId,
+    metadata: CallMetadata,
+    cancellation_token: CancellationToken,
+    #[cfg(feature = "tracing")]
+    trace_parent: Option<crate::trace::TraceContext>,
+}
+
+impl CallInfo {
+    pub(crate) fn new(
+        context_name: &str,
+        request: PacketView,
+        cancellation_token: CancellationToken,
+    ) -> Self {
+        #[allow(unused_mut)]
+        let mut metadata = request.metadata().unwrap_or_default();
+        CallInfo {
+            context_name: context_name.to_owned(),
+            object_id: request.object_id(),
+            method: request.method(),
+            #[cfg(feature = "tracing")]
+            trace_parent: crate::trace::extract(&mut metadata),
+            metadata,
+            cancellation_token,
+        }
+    }
+
+    /// The name of the context that has received the call, which is [`Config::name`].
+    ///
+    /// [`Config::name`]: ./struct.Config.html#structfield.name
+    pub fn context_name(&self) -> &str {
+        &self.context_name
+    }
+
+    /// The id of the service object being called, which is local to the context.
+    pub fn object_id(&self) -> u32 {
+        self.object_id
+    }
+
+    /// The id of the method being called.
+    pub fn method(&self) -> MethodId {
+        self.method
+    }
+
+    /// The metadata that the caller has given with [`with_call_metadata()`].
+    ///
+    /// [`with_call_metadata()`]: ./fn.with_call_metadata.html
+    pub fn metadata(&self) -> &CallMetadata {
+        &self.metadata
+    }
+
+    /// Tells whether the caller still wants the result, as [`CancellationToken::current()`] does.
+    ///
+    /// [`CancellationToken::current()`]: ./struct.CancellationToken.html#method.current
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation_token
+    }
+
+    /// The trace context of the caller's span, which is taken out of the metadata.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn trace_parent(&self) -> Option<crate::trace::TraceContext> {
+        self.trace_parent
+    }
+
+    /// Makes this the call of the current thread, until the guard is dropped.
+    pub(crate) fn enter(self) -> CallGuard {
+        let previous = CURRENT.with(|current| current.borrow_mut().replace(Arc::new(self)));
+        CallGuard { previous }
+    }
+}
+
+pub(crate) struct CallGuard {
+    previous: Option<Arc<CallInfo>>,
+}
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Returns the remote call that the current thread is handling, if any.
+///
+/// A service object can call this in its method, to see who is calling it and with what metadata.
+/// ```ignore
+/// fn withdraw(&self, amount: u64) -> Result<u64, Error> {
+///     let call = current_call().expect("It is called remotely");
+///     let user = call.metadata().get("user").ok_or(Error::Unauthorized)?;
+///     ...
+/// }
+/// ```
+/// It is `None` if the method is called locally, not through a context.
+/// Note that a proxy object called by another thread, like one spawned by the service object, doesn't see this.
+pub fn current_call() -> Option<Arc<CallInfo>> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Runs the closure, giving the metadata to every remote call that it makes on the current thread.
+///
+/// It can be nested, and the inner metadata overrides the outer one with the same key.
+/// ```ignore
+/// let mut metadata = CallMetadata::new();
+/// metadata.insert("user".to_owned(), "alice".to_owned());
+/// let balance = with_call_metadata(metadata, || bank.withdraw(100));
+/// ```
+/// The metadata of the call being handled is not passed on to the calls that the handler makes, unless you do it explicitly,
+/// since it might be something that shouldn't be exposed to others, like an auth token.
+pub fn with_call_metadata<R>(metadata: CallMetadata, f: impl FnOnce() -> R) -> R {
+    struct Restore(CallMetadata);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = std::mem::take(&mut self.0);
+            OUTGOING.with(|outgoing| *outgoing.borrow_mut() = previous);
+        }
+    }
+
+    let _restore = OUTGOING.with(|outgoing| {
+        let mut outgoing = outgoing.borrow_mut();
+        let previous = outgoing.clone();
+        outgoing.extend(metadata);
+        Restore(previous)
+    });
+    f()
+}
+
+/// The metadata for the calls made on the current thread now, if any.
+pub(crate) fn outgoing_metadata() -> Option<CallMetadata> {
+    OUTGOING.with(|outgoing| {
+        let outgoing = outgoing.borrow();
+        if outgoing.is_empty() {
+            None
+        } else {
+            Some(outgoing.clone())
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_metadata() {
+        let entry = |key: &str, value: &str| -> CallMetadata {
+            vec![(key.to_owned(), value.to_owned())]
+                .into_iter()
+                .collect()
+        };
+        assert_eq!(outgoing_metadata(), None);
+        with_call_metadata(entry("a", "1"), || {
+            with_call_metadata(entry("a", "2"), || {
+                with_call_metadata(entry("b", "3"), || {
+                    let metadata = outgoing_metadata().unwrap();
+                    assert_eq!(metadata["a"], "2");
+                    assert_eq!(metadata["b"], "3");
+                })
+            });
+            assert_eq!(outgoing_metadata(), Some(entry("a", "1")));
+        });
+        assert_eq!(outgoing_metadata(), None);
+    }
+}
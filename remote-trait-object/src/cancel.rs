@@ -0,0 +1,154 @@
+use crate::call::current_call;
+use crate::service::RemoteError;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Tells a service object whether the call it is handling is still wanted by the caller.
+///
+/// A call is cancelled when the caller gives up waiting for it, by timing out or dropping its future or [`PendingReply`].
+/// The caller may also give the call a deadline, which comes from [`Config::call_timeout`]
+/// or the deadline of the call that the caller is handling itself.
+/// A long-running method can check this once in a while, and stop early.
+/// ```ignore
+/// fn process(&self, items: Vec<Item>) -> Result<u64, RemoteError> {
+///     let token = CancellationToken::current().expect("It is called remotely");
+///     for item in items {
+///         token.check()?;
+///         self.process_one(item);
+///     }
+///     Ok(items.len() as u64)
+/// }
+/// ```
+/// Calls made by a service object while handling a call inherit its remaining time as their deadline,
+/// so a chain of calls gives up together.
+///
+/// [`PendingReply`]: ./struct.PendingReply.html
+/// [`Config::call_timeout`]: ./struct.Config.html#structfield.call_timeout
+#[derive(Clone)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.cancelled.load(Ordering::SeqCst))
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
+
+impl CancellationToken {
+    pub(crate) fn new(deadline: Option<Instant>) -> Self {
+        CancellationToken {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            deadline,
+        }
+    }
+
+    /// Returns the token of the call that the current thread is handling, from [`current_call()`].
+    ///
+    /// It is `None` if the thread is not handling a remote call.
+    /// A `#[oneway]` call is never cancelled, since nobody waits for it.
+    ///
+    /// [`current_call()`]: ./fn.current_call.html
+    pub fn current() -> Option<Self> {
+        current_call().map(|call| call.cancellation_token().clone())
+    }
+
+    /// Whether the caller has cancelled the call, or its deadline has passed.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst) || self.remaining() == Some(Duration::from_secs(0))
+    }
+
+    /// The time by which the caller expects the response.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// The time left until the deadline, which is zero if it has passed.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Returns `RemoteError::Cancelled` if the call is cancelled, or `RemoteError::TimeOut` if its deadline has passed.
+    pub fn check(&self) -> Result<(), RemoteError> {
+        if self.cancelled.load(Ordering::SeqCst) {
+            Err(RemoteError::Cancelled)
+        } else if self.remaining() == Some(Duration::from_secs(0)) {
+            Err(RemoteError::TimeOut)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst)
+    }
+
+    pub(crate) fn is_same(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+}
+
+/// The time that an outgoing call may take, which is limited by the deadline of the call being handled, if any.
+pub(crate) fn inherited_timeout(timeout: Option<Duration>) -> Option<Duration> {
+    let remaining = CancellationToken::current().and_then(|token| token.remaining());
+    match (timeout, remaining) {
+        (Some(timeout), Some(remaining)) => Some(timeout.min(remaining)),
+        (timeout, remaining) => timeout.or(remaining),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::call::CallInfo;
+    use crate::packet::Packet;
+
+    fn enter(token: &CancellationToken) -> crate::call::CallGuard {
+        let request = Packet::new_request(1, 2, &[]);
+        CallInfo::new("test", request.view(), token.clone()).enter()
+    }
+
+    #[test]
+    fn inherit() {
+        assert_eq!(inherited_timeout(None), None);
+        assert_eq!(
+            inherited_timeout(Some(Duration::from_secs(1))),
+            Some(Duration::from_secs(1))
+        );
+        let token = CancellationToken::new(Some(Instant::now() + Duration::from_secs(10)));
+        {
+            let _guard = enter(&token);
+            assert!(inherited_timeout(None).unwrap() <= Duration::from_secs(10));
+            assert_eq!(
+                inherited_timeout(Some(Duration::from_secs(1))),
+                Some(Duration::from_secs(1))
+            );
+            {
+                let _guard = enter(&CancellationToken::new(None));
+                assert_eq!(inherited_timeout(None), None);
+            }
+            assert!(CancellationToken::current().unwrap().is_same(&token));
+        }
+        assert!(CancellationToken::current().is_none());
+    }
+
+    #[test]
+    fn cancel_and_deadline() {
+        let token = CancellationToken::new(None);
+        assert_eq!(token.check(), Ok(()));
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+        assert_eq!(token.check(), Err(RemoteError::Cancelled));
+
+        let token = CancellationToken::new(Some(Instant::now()));
+        assert!(token.is_cancelled());
+        assert_eq!(token.check(), Err(RemoteError::TimeOut));
+    }
+}
@@ -1,33 +1,17 @@
-use crate::packet::{PacketView, SlotType};
+use crate::interceptor::Interceptor;
+use crate::metrics::Metrics;
+use crate::packet::{Packet, PacketFlags, PacketView, ProtocolError, ProtocolErrorReporter};
 use crate::port::{client::Client, server::Server, BasicPort, Port};
 use crate::transport::multiplex::{self, ForwardResult, MultiplexResult, Multiplexer};
 use crate::transport::{TransportRecv, TransportSend};
-use crate::{raw_exchange::*, Service, ServiceToExport, ServiceToImport};
+use crate::{
+    raw_exchange::*, DescribeService, RemoteError, Service, ServiceToExport, ServiceToImport,
+};
 use parking_lot::Mutex;
 use std::sync::{Arc, Weak};
 use threadpool::ThreadPool;
 
-mod meta_service {
-    use super::*;
-    /// This is required because of macro
-    use crate as remote_trait_object;
-
-    #[remote_trait_object_macro::service]
-    pub trait MetaService: Service {}
-
-    pub struct MetaServiceImpl {}
-
-    impl MetaServiceImpl {
-        pub fn new() -> Self {
-            Self {}
-        }
-    }
-
-    impl Service for MetaServiceImpl {}
-
-    impl MetaService for MetaServiceImpl {}
-}
-use meta_service::{MetaService, MetaServiceImpl};
+use crate::meta_service::{MetaService, MetaServiceImpl};
 
 /// A configuration of a `remote-trait-object` context.
 #[derive(Clone, Debug)]
@@ -48,9 +32,12 @@ pub struct Config {
     /// A timeout for a remote method call.
     ///
     /// All remote method invocations through your proxy object and delete requests (that happens when you drop a proxy object)
-    /// will have this timeout. If it exceeds, it will cause an error.
+    /// will have this timeout. If it exceeds, it will cause an error, and the call is cancelled on the other side.
+    /// The other side sees it as the deadline of the call, from [`CancellationToken`].
     ///
     /// Use `None` for to wait indefinitely.
+    ///
+    /// [`CancellationToken`]: ./struct.CancellationToken.html
     pub call_timeout: Option<std::time::Duration>,
 
     /// A maximum number of services that this context can export.
@@ -62,6 +49,20 @@ pub struct Config {
     /// Size of this pool determines the maximum number of concurrent calls that the context can handle.
     /// Note that this pool is wrapped in `Arc`, which means that it can be possibly shared with other places.
     pub thread_pool: Arc<Mutex<threadpool::ThreadPool>>,
+
+    /// Interceptors that wrap the calls made through the proxy objects imported by this context.
+    ///
+    /// See [`Interceptor`] for the details.
+    ///
+    /// [`Interceptor`]: ./trait.Interceptor.html
+    pub client_interceptors: Vec<Arc<dyn Interceptor>>,
+
+    /// Interceptors that wrap the calls of the service objects exported by this context.
+    ///
+    /// See [`Interceptor`] for the details.
+    ///
+    /// [`Interceptor`]: ./trait.Interceptor.html
+    pub server_interceptors: Vec<Arc<dyn Interceptor>>,
 }
 
 impl Config {
@@ -73,6 +74,8 @@ impl Config {
             call_timeout: Some(std::time::Duration::from_millis(1000)),
 
             thread_pool: Arc::new(Mutex::new(ThreadPool::new(8))),
+            client_interceptors: Vec::new(),
+            server_interceptors: Vec::new(),
         }
     }
 }
@@ -100,6 +103,7 @@ pub struct Context {
     server: Option<Server>,
     port: Option<Arc<BasicPort>>,
     meta_service: Option<Box<dyn MetaService>>,
+    protocol_error: ProtocolErrorReporter,
     cleaned: bool,
 }
 
@@ -166,7 +170,7 @@ impl Context {
     pub fn with_initial_service_import<
         S: TransportSend + 'static,
         R: TransportRecv + 'static,
-        B: ?Sized + Service,
+        B: ?Sized + Service + DescribeService,
     >(
         config: Config,
         transport_send: S,
@@ -190,35 +194,55 @@ impl Context {
     /// The other end's context must be initialized with `with_initial_service()` as well, and
     /// such processes will be symmetric for both.
     ///
+    /// The other end tells the signature of its initial service in the handshake, and it is checked against `B`
+    /// as [`ServiceToImport::cast_service()`] does.
+    /// Since this doesn't wait for the other end, an incompatible one is reported later as a [`ProtocolError::Handshake`],
+    /// closing the connection.
+    ///
+    /// [`ServiceToImport::cast_service()`]: ./struct.ServiceToImport.html#method.cast_service
+    /// [`ProtocolError::Handshake`]: ./enum.ProtocolError.html#variant.Handshake
     /// [`HandleToExchange`]: ../raw_exchange/struct.HandleToExchange.html
     pub fn with_initial_service<
         S: TransportSend + 'static,
         R: TransportRecv + 'static,
         A: ?Sized + Service,
-        B: ?Sized + Service,
+        B: ?Sized + Service + DescribeService,
     >(
         config: Config,
         transport_send: S,
         transport_recv: R,
         initial_service: ServiceToExport<A>,
     ) -> (Self, ServiceToImport<B>) {
+        let protocol_error = ProtocolErrorReporter::default();
         let MultiplexResult {
             multiplexer,
             request_recv,
             response_recv,
-        } = Multiplexer::multiplex::<R, PacketForward>(config.clone(), transport_recv);
+        } = Multiplexer::multiplex::<R, PacketForward>(
+            config.clone(),
+            transport_recv,
+            protocol_error.clone(),
+            B::signature(),
+        );
+        let initial_service = initial_service.get_raw_export();
+        let handshake = Packet::new_handshake(initial_service.raw.signature().as_ref());
         let transport_send = Arc::new(transport_send) as Arc<dyn TransportSend>;
+        // The other end's multiplexer expects this before any other packets.
+        if let Err(err) = transport_send.send(handshake.buffer(), config.call_timeout) {
+            warn!("Failed to send a handshake: {:?}", err);
+        }
 
         let client = Client::new(
             config.clone(),
             Arc::clone(&transport_send),
             Box::new(response_recv),
+            protocol_error.clone(),
         );
         let port = BasicPort::new(
             config.clone(),
             client,
             (Box::new(MetaServiceImpl::new()) as Box<dyn MetaService>).into_skeleton(),
-            initial_service.get_raw_export(),
+            initial_service,
         );
         let server = Server::new(
             config.clone(),
@@ -240,6 +264,7 @@ impl Context {
             server: Some(server),
             port: Some(port),
             meta_service: Some(meta_service),
+            protocol_error,
             cleaned: false,
         };
         let initial_service = ServiceToImport::from_raw_import(initial_handle, port_weak);
@@ -255,6 +280,62 @@ impl Context {
         ) as Weak<dyn Port>
     }
 
+    /// Returns the latest violation of the protocol by the other end, if any.
+    ///
+    /// Such packets are discarded without affecting the other calls, except for a failed handshake that closes the connection.
+    pub fn protocol_error(&self) -> Option<ProtocolError> {
+        self.protocol_error.last()
+    }
+
+    /// Returns whether the connection has been closed by the other end, or broken by an error of the transport.
+    ///
+    /// Remote calls made after that fail with [`RemoteError::Disconnected`] immediately.
+    ///
+    /// [`RemoteError::Disconnected`]: ./enum.RemoteError.html#variant.Disconnected
+    pub fn is_closed(&self) -> bool {
+        self.multiplexer
+            .as_ref()
+            .expect("It becomes None only when the context is dropped.")
+            .is_closed()
+    }
+
+    /// Returns the proxy object of the other end's [`MetaService`], which tells about the other end.
+    ///
+    /// [`MetaService`]: ./trait.MetaService.html
+    pub fn meta_service(&self) -> &dyn MetaService {
+        self.meta_service
+            .as_deref()
+            .expect("It becomes None only when the context is dropped.")
+    }
+
+    /// Measures the round-trip time of a call to the other end, by pinging its [`MetaService`].
+    ///
+    /// [`MetaService`]: ./trait.MetaService.html
+    pub fn ping(&self) -> Result<std::time::Duration, RemoteError> {
+        let start = std::time::Instant::now();
+        self.meta_service().ping()?;
+        Ok(start.elapsed())
+    }
+
+    /// Returns a snapshot of the metrics of this context.
+    ///
+    /// Calls are counted on both sides, by trait and method, along with the gauges of the context.
+    /// See [`Metrics`] for the details.
+    ///
+    /// [`Metrics`]: ./struct.Metrics.html
+    pub fn metrics(&self) -> Metrics {
+        let port = self
+            .port
+            .as_ref()
+            .expect("It becomes None only when the context is dropped.");
+        let registry = port.get_registry();
+        let mut metrics = registry.metrics().snapshot();
+        metrics.exported_objects = registry.exported_objects();
+        metrics.call_slots_in_use = port.call_slots_in_use();
+        metrics.queued_calls = self.config.thread_pool.lock().queued_count();
+        metrics
+    }
+
     /// Clears all service objects in its registry.
     ///
     /// The most usual way of deleting a service object is dropping its proxy object on the client side, and letting it request a delete to the exporter side.
@@ -288,6 +369,7 @@ impl Context {
     /// Use this if you have nothing to do while the connection is working well.
     ///
     /// TODO: We should actually consider `timeout`
+    #[allow(clippy::result_large_err)]
     pub fn wait(mut self, timeout: Option<std::time::Duration>) -> Result<(), Self> {
         if let Err(multiplexer) = self
             .multiplexer
@@ -342,10 +424,16 @@ impl Drop for Context {
 pub struct PacketForward;
 
 impl multiplex::Forward for PacketForward {
-    fn forward(packet: PacketView) -> ForwardResult {
-        match packet.slot().get_type() {
-            SlotType::Request => ForwardResult::Request,
-            SlotType::Response => ForwardResult::Response,
+    fn forward(packet: &PacketView) -> ForwardResult {
+        let flags = packet.flags();
+        if flags.contains(PacketFlags::CANCEL) {
+            ForwardResult::Cancel
+        } else if flags.contains(PacketFlags::CONTROL) {
+            ForwardResult::Control
+        } else if flags.contains(PacketFlags::RESPONSE) {
+            ForwardResult::Response
+        } else {
+            ForwardResult::Request
         }
     }
 }
@@ -1,23 +1,47 @@
+use crate::handoff::{self, HandoffToken};
+use crate::interceptor::{Chain, InterceptedCall, Interceptor};
+use crate::meta_service::{ContextInfo, ExportedService};
+use crate::metrics::{Direction, MetricsRecorder};
 use crate::packet::PacketView;
 use crate::port::{null_weak_port, Handler, Port};
 use crate::raw_exchange::Skeleton;
-use crate::service::Dispatch;
+use crate::service::{Dispatch, RemoteError, ServiceSignature};
+use crate::trace::ServerSpan;
 use crate::Config;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+use std::any::Any;
 use std::collections::{HashMap, VecDeque};
 use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Weak};
 
 pub type ServiceObjectId = u32;
-pub const DELETE_REQUEST: crate::service::MethodId = std::u32::MAX;
+pub const DELETE_REQUEST: crate::service::MethodId = u32::MAX;
+/// Adds a reference to a service object, so that it takes one more delete request to delete it.
+pub const RETAIN_REQUEST: crate::service::MethodId = u32::MAX - 1;
+/// Mints a handoff token for a service object, and returns it.
+pub const MINT_HANDOFF_REQUEST: crate::service::MethodId = u32::MAX - 2;
+/// Registers the service object of a handoff token, which is given as the data, and returns its id.
+pub const REDEEM_HANDOFF_REQUEST: crate::service::MethodId = u32::MAX - 3;
+/// Discards a handoff token, which is given as the data, that won't be redeemed.
+pub const REVOKE_HANDOFF_REQUEST: crate::service::MethodId = u32::MAX - 4;
 pub const META_SERVICE_OBJECT_ID: ServiceObjectId = 0;
 pub const INITIAL_SERVICE_OBJECT_ID: ServiceObjectId = 1;
-pub const NULL_ID: ServiceObjectId = std::u32::MAX;
+pub const NULL_ID: ServiceObjectId = u32::MAX;
 
 pub struct ServiceForwarder {
     service_objects: RwLock<HashMap<ServiceObjectId, Arc<dyn Dispatch>>>,
+    /// Number of the references to each service object besides the first one, which are made by retain requests.
+    /// It is locked after `service_objects`.
+    extra_references: Mutex<HashMap<ServiceObjectId, usize>>,
     available_ids: RwLock<VecDeque<ServiceObjectId>>,
+    /// Identifies this as the minter of handoff tokens, which are discarded when this is cleared.
+    minter: u64,
     port: RwLock<Weak<dyn Port>>,
+    interceptors: Vec<Arc<dyn Interceptor>>,
+    metrics: Arc<MetricsRecorder>,
+    info: ContextInfo,
 }
 
 impl fmt::Debug for ServiceForwarder {
@@ -47,8 +71,16 @@ impl ServiceForwarder {
 
         Self {
             service_objects,
+            extra_references: Default::default(),
             available_ids: RwLock::new(available_ids),
+            minter: {
+                static NEXT_MINTER: AtomicU64 = AtomicU64::new(0);
+                NEXT_MINTER.fetch_add(1, Ordering::Relaxed)
+            },
             port: RwLock::new(null_weak_port()),
+            metrics: Arc::new(MetricsRecorder::new(&config.name)),
+            info: ContextInfo::new(&config),
+            interceptors: config.server_interceptors,
         }
     }
 
@@ -66,36 +98,172 @@ impl ServiceForwarder {
         id
     }
 
-    pub fn forward_and_call(&self, packet: PacketView) -> Vec<u8> {
+    pub fn forward_and_call(&self, packet: PacketView) -> Result<Vec<u8>, RemoteError> {
         let object_id = packet.object_id();
         let method = packet.method();
         let data = packet.data();
 
         if method == DELETE_REQUEST {
-            self.delete(object_id);
-            Vec::new()
+            self.delete(object_id)?;
+            Ok(Vec::new())
+        } else if method == RETAIN_REQUEST {
+            self.retain(object_id)?;
+            Ok(Vec::new())
+        } else if method == MINT_HANDOFF_REQUEST {
+            let service_object = Arc::clone(
+                self.service_objects
+                    .read()
+                    .get(&object_id)
+                    .ok_or(RemoteError::UnknownObject(object_id))?,
+            );
+            Ok(handoff::mint(self.minter, service_object).to_bytes())
+        } else if method == REDEEM_HANDOFF_REQUEST {
+            let token = HandoffToken::from_bytes(data).ok_or(RemoteError::InvalidData)?;
+            let service_object = handoff::redeem(token).ok_or(RemoteError::UnknownHandoffToken)?;
+            Ok(self
+                .register_service_object(service_object)
+                .to_le_bytes()
+                .to_vec())
+        } else if method == REVOKE_HANDOFF_REQUEST {
+            let token = HandoffToken::from_bytes(data).ok_or(RemoteError::InvalidData)?;
+            if handoff::redeem(token).is_some() {
+                return Ok(Vec::new());
+            }
+            // The token might have been minted by the exporter of a forwarded service, which passes the request on.
+            let service_object = Arc::clone(
+                self.service_objects
+                    .read()
+                    .get(&object_id)
+                    .ok_or(RemoteError::UnknownObject(object_id))?,
+            );
+            service_object.dispatch_and_call(method, data)
         } else {
-            crate::service::serde_support::port_thread_local::set_port(self.port.read().clone());
             let handler = Arc::clone(
                 self.service_objects
                     .read()
                     .get(&object_id)
-                    .unwrap_or_else(|| panic!("Fail to find {} from ServiceForwarder", object_id)),
+                    .ok_or(RemoteError::UnknownObject(object_id))?,
+            );
+            let call = InterceptedCall::new(
+                handler.trait_name(),
+                handler.method_name(method),
+                method,
+                object_id,
+                data,
             );
-            let result = handler.dispatch_and_call(method, data);
-            crate::service::serde_support::port_thread_local::remove_port();
+            let _span = ServerSpan::enter(call.trait_name(), call.method_name());
+            let metrics = Some((Arc::clone(&self.metrics), Direction::Incoming));
+            let (chain, entered) = Chain::enter(&self.interceptors, metrics, &call);
+            let result = entered.and_then(|()| self.call(handler.as_ref(), method, data));
+            chain.exit(&call, result.as_deref());
             result
         }
     }
 
+    fn call(
+        &self,
+        handler: &dyn Dispatch,
+        method: crate::service::MethodId,
+        data: &[u8],
+    ) -> Result<Vec<u8>, RemoteError> {
+        crate::service::serde_support::port_thread_local::set_port(self.port.read().clone());
+        let result =
+            panic::catch_unwind(AssertUnwindSafe(|| handler.dispatch_and_call(method, data)));
+        // This must be done even if the service object panics, since the thread will be reused.
+        crate::service::serde_support::port_thread_local::remove_port();
+        result.unwrap_or_else(|payload| {
+            Err(RemoteError::Panic {
+                trait_name: handler.trait_name().to_owned(),
+                method,
+                message: panic_message(payload.as_ref()),
+            })
+        })
+    }
+
+    /// The metrics of the context that this belongs to.
+    pub fn metrics(&self) -> &Arc<MetricsRecorder> {
+        &self.metrics
+    }
+
+    /// The signature of the exported service object, if it is known.
+    pub fn signature(&self, id: ServiceObjectId) -> Result<Option<ServiceSignature>, RemoteError> {
+        let service_object = Arc::clone(
+            self.service_objects
+                .read()
+                .get(&id)
+                .ok_or(RemoteError::UnknownObject(id))?,
+        );
+        // A relay asks its exporter, so the lock must not be held.
+        Ok(service_object.signature())
+    }
+
+    /// The exported service objects except the meta service, in the order of their ids.
+    pub fn exported_services(&self) -> Vec<ExportedService> {
+        let mut services: Vec<_> = self
+            .service_objects
+            .read()
+            .iter()
+            .filter(|(id, _)| **id != META_SERVICE_OBJECT_ID)
+            .map(|(id, service_object)| ExportedService {
+                id: *id,
+                trait_name: service_object.trait_name().to_owned(),
+            })
+            .collect();
+        services.sort_by_key(|service| service.id);
+        services
+    }
+
+    /// What the context that this belongs to tells about itself.
+    pub fn info(&self) -> &ContextInfo {
+        &self.info
+    }
+
+    /// Number of the exported service objects, except the meta service.
+    pub fn exported_objects(&self) -> usize {
+        self.service_objects
+            .read()
+            .keys()
+            .filter(|id| **id != META_SERVICE_OBJECT_ID)
+            .count()
+    }
+
     pub fn clear(&self) {
         self.service_objects.write().clear();
+        self.extra_references.lock().clear();
+        handoff::discard(self.minter);
         // we don't restore available_ids here becuase clear() will be called in termination phase
     }
 
-    fn delete(&self, id: ServiceObjectId) {
-        self.service_objects.write().remove(&id).unwrap();
+    /// Removes a reference to the service object, and deletes the object if it was the last one.
+    ///
+    /// The meta service is not counted, since it lives as long as the context.
+    fn delete(&self, id: ServiceObjectId) -> Result<(), RemoteError> {
+        let mut service_objects = self.service_objects.write();
+        if id == META_SERVICE_OBJECT_ID || !service_objects.contains_key(&id) {
+            return Err(RemoteError::UnknownObject(id));
+        }
+        let mut extra_references = self.extra_references.lock();
+        if let Some(count) = extra_references.get_mut(&id) {
+            *count -= 1;
+            if *count == 0 {
+                extra_references.remove(&id);
+            }
+            return Ok(());
+        }
+        drop(extra_references);
+        service_objects.remove(&id);
+        drop(service_objects);
         self.available_ids.write().push_back(id);
+        Ok(())
+    }
+
+    fn retain(&self, id: ServiceObjectId) -> Result<(), RemoteError> {
+        let service_objects = self.service_objects.read();
+        if id == META_SERVICE_OBJECT_ID || !service_objects.contains_key(&id) {
+            return Err(RemoteError::UnknownObject(id));
+        }
+        *self.extra_references.lock().entry(id).or_insert(0) += 1;
+        Ok(())
     }
 
     /// Be careful of this circular reference
@@ -104,8 +272,18 @@ impl ServiceForwarder {
     }
 }
 
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_owned()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_owned()
+    }
+}
+
 impl Handler for ServiceForwarder {
-    fn handle(&self, input: PacketView) -> Vec<u8> {
+    fn handle(&self, input: PacketView) -> Result<Vec<u8>, RemoteError> {
         self.forward_and_call(input)
     }
 }
@@ -0,0 +1,242 @@
+//! Introduction of a service to a third party, which imports it directly from the exporter if it can.
+
+use crate::context::Context;
+use crate::raw_exchange::HandleToExchange;
+use crate::service::{Dispatch, RemoteError, Service};
+use crate::ServiceToImport;
+use parking_lot::{const_mutex, Mutex};
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::sync::Arc;
+
+/// A service object that a token has been minted for, until it is redeemed.
+struct Minted {
+    /// The registry that has minted the token.
+    minter: u64,
+    service_object: Arc<dyn Dispatch>,
+}
+
+/// The tokens minted in this process, so that any context of the process can redeem them.
+static MINTED: Mutex<BTreeMap<HandoffToken, Minted>> = const_mutex(BTreeMap::new());
+
+/// A transferable token for a service object, which can be imported through another context of the exporter.
+///
+/// The exporter mints it on request of the importer, with [`mint_handoff_token()`].
+/// Then the importer gives it to a third party, who imports the service with [`import_service_from_handoff_token()`]
+/// through its own connection to the exporter, without going through the importer.
+///
+/// A token can be redeemed only once, through any context in the process of the exporter.
+/// A token that is never redeemed keeps the service object alive until the context that has minted it is dropped,
+/// unless it is revoked like [`Handoff::import()`] does when it falls back.
+///
+/// Anyone who knows a token can redeem it, so it is made of 128 bits from the random number generator of the OS.
+/// Still, pass it only to whom the service is meant for.
+///
+/// [`mint_handoff_token()`]: ./fn.mint_handoff_token.html
+/// [`import_service_from_handoff_token()`]: ./fn.import_service_from_handoff_token.html
+/// [`Handoff::import()`]: ../struct.Handoff.html#method.import
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct HandoffToken(u64, u64);
+
+impl HandoffToken {
+    fn random() -> Self {
+        let mut bytes = [0; 16];
+        getrandom::getrandom(&mut bytes).expect("Failed to get random bytes for a handoff token");
+        Self::from_bytes(&bytes).unwrap()
+    }
+
+    pub(crate) fn to_bytes(self) -> Vec<u8> {
+        let mut bytes = self.0.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&self.1.to_le_bytes());
+        bytes
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != 16 {
+            return None;
+        }
+        let mut high = [0; 8];
+        let mut low = [0; 8];
+        high.copy_from_slice(&bytes[..8]);
+        low.copy_from_slice(&bytes[8..]);
+        Some(HandoffToken(
+            u64::from_le_bytes(high),
+            u64::from_le_bytes(low),
+        ))
+    }
+}
+
+/// Mints a token for the service object, on behalf of the registry `minter`.
+pub(crate) fn mint(minter: u64, service_object: Arc<dyn Dispatch>) -> HandoffToken {
+    let mut minted = MINTED.lock();
+    loop {
+        let token = HandoffToken::random();
+        if let Entry::Vacant(entry) = minted.entry(token) {
+            entry.insert(Minted {
+                minter,
+                service_object,
+            });
+            return token;
+        }
+    }
+}
+
+/// Takes the service object of the token out, if it is minted in this process and not redeemed yet.
+pub(crate) fn redeem(token: HandoffToken) -> Option<Arc<dyn Dispatch>> {
+    MINTED
+        .lock()
+        .remove(&token)
+        .map(|minted| minted.service_object)
+}
+
+/// Discards all the tokens minted by the registry, which are not redeemed yet.
+pub(crate) fn discard(minter: u64) {
+    // Service objects might make delete requests when dropped, so they must not be dropped with the lock held.
+    let discarded: Vec<_> = {
+        let mut minted = MINTED.lock();
+        let tokens: Vec<_> = minted
+            .iter()
+            .filter(|(_, minted)| minted.minter == minter)
+            .map(|(token, _)| *token)
+            .collect();
+        tokens
+            .into_iter()
+            .filter_map(|token| minted.remove(&token))
+            .collect()
+    };
+    drop(discarded);
+}
+
+/// Asks the exporter to mint a [`HandoffToken`] for the service imported by the context.
+///
+/// It doesn't affect the handle, which still has to be imported (or dropped) eventually.
+///
+/// [`HandoffToken`]: ./struct.HandoffToken.html
+pub fn mint_handoff_token(
+    context: &Context,
+    handle: HandleToExchange,
+) -> Result<HandoffToken, RemoteError> {
+    context
+        .get_port()
+        .upgrade()
+        .ok_or(RemoteError::Disconnected)?
+        .mint_handoff_token(handle.0)
+}
+
+/// Imports a service with a [`HandoffToken`], through the context that is connected to the exporter.
+///
+/// It fails with [`RemoteError::UnknownHandoffToken`] if the other end is not in the process that has minted the token,
+/// or the token has already been redeemed.
+///
+/// [`HandoffToken`]: ./struct.HandoffToken.html
+/// [`RemoteError::UnknownHandoffToken`]: ../enum.RemoteError.html#variant.UnknownHandoffToken
+pub fn import_service_from_handoff_token<
+    T: ?Sized + Service,
+    P: crate::raw_exchange::ImportProxy<T>,
+>(
+    context: &Context,
+    token: HandoffToken,
+) -> Result<P, RemoteError> {
+    let port = context.get_port();
+    let handle = port
+        .upgrade()
+        .ok_or(RemoteError::Disconnected)?
+        .redeem_handoff_token(token)?;
+    Ok(P::import_proxy(port, handle))
+}
+
+/// An imported service that is being introduced to a third party.
+///
+/// Suppose that a host is connected to two parties, and wants to pass a service of one party to the other.
+/// Passing the [`ServiceToImport`] as it is makes every call go through the host.
+/// Instead, the host can pass a `Handoff`, which carries a [`HandoffToken`] along with such a forwarded service.
+/// The third party imports the service directly from the exporter if it is connected to it, and falls back to the forwarded one if not.
+/**
+```ignore
+// HOST SIDE
+let store: ServiceToImport<dyn Store> = market.open_store();
+customer.visit(Handoff::new(store)?);
+
+// THIRD PARTY
+impl Customer for SomeCustomer {
+    fn visit(&self, store: Handoff<dyn Store>) {
+        let store: Box<dyn Store> = store.import(self.market_context.as_ref()).into_proxy();
+        ...
+    }
+}
+```
+**/
+/// Like [`ServiceToImport`], it must be passed in a remote call, or imported eventually.
+///
+/// [`ServiceToImport`]: ./struct.ServiceToImport.html
+/// [`HandoffToken`]: ./raw_exchange/struct.HandoffToken.html
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Handoff<T: ?Sized + Service> {
+    token: HandoffToken,
+    fallback: ServiceToImport<T>,
+}
+
+impl<T: ?Sized + Service> Handoff<T> {
+    /// Asks the exporter of the service to mint a token for it.
+    pub fn new(service: ServiceToImport<T>) -> Result<Self, RemoteError> {
+        let token = service
+            .port()
+            .upgrade()
+            .ok_or(RemoteError::Disconnected)?
+            .mint_handoff_token(service.handle().0)?;
+        Ok(Self {
+            token,
+            fallback: service,
+        })
+    }
+
+    /// The token, which can be redeemed by [`import_service_from_handoff_token()`].
+    ///
+    /// [`import_service_from_handoff_token()`]: ./raw_exchange/fn.import_service_from_handoff_token.html
+    pub fn token(&self) -> HandoffToken {
+        self.token
+    }
+
+    /// Imports the service through `direct`, which is a context connected to the exporter.
+    ///
+    /// If it is `None`, or the token can't be redeemed through it, the service forwarded by the introducer is imported instead.
+    /// Then the token is revoked through the introducer, so that the exporter doesn't keep the service object for it.
+    pub fn import(self, direct: Option<&Context>) -> ServiceToImport<T> {
+        let direct = match direct {
+            Some(direct) => direct,
+            None => return self.fall_back(),
+        };
+        let port = direct.get_port();
+        let redeemed = port
+            .upgrade()
+            .ok_or(RemoteError::Disconnected)
+            .and_then(|port| port.redeem_handoff_token(self.token));
+        match redeemed {
+            Ok(handle) => {
+                self.fallback.discard();
+                ServiceToImport::from_raw_import(handle, port)
+            }
+            Err(err) => {
+                debug!(
+                    "Failed to redeem {:?}, so falling back: {}",
+                    self.token, err
+                );
+                self.fall_back()
+            }
+        }
+    }
+
+    fn fall_back(self) -> ServiceToImport<T> {
+        let revoked = self
+            .fallback
+            .port()
+            .upgrade()
+            .ok_or(RemoteError::Disconnected)
+            .and_then(|port| port.revoke_handoff_token(self.fallback.handle().0, self.token));
+        if let Err(err) = revoked {
+            debug!("Failed to revoke {:?}: {}", self.token, err);
+        }
+        self.fallback
+    }
+}
@@ -0,0 +1,126 @@
+use crate::forwarder::ServiceObjectId;
+use crate::metrics::{Direction, MetricsRecorder};
+use crate::service::{MethodId, RemoteError};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A method call that passes through [`Interceptor`]s.
+///
+/// [`Interceptor`]: ./trait.Interceptor.html
+#[derive(Debug, Clone, Copy)]
+pub struct InterceptedCall<'a> {
+    trait_name: &'static str,
+    method_name: &'static str,
+    method: MethodId,
+    object_id: ServiceObjectId,
+    args: &'a [u8],
+}
+
+impl<'a> InterceptedCall<'a> {
+    pub(crate) fn new(
+        trait_name: &'static str,
+        method_name: &'static str,
+        method: MethodId,
+        object_id: ServiceObjectId,
+        args: &'a [u8],
+    ) -> Self {
+        Self {
+            trait_name,
+            method_name,
+            method,
+            object_id,
+            args,
+        }
+    }
+
+    /// Name of the service trait, which is empty if it is unknown.
+    pub fn trait_name(&self) -> &'static str {
+        self.trait_name
+    }
+
+    /// Name of the method, which is empty if it is unknown.
+    pub fn method_name(&self) -> &'static str {
+        self.method_name
+    }
+
+    /// Id of the method.
+    pub fn method(&self) -> MethodId {
+        self.method
+    }
+
+    /// Id of the service object on the exporter side.
+    pub fn object_id(&self) -> ServiceObjectId {
+        self.object_id
+    }
+
+    /// The serialized arguments.
+    pub fn args(&self) -> &'a [u8] {
+        self.args
+    }
+}
+
+/// A middleware that wraps method calls, given by [`Config::client_interceptors`] or [`Config::server_interceptors`].
+///
+/// Client interceptors wrap the calls made through the proxy objects, and server interceptors wrap
+/// the calls of exported service objects.
+/// Interceptors are applied in the order of the list, so the first one is the outermost:
+/// `before()`s are called from the first, and `after()`s are called from the last.
+///
+/// Delete requests, which are sent when a proxy object is dropped, are not intercepted.
+///
+/// [`Config::client_interceptors`]: ./struct.Config.html#structfield.client_interceptors
+/// [`Config::server_interceptors`]: ./struct.Config.html#structfield.server_interceptors
+pub trait Interceptor: fmt::Debug + Send + Sync + 'static {
+    /// Called before the call is made.
+    ///
+    /// Returning an error short-circuits the call: the call is not made, and the error becomes its result.
+    /// The outer interceptors still see the error in their `after()`.
+    fn before(&self, _call: &InterceptedCall<'_>) -> Result<(), RemoteError> {
+        Ok(())
+    }
+
+    /// Called with the serialized result after the call is made, only if `before()` has succeeded.
+    ///
+    /// For a `#[oneway]` method, the result is an empty one once the request is sent.
+    fn after(&self, _call: &InterceptedCall<'_>, _result: Result<&[u8], &RemoteError>) {}
+}
+
+/// Interceptors that a call has passed through, and where the metrics of the call are recorded.
+pub(crate) struct Chain {
+    entered: Vec<Arc<dyn Interceptor>>,
+    metrics: Option<(Arc<MetricsRecorder>, Direction)>,
+    started: Instant,
+}
+
+impl Chain {
+    /// Passes the call through `before()` of the interceptors, until one of them short-circuits it.
+    pub fn enter(
+        interceptors: &[Arc<dyn Interceptor>],
+        metrics: Option<(Arc<MetricsRecorder>, Direction)>,
+        call: &InterceptedCall<'_>,
+    ) -> (Self, Result<(), RemoteError>) {
+        let mut chain = Self {
+            entered: Vec::new(),
+            metrics,
+            started: Instant::now(),
+        };
+        for interceptor in interceptors {
+            if let Err(err) = interceptor.before(call) {
+                return (chain, Err(err));
+            }
+            chain.entered.push(Arc::clone(interceptor));
+        }
+        (chain, Ok(()))
+    }
+
+    /// Passes the result through `after()` of the interceptors that have been entered, in reverse order.
+    pub fn exit(self, call: &InterceptedCall<'_>, result: Result<&[u8], &RemoteError>) {
+        for interceptor in self.entered.iter().rev() {
+            interceptor.after(call, result);
+        }
+        if let Some((metrics, direction)) = self.metrics {
+            metrics.record(direction, call, self.started.elapsed(), result);
+        }
+    }
+}
@@ -5,10 +5,11 @@ based on trait objects.
 It is...
 
 1. Based on _services_ that can be exported and imported **as trait objects** -
-You register a service object, which is a trait object, and export it. On the other side, you import it into a proxy object, which is also a trait object.
+   You register a service object, which is a trait object, and export it. On the other side, you import it into a proxy object, which is also a trait object.
 1. Based on a point-to-point connection - All operations are conducted upon a single connection, which has **two ends**.
 1. Easy to export and import services - During a remote method call in some service, you **can export and import another service as an argument or a return value** of the method.
-1. Independent from the transport model - The transport model is abstracted and **users must provide a concrete implementation of it**.
+1. Independent from the transport model - The transport model is abstracted and **users can provide a concrete implementation of it**,
+   or use one of the [built-in transports](./transport/index.html).
 1. Concurrent - you can both **call and handle remote calls concurrently**.
 
 Note that it is commonly abbreviated as **RTO**.
@@ -37,6 +38,8 @@ It is a trait object that is a proxy to the remote _service object_ on the _serv
 A _proxy object_ corresponds to exactly one _skeleton_, and vice versa.
 If a proxy object is dropped, it will request its deletion on the server side. This is called _delete request_.
 With this, the server side's context will remove the skeleton if the client doesn't own its proxy anymore.
+An imported service can be cloned with [`ServiceToImport::try_clone()`], which makes the server count one more reference to the skeleton.
+In that case the skeleton is removed only when all of the proxy objects are dropped.
 
 **_Service trait_** is a trait that represents a `service`.
 It is for two trait objects (_service object_ and _proxy object_).
@@ -71,7 +74,7 @@ It is sometimes called an _exchange_ when referring to both _export_ and _import
 
 1. User calls a method of _proxy object_ which is a trait object wrapped in a smart pointer.
 2. The call will be delivered to the _context_ from which the _proxy object_ is imported, after serialized into a byte packet.
-Note that the actual transportation of data happens only at the _context_, which functions as a connection end.
+   Note that the actual transportation of data happens only at the _context_, which functions as a connection end.
 3. The packet will be sent to the other end, (or context) by the _transport_.
 4. After the other side's _context_ receives the packet, it forwards the packet to the target _skeleton_ in its registry.
 5. The skeleton will dispatch the packet into an actual method call to the _service object_, which is a trait object wrapped in a smart pointer.
@@ -95,13 +98,13 @@ Both can decide which to use, depending on their own requirements.
 
 **Exporter (server)**
 - Use `Box<>` when you have nothing to do with the object after you export it.
-It will be registered in the [`Context`], and will be alive until the corresponding proxy object is dropped.
-You can never access the object directly, since it will be _moved_ to the registry.
+  It will be registered in the [`Context`], and will be alive until the corresponding proxy object is dropped.
+  You can never access the object directly, since it will be _moved_ to the registry.
 
 - Use `Arc<>` when you have something to do with the object after you export it, by `Arc::clone()` and holding somewhere.
-In this case, both its proxy object and some `Arc` copy on the exporter side can access the object,
-though the latter can only access it immutably.
-With this a single _service object_ can be shared among multiple _skeletons_ while a _skeleton_ always matches to exactly one _service object_.
+  In this case, both its proxy object and some `Arc` copy on the exporter side can access the object,
+  though the latter can only access it immutably.
+  With this a single _service object_ can be shared among multiple _skeletons_ while a _skeleton_ always matches to exactly one _service object_.
 
 - Use `Arc<RwLock<>>` when you have to access the object **mutably**, in the similar situation with `Arc` case.
 
@@ -127,15 +130,15 @@ There are some rules to use a trait as a service trait.
 1. No generic parameter (including lifetime) is allowed, in both trait definition and methods.
 
 1. All types appeared in method parameter or return value must implement [`serde`]'s [`Serialize`] and [`Deserialize`].
-This library performs de/serialization of data using [`serde`], though the data format can be chosen.
-Depending on your choice of macro arguments, this condition may differ slightly. See this [section](https://github.com/CodeChain-io/remote-trait-object)
+   This library performs de/serialization of data using [`serde`], though the data format can be chosen.
+   Depending on your choice of macro arguments, this condition may differ slightly. See this [section](https://github.com/CodeChain-io/remote-trait-object)
 
 1. You can't return a reference as a return type.
-This holds for a composite type too. For example, you can't return `&i32` nor `(i32, i32, &i32)`.
+   This holds for a composite type too. For example, you can't return `&i32` nor `(i32, i32, &i32)`.
 
 1. You can pass only first-order reference as a parameter.
-For example, you can pass `&T` only if the `T` doesn't a contain reference at all.
-Note that T must be `Sized`. There are two exceptions that accept `?Sized` `T`s: `str` and `[U]` where `U` doesn't contain reference at all.
+   For example, you can pass `&T` only if the `T` doesn't a contain reference at all.
+   Note that T must be `Sized`. There are two exceptions that accept `?Sized` `T`s: `str` and `[U]` where `U` doesn't contain reference at all.
 
 ### Example
 ```
@@ -148,17 +151,155 @@ pub trait PizzaStore : rto::Service {
 }
 ```
 
+### Async Methods
+A method can be an `async fn`. Since a trait object can't have one, the macro rewrites it into a method
+that returns a [`BoxFuture`], which the service object implements with an `async` block.
+```
+use remote_trait_object::*;
+
+#[service]
+pub trait Oven: Service {
+    async fn bake(&self, menu: String) -> String;
+}
+
+struct SomeOven;
+impl Service for SomeOven {}
+impl Oven for SomeOven {
+    fn bake(&self, menu: String) -> BoxFuture<'_, String> {
+        Box::pin(async move { format!("Baked {}", menu) })
+    }
+}
+```
+The proxy sends the request as soon as the method is called, and the returned future resolves when the response arrives,
+so no thread is blocked while waiting for it.
+On the other side, the skeleton drives the future of the service object with [`block_on()`] on the thread that handles the call,
+which doesn't provide any reactor by itself.
+With the `tokio` feature, the call is handled in the tokio runtime that the context has been created in, if any,
+so the future can use IO and timers of `tokio` as long as it is a multi-thread runtime.
+A method may also be declared to return `BoxFuture<'_, T>` directly, which is equivalent.
+
+### Pending Replies
+To make many calls from a single thread without going async, put `#[pending]` on a method.
+The trait gets a `<method>_pending` variant, which returns a [`PendingReply`] as soon as the request is sent.
+```
+use remote_trait_object::*;
+
+#[service]
+pub trait Warehouse: Service {
+    #[pending]
+    fn stock(&self, item: String) -> u64;
+}
+
+fn total_stock(warehouses: &[Box<dyn Warehouse>]) -> u64 {
+    let replies: Vec<_> = warehouses.iter().map(|w| w.stock_pending("apple".to_owned())).collect();
+    replies.into_iter().map(|reply| reply.wait().unwrap()).sum()
+}
+```
+A service object doesn't implement the variant, whose default implementation calls the original method.
+
+### Oneway Methods
+A method that returns nothing can be marked with `#[oneway]`, for notifications that don't need any acknowledgement.
+Its proxy returns as soon as the request is sent, without taking a call slot, and the other side doesn't send a response.
+Thus a failure of the call is only logged, and the method may still be running when the proxy returns.
+A fallible one, which returns `Result<(), RemoteError>`, returns the error only if the request couldn't be sent.
+```
+use remote_trait_object::*;
+
+#[service]
+pub trait LogSink: Service {
+    #[oneway]
+    fn log(&self, message: String);
+}
+```
+
+With the `tokio` feature, [`AsyncContext`] runs a connection as tasks on a tokio runtime, instead of spawning its own threads.
+
+### Cancellation and Deadlines
+A call is cancelled when its caller gives up, by [`Config::call_timeout`] or by dropping its future or [`PendingReply`].
+The other side gets a cancel packet for it, and answers it right away, so that the caller can reuse the call slot.
+A service object can see whether the call it is handling is still wanted with [`CancellationToken::current()`],
+which also tells the deadline of the call. Calls made while handling a call inherit its remaining time.
+
+### Call Metadata
+A caller can give out-of-band data to the calls it makes, like a trace id or an auth token,
+with [`with_call_metadata()`]. A service object reads it with [`current_call()`],
+which also tells the name of the context that has received the call and the id of the service object being called.
+```
+use remote_trait_object::*;
+
+#[service]
+pub trait Bank: Service {
+    fn withdraw(&self, amount: u64) -> Result<u64, RemoteError>;
+}
+
+fn withdraw_as(bank: &dyn Bank, user: &str) -> Result<u64, RemoteError> {
+    let mut metadata = CallMetadata::new();
+    metadata.insert("user".to_owned(), user.to_owned());
+    with_call_metadata(metadata, || bank.withdraw(100))
+}
+```
+
+### Interceptors
+[`Config::client_interceptors`] and [`Config::server_interceptors`] wrap every call made and handled by a context,
+with the names of the trait and the method, the id of the service object and the serialized arguments.
+An [`Interceptor`] can also reject a call with an error, before it is made.
+```
+use remote_trait_object::*;
+use std::sync::Arc;
+
+#[derive(Debug)]
+struct ReadOnly;
+
+impl Interceptor for ReadOnly {
+    fn before(&self, call: &InterceptedCall<'_>) -> Result<(), RemoteError> {
+        if call.method_name().starts_with("set_") {
+            return Err(RemoteError::UnknownMethod {
+                trait_name: call.trait_name().to_owned(),
+                method: call.method(),
+            });
+        }
+        Ok(())
+    }
+}
+
+let config = Config {
+    server_interceptors: vec![Arc::new(ReadOnly)],
+    ..Config::default_setup()
+};
+```
+
+### Metrics
+Every context counts the calls it makes and handles, by trait and method, along with their errors, latencies and sizes.
+[`Context::metrics()`] returns a snapshot of them, with the numbers of exported service objects, imported proxy objects and call slots in use.
+With the `metrics` feature, they are also recorded to the [`metrics`](https://docs.rs/metrics) crate.
+
+### Tracing
+With the `tracing` feature, each call opens a [`tracing`](https://docs.rs/tracing) span on both sides,
+`remote_call` on the caller and `handle_call` on the callee, with `otel.name` of `Trait::method`.
+The trace context is carried with the call as `traceparent` metadata, so the spans of a call and
+the calls made while handling it form a single trace across the contexts. See `TraceContext` for more.
+
+### Introspection
+Every context exports a [`MetaService`] by itself, whose proxy object is given by [`Context::meta_service()`].
+It lists the service objects that the other end exports with their traits and signatures,
+and tells the versions and the limits of the other end, so that a live connection can be inspected while debugging.
+[`Context::ping()`] measures the round-trip time with it.
+
 ### Service Compatibility
 Although it is common to use the same trait for both proxy object and service object, it is possible to import a service into another trait.
 
-TODO: We have not strictly designed the compatibility model but will be provided in the next version.
+The macro describes each service trait with a [`ServiceSignature`], which lists its methods with their ids, names and types.
+Trait `P` is compatible to be proxy of trait `S`, only if every method of `P` is in `S` with the same id, name and types.
+`S` may have more methods, so that `P` can be a smaller trait that declares a part of the methods of `S` in the same order.
+The names of the traits don't matter.
 
-Roughly, in current version, trait `P` is considered to be compatible to be proxy of trait `S`, only if
-1. `P` has exactly the same methods as `S` declared in the same order, that differ only in types of parameter and return value.
-2. Such different types must be compatible.
-3. Types are considered to be compatible if both are serialized and deserialized with exactly the same value.
+Types are compared as they are written, except that references are removed and only the last segment of each path is kept.
+Thus `&str` is the same as `String`, and `&[T]` is the same as `Vec<T>`, as they are serialized the same.
+`remote-trait-object` also treats [`ServiceToExport`], [`ServiceToImport`] and [`ServiceRef`] as the same type.
 
-`remote-trait-object` always guarantees 3. between [`ServiceToExport`], [`ServiceToImport`] and [`ServiceRef`].
+It is checked in [`ServiceToImport::cast_service()`], which asks the exporter for the signature of the service object,
+and for the initial service when a context is created, whose signature is carried in the handshake.
+Note that the types are compared by their names, so two different types of the same name are still considered to be the same.
 
 ## Export & Import services
 One of the core features of `remote-trait-object` is its simple and straightforward but extensive export & import of services.
@@ -172,6 +313,8 @@ See details [here](./struct.Context.html#method.with_initial_service)
 
 ### As a Parameter or a Return Value
 This is the most common way of exporting / importing services.
+An imported service can also be passed on to another context as it is, and then the calls to it are forwarded to the exporter.
+If the receiver might be connected to the exporter by itself, pass a [`Handoff`] instead, so that it can import the service directly.
 
 See [`ServiceToExport`], [`ServiceToImport`] and [`ServiceRef`] for more.
 
@@ -239,6 +382,25 @@ You can check the working code of this example [here](https://github.com/CodeCha
 See more examples [here](https://github.com/CodeChain-io/remote-trait-object/tree/master/remote-trait-object-tests/src).
 
 [`Arc`]: https://doc.rust-lang.org/std/sync/struct.Arc.html
+[`BoxFuture`]: ./type.BoxFuture.html
+[`PendingReply`]: ./struct.PendingReply.html
+[`with_call_metadata()`]: ./fn.with_call_metadata.html
+[`current_call()`]: ./fn.current_call.html
+[`ServiceToImport::try_clone()`]: ./struct.ServiceToImport.html#method.try_clone
+[`Handoff`]: ./struct.Handoff.html
+[`ServiceSignature`]: ./struct.ServiceSignature.html
+[`MetaService`]: ./trait.MetaService.html
+[`Context::meta_service()`]: ./struct.Context.html#method.meta_service
+[`Context::ping()`]: ./struct.Context.html#method.ping
+[`ServiceToImport::cast_service()`]: ./struct.ServiceToImport.html#method.cast_service
+[`Interceptor`]: ./trait.Interceptor.html
+[`Context::metrics()`]: ./struct.Context.html#method.metrics
+[`Config::client_interceptors`]: ./struct.Config.html#structfield.client_interceptors
+[`Config::server_interceptors`]: ./struct.Config.html#structfield.server_interceptors
+[`CancellationToken::current()`]: ./struct.CancellationToken.html#method.current
+[`Config::call_timeout`]: ./struct.Config.html#structfield.call_timeout
+[`block_on()`]: ./fn.block_on.html
+[`AsyncContext`]: ./struct.AsyncContext.html
 [`Skeleton`]: ./raw_exchange/struct.Skeleton.html
 [`HandleToExchange`]: ./raw_exchange/struct.HandleToExchange.html
 [`Serialize`]: https://docs.serde.rs/serde/trait.Serialize.html
@@ -248,20 +410,43 @@ See more examples [here](https://github.com/CodeChain-io/remote-trait-object/tre
 #[macro_use]
 extern crate log;
 
+#[cfg(feature = "tokio")]
+mod async_context;
+mod call;
+mod cancel;
 mod context;
 mod forwarder;
+mod handoff;
+mod interceptor;
+mod meta_service;
+mod metrics;
 mod packet;
 mod port;
 mod queue;
 mod service;
 #[cfg(test)]
 mod tests;
+mod trace;
 pub mod transport;
 
+#[cfg(feature = "tokio")]
+pub use async_context::AsyncContext;
+pub use call::{current_call, with_call_metadata, CallInfo, CallMetadata};
+pub use cancel::CancellationToken;
 pub use context::{Config, Context};
+pub use handoff::Handoff;
+pub use interceptor::{InterceptedCall, Interceptor};
+pub use meta_service::{ContextInfo, ExportedService, MetaService};
+pub use metrics::{LatencyHistogram, MethodMetrics, Metrics};
+pub use packet::ProtocolError;
 pub use service::id::setup_identifiers;
 pub use service::serde_support::{ServiceRef, ServiceToExport, ServiceToImport};
-pub use service::{SerdeFormat, Service};
+pub use service::{
+    block_on, BoxFuture, DescribeService, MethodSignature, PendingReply, RemoteError, SerdeFormat,
+    Service, ServiceSignature,
+};
+#[cfg(feature = "tracing")]
+pub use trace::TraceContext;
 
 pub mod raw_exchange {
     //! This module is needed only if you want to perform some raw exchange (or export/import) of services.
@@ -282,6 +467,7 @@ pub mod raw_exchange {
     //! [`ServiceToImport`]: ../struct.ServiceToImport.html
     //! [`ServiceRef`]: ../enum.ServiceRef.html
 
+    pub use crate::handoff::{import_service_from_handoff_token, mint_handoff_token, HandoffToken};
     pub use crate::service::export_import::{
         export_service_into_handle, import_null_proxy, import_service_from_handle,
         HandleToExchange, ImportProxy, IntoSkeleton, Skeleton,
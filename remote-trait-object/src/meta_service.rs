@@ -0,0 +1,148 @@
+//! The service that each context exports by itself, which tells the other end about the context.
+
+/// This is required because of macro
+use crate as remote_trait_object;
+use crate::forwarder::{ServiceForwarder, ServiceObjectId, META_SERVICE_OBJECT_ID};
+use crate::packet::PROTOCOL_VERSION;
+use crate::port::Port;
+use crate::raw_exchange::{HandleToExchange, ImportProxy};
+use crate::service::serde_support::port_thread_local;
+use crate::{Config, RemoteError, Service, ServiceSignature};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+/// The service that every context exports at a fixed id, without being asked to.
+///
+/// It tells what the context has registered in its registry, which is useful to debug a live connection.
+/// Its proxy object for the other end is given by [`Context::meta_service()`].
+///
+/// Every method is fallible, since the other end might be too old to have it.
+///
+/// [`Context::meta_service()`]: ./struct.Context.html#method.meta_service
+#[remote_trait_object_macro::service]
+pub trait MetaService: Service {
+    /// The signature of a service object that this side exports, if it is known.
+    fn signature(&self, id: ServiceObjectId) -> Result<Option<ServiceSignature>, RemoteError>;
+
+    /// Lists the service objects that this side exports, except the meta service itself, in the order of their ids.
+    fn exported_services(&self) -> Result<Vec<ExportedService>, RemoteError>;
+
+    /// The signature of a service trait, if this side exports any service object of it.
+    fn trait_signature(&self, trait_name: &str) -> Result<Option<ServiceSignature>, RemoteError>;
+
+    /// The versions and the limits of this side.
+    fn info(&self) -> Result<ContextInfo, RemoteError>;
+
+    /// Does nothing, so that the round-trip time can be measured with it.
+    fn ping(&self) -> Result<(), RemoteError>;
+}
+
+/// A service object listed by [`MetaService::exported_services()`].
+///
+/// [`MetaService::exported_services()`]: ./trait.MetaService.html#tymethod.exported_services
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedService {
+    pub id: ServiceObjectId,
+    /// Name of the service trait, which is empty if it is unknown, like for a forwarded service.
+    pub trait_name: String,
+}
+
+/// What a context tells about itself with [`MetaService::info()`].
+///
+/// [`MetaService::info()`]: ./trait.MetaService.html#tymethod.info
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextInfo {
+    /// [`Config::name`](./struct.Config.html#structfield.name) of the context.
+    pub name: String,
+    /// Version of the packet format.
+    pub protocol_version: u8,
+    /// Version of `remote-trait-object`.
+    pub crate_version: String,
+    /// [`Config::call_slots`](./struct.Config.html#structfield.call_slots) of the context.
+    pub call_slots: usize,
+    /// [`Config::call_timeout`](./struct.Config.html#structfield.call_timeout) of the context.
+    pub call_timeout: Option<Duration>,
+    /// [`Config::maximum_services_num`](./struct.Config.html#structfield.maximum_services_num) of the context.
+    pub maximum_services_num: usize,
+}
+
+impl ContextInfo {
+    pub(crate) fn new(config: &Config) -> Self {
+        Self {
+            name: config.name.clone(),
+            protocol_version: PROTOCOL_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_owned(),
+            call_slots: config.call_slots,
+            call_timeout: config.call_timeout,
+            maximum_services_num: config.maximum_services_num,
+        }
+    }
+}
+
+pub(crate) struct MetaServiceImpl {}
+
+impl MetaServiceImpl {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// The registry of the context that is handling the call.
+    fn registry() -> Result<Arc<ServiceForwarder>, RemoteError> {
+        port_thread_local::get_port()
+            .upgrade()
+            .and_then(|port| port.registry())
+            .ok_or(RemoteError::Disconnected)
+    }
+}
+
+impl Service for MetaServiceImpl {}
+
+impl MetaService for MetaServiceImpl {
+    fn signature(&self, id: ServiceObjectId) -> Result<Option<ServiceSignature>, RemoteError> {
+        Self::registry()?.signature(id)
+    }
+
+    fn exported_services(&self) -> Result<Vec<ExportedService>, RemoteError> {
+        Ok(Self::registry()?.exported_services())
+    }
+
+    fn trait_signature(&self, trait_name: &str) -> Result<Option<ServiceSignature>, RemoteError> {
+        let registry = Self::registry()?;
+        for service in registry.exported_services() {
+            if service.trait_name != trait_name {
+                continue;
+            }
+            // It might have been deleted in the meantime.
+            if let Ok(Some(signature)) = registry.signature(service.id) {
+                return Ok(Some(signature));
+            }
+        }
+        Ok(None)
+    }
+
+    fn info(&self) -> Result<ContextInfo, RemoteError> {
+        Ok(Self::registry()?.info().clone())
+    }
+
+    fn ping(&self) -> Result<(), RemoteError> {
+        Ok(())
+    }
+}
+
+/// Asks the other side for the signature of a service object imported through the port.
+///
+/// It is `None` if the other side doesn't know, or is too old to tell.
+pub(crate) fn exported_signature(
+    port: &Weak<dyn Port>,
+    id: ServiceObjectId,
+) -> Result<Option<ServiceSignature>, RemoteError> {
+    let meta_service = <Box<dyn MetaService> as ImportProxy<dyn MetaService>>::import_proxy(
+        Weak::clone(port),
+        HandleToExchange(META_SERVICE_OBJECT_ID),
+    );
+    match meta_service.signature(id) {
+        Err(RemoteError::UnknownMethod { .. }) => Ok(None),
+        result => result,
+    }
+}
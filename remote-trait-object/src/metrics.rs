@@ -0,0 +1,323 @@
+use crate::interceptor::InterceptedCall;
+use crate::service::RemoteError;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Upper bounds of the buckets of [`LatencyHistogram`], in microseconds.
+/// The last bucket, which is not listed here, has no upper bound.
+///
+/// [`LatencyHistogram`]: ./struct.LatencyHistogram.html
+const LATENCY_BUCKETS: [u64; 16] = [
+    50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000,
+    1_000_000, 2_500_000, 5_000_000,
+];
+
+/// A distribution of call latencies, counted in fixed buckets from 50µs to 5s.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    counts: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: Duration,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            counts: [0; LATENCY_BUCKETS.len() + 1],
+            sum: Duration::from_secs(0),
+        }
+    }
+}
+
+impl LatencyHistogram {
+    fn record(&mut self, latency: Duration) {
+        let micros = latency.as_micros();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| micros <= u128::from(*bound))
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.counts[bucket] += 1;
+        self.sum += latency;
+    }
+
+    /// Number of the recorded latencies.
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Sum of the recorded latencies.
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    /// Average of the recorded latencies, or `None` if nothing is recorded.
+    pub fn mean(&self) -> Option<Duration> {
+        match self.count() {
+            0 => None,
+            count => Some(self.sum / count as u32),
+        }
+    }
+
+    /// The buckets, each of which is its inclusive upper bound and the number of latencies in it.
+    ///
+    /// The last bucket has no upper bound.
+    pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, u64)> + '_ {
+        LATENCY_BUCKETS
+            .iter()
+            .map(|bound| Some(Duration::from_micros(*bound)))
+            .chain(std::iter::once(None))
+            .zip(self.counts.iter().copied())
+    }
+
+    /// An estimate of the `q`-quantile (e.g. `0.99`), which is the upper bound of the bucket that it falls in.
+    ///
+    /// It is `None` if nothing is recorded, and `Some(None)` if it is in the last bucket.
+    pub fn quantile(&self, q: f64) -> Option<Option<Duration>> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bound, n) in self.buckets() {
+            seen += n;
+            if seen >= rank {
+                return Some(bound);
+            }
+        }
+        unreachable!()
+    }
+}
+
+/// Statistics of the calls of a method.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MethodMetrics {
+    /// Number of the calls, including failed ones.
+    pub calls: u64,
+    /// Number of the calls that have failed with a [`RemoteError`], including those rejected by interceptors.
+    ///
+    /// An error that a fallible method returns as its value is not counted.
+    ///
+    /// [`RemoteError`]: ./enum.RemoteError.html
+    pub errors: u64,
+    /// Total size of the serialized arguments.
+    pub request_bytes: u64,
+    /// Total size of the serialized return values.
+    pub response_bytes: u64,
+    /// How long the calls have taken, including the interceptors.
+    pub latency: LatencyHistogram,
+}
+
+/// A snapshot of the metrics of a context, given by [`Context::metrics()`].
+///
+/// Each method is keyed by the names of its trait and itself, which are empty if unknown.
+///
+/// [`Context::metrics()`]: ./struct.Context.html#method.metrics
+#[derive(Clone, Debug, Default)]
+pub struct Metrics {
+    /// The name of the context, from [`Config::name`].
+    ///
+    /// [`Config::name`]: ./struct.Config.html#structfield.name
+    pub context_name: String,
+    /// Calls made through the proxy objects imported by the context.
+    pub outgoing: BTreeMap<(&'static str, &'static str), MethodMetrics>,
+    /// Calls of the service objects exported by the context.
+    pub incoming: BTreeMap<(&'static str, &'static str), MethodMetrics>,
+    /// Number of the service objects exported by the context, including the initial service.
+    pub exported_objects: usize,
+    /// Number of the proxy objects imported by the context, which are alive.
+    ///
+    /// The proxy object of the meta service, which the context holds, is not counted.
+    pub imported_proxies: usize,
+    /// Number of the call slots that are waiting for their responses.
+    pub call_slots_in_use: usize,
+    /// Number of the incoming calls that are waiting for a thread of [`Config::thread_pool`].
+    ///
+    /// Note that the pool might be shared with other contexts.
+    /// It is always zero for an [`AsyncContext`], which doesn't use the pool.
+    ///
+    /// [`Config::thread_pool`]: ./struct.Config.html#structfield.thread_pool
+    /// [`AsyncContext`]: ./struct.AsyncContext.html
+    pub queued_calls: usize,
+}
+
+#[cfg(feature = "metrics")]
+impl Metrics {
+    /// Sets the gauges of the snapshot to the recorder of the `metrics` crate.
+    ///
+    /// Counters and histograms are recorded to it as calls are made, so they don't need this.
+    pub fn publish(&self) {
+        let labels = [("context", self.context_name.clone())];
+        let gauges = [
+            (
+                "remote_trait_object_exported_objects",
+                self.exported_objects,
+            ),
+            (
+                "remote_trait_object_imported_proxies",
+                self.imported_proxies,
+            ),
+            (
+                "remote_trait_object_call_slots_in_use",
+                self.call_slots_in_use,
+            ),
+            ("remote_trait_object_queued_calls", self.queued_calls),
+        ];
+        for (name, value) in gauges.iter() {
+            ::metrics::gauge!(*name, *value as f64, &labels);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Outgoing,
+    Incoming,
+}
+
+impl Direction {
+    #[cfg(feature = "metrics")]
+    fn as_str(self) -> &'static str {
+        match self {
+            Direction::Outgoing => "outgoing",
+            Direction::Incoming => "incoming",
+        }
+    }
+}
+
+/// Collects the metrics of a context, which is shared by its port and registry.
+#[derive(Debug, Default)]
+pub struct MetricsRecorder {
+    context_name: String,
+    outgoing: Mutex<HashMap<(&'static str, &'static str), MethodMetrics>>,
+    incoming: Mutex<HashMap<(&'static str, &'static str), MethodMetrics>>,
+    imported_proxies: AtomicUsize,
+}
+
+impl MetricsRecorder {
+    pub fn new(context_name: &str) -> Self {
+        Self {
+            context_name: context_name.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    pub fn record(
+        &self,
+        direction: Direction,
+        call: &InterceptedCall<'_>,
+        latency: Duration,
+        result: Result<&[u8], &RemoteError>,
+    ) {
+        let table = match direction {
+            Direction::Outgoing => &self.outgoing,
+            Direction::Incoming => &self.incoming,
+        };
+        {
+            let mut table = table.lock();
+            let method = table
+                .entry((call.trait_name(), call.method_name()))
+                .or_default();
+            method.calls += 1;
+            method.request_bytes += call.args().len() as u64;
+            match result {
+                Ok(response) => method.response_bytes += response.len() as u64,
+                Err(_) => method.errors += 1,
+            }
+            method.latency.record(latency);
+        }
+
+        #[cfg(feature = "metrics")]
+        {
+            let labels = [
+                ("context", self.context_name.clone()),
+                ("direction", direction.as_str().to_owned()),
+                ("trait", call.trait_name().to_owned()),
+                ("method", call.method_name().to_owned()),
+            ];
+            ::metrics::counter!("remote_trait_object_calls_total", 1, &labels);
+            if result.is_err() {
+                ::metrics::counter!("remote_trait_object_errors_total", 1, &labels);
+            }
+            ::metrics::counter!(
+                "remote_trait_object_request_bytes_total",
+                call.args().len() as u64,
+                &labels
+            );
+            if let Ok(response) = result {
+                ::metrics::counter!(
+                    "remote_trait_object_response_bytes_total",
+                    response.len() as u64,
+                    &labels
+                );
+            }
+            ::metrics::histogram!(
+                "remote_trait_object_call_duration_seconds",
+                latency,
+                &labels
+            );
+        }
+    }
+
+    pub fn proxy_imported(&self) {
+        self.imported_proxies.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn proxy_dropped(&self) {
+        self.imported_proxies.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Takes a snapshot of what this records, leaving the rest of it empty.
+    pub fn snapshot(&self) -> Metrics {
+        Metrics {
+            context_name: self.context_name.clone(),
+            outgoing: self.outgoing.lock().clone().into_iter().collect(),
+            incoming: self.incoming.lock().clone().into_iter().collect(),
+            imported_proxies: self.imported_proxies.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_histogram() {
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!(histogram.mean(), None);
+        assert_eq!(histogram.quantile(0.5), None);
+
+        histogram.record(Duration::from_micros(10));
+        histogram.record(Duration::from_micros(100));
+        histogram.record(Duration::from_micros(120));
+        histogram.record(Duration::from_secs(10));
+        assert_eq!(histogram.count(), 4);
+        assert_eq!(histogram.sum(), Duration::from_micros(10_000_230));
+        assert_eq!(
+            histogram.quantile(0.25),
+            Some(Some(Duration::from_micros(50)))
+        );
+        assert_eq!(
+            histogram.quantile(0.5),
+            Some(Some(Duration::from_micros(100)))
+        );
+        assert_eq!(
+            histogram.quantile(0.75),
+            Some(Some(Duration::from_micros(250)))
+        );
+        assert_eq!(histogram.quantile(1.0), Some(None));
+        let buckets: Vec<_> = histogram.buckets().filter(|(_, n)| *n > 0).collect();
+        assert_eq!(
+            buckets,
+            vec![
+                (Some(Duration::from_micros(50)), 1),
+                (Some(Duration::from_micros(100)), 1),
+                (Some(Duration::from_micros(250)), 1),
+                (None, 1),
+            ]
+        );
+    }
+}
@@ -1,30 +1,76 @@
+use crate::call::CallMetadata;
 use crate::forwarder::ServiceObjectId;
-use crate::service::MethodId;
+use crate::service::{MethodId, RemoteError, ServiceSignature};
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::sync::Arc;
+use std::time;
+
+/// Version of the packet format.
+///
+/// Version 1 had no version field nor flags, and distinguished requests from responses by the range of the slot id.
+pub const PROTOCOL_VERSION: u8 = 2;
+
+/// Payload of the handshake packet, which is the first packet that each end sends.
+///
+/// It is followed by the signature of the initial service, if it is known.
+const HANDSHAKE_MAGIC: &[u8] = b"remote-trait-object";
 
 const UNDECIDED_SLOT: u32 = 4_294_967_295;
 
+/// A violation of the protocol by the other end.
+///
+/// Such packets are discarded, and the latest one is kept in the [`Context`].
+///
+/// [`Context`]: ./struct.Context.html
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProtocolError {
+    /// The other end didn't start with a valid handshake, or its initial service can't be imported as the one expected.
+    /// The connection is closed in this case.
+    Handshake(String),
+    /// A packet couldn't be parsed.
+    MalformedPacket(String),
+    /// A response was given for a call slot that doesn't exist.
+    InvalidSlot(u32),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::Handshake(reason) => write!(f, "handshake failed: {}", reason),
+            ProtocolError::MalformedPacket(reason) => write!(f, "malformed packet: {}", reason),
+            ProtocolError::InvalidSlot(slot) => write!(f, "response for an invalid slot {}", slot),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+/// A place to report `ProtocolError`s, shared by the components of a context.
+#[derive(Clone, Debug, Default)]
+pub struct ProtocolErrorReporter(Arc<Mutex<Option<ProtocolError>>>);
+
+impl ProtocolErrorReporter {
+    pub fn report(&self, error: ProtocolError) {
+        warn!("remote-trait-object protocol error: {}", error);
+        self.0.lock().replace(error);
+    }
+
+    pub fn last(&self) -> Option<ProtocolError> {
+        self.0.lock().clone()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Copy, Clone)]
 pub struct SlotId(u32);
 
 impl fmt::Display for SlotId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "SlotId {{ id: {:?}, type: {:?} }}",
-            self.0,
-            self.get_type()
-        )
+        write!(f, "SlotId({})", self.0)
     }
 }
 
-#[derive(Debug)]
-pub enum SlotType {
-    Request,
-    Response,
-}
-
 impl SlotId {
     pub fn new(num: u32) -> Self {
         Self(num)
@@ -34,53 +80,104 @@ impl SlotId {
         Self(UNDECIDED_SLOT)
     }
 
-    pub fn get_type(&self) -> SlotType {
-        if self.0 >= SLOT_CALL_OR_RETURN_INDICATOR.0 {
-            SlotType::Request
-        } else {
-            SlotType::Response
-        }
+    pub fn as_usize(&self) -> usize {
+        self.0 as usize
     }
 
-    pub fn change_to_response(&mut self) {
-        assert!(self.0 >= SLOT_CALL_OR_RETURN_INDICATOR.0);
-        self.0 -= SLOT_CALL_OR_RETURN_INDICATOR.0;
+    pub fn as_raw(&self) -> u32 {
+        self.0
     }
+}
 
-    pub fn into_request(self) -> Self {
-        assert!(self.0 < SLOT_CALL_OR_RETURN_INDICATOR.0);
-        Self(self.0 + SLOT_CALL_OR_RETURN_INDICATOR.0)
+/// A set of bits that describes the kind of a packet.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Copy, Clone)]
+pub struct PacketFlags(u8);
+
+impl PacketFlags {
+    /// A method call.
+    pub const REQUEST: Self = Self(1);
+    /// A return value of a method call.
+    pub const RESPONSE: Self = Self(1 << 1);
+    /// The response carries a `RemoteError` instead of the return value.
+    pub const ERROR: Self = Self(1 << 2);
+    /// A request that doesn't expect a response.
+    pub const ONEWAY: Self = Self(1 << 3);
+    /// A request to cancel an ongoing call.
+    pub const CANCEL: Self = Self(1 << 4);
+    /// A packet that is handled by the context itself, not by any service object.
+    pub const CONTROL: Self = Self(1 << 5);
+    /// The data of the request starts with the time left for the call, in microseconds as a little-endian `u64`.
+    pub const DEADLINE: Self = Self(1 << 6);
+    /// The data of the request starts with the call metadata, after the deadline if any.
+    /// It is prefixed with its length as a little-endian `u32`.
+    pub const METADATA: Self = Self(1 << 7);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
     }
 
-    pub fn as_usize(&self) -> usize {
-        self.0 as usize
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0
     }
 
-    pub fn as_raw(&self) -> u32 {
-        self.0
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0
     }
 }
 
-const SLOT_CALL_OR_RETURN_INDICATOR: SlotId = SlotId(2_147_483_648);
+impl fmt::Debug for PacketFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [
+            (Self::REQUEST, "REQUEST"),
+            (Self::RESPONSE, "RESPONSE"),
+            (Self::ERROR, "ERROR"),
+            (Self::ONEWAY, "ONEWAY"),
+            (Self::CANCEL, "CANCEL"),
+            (Self::CONTROL, "CONTROL"),
+            (Self::DEADLINE, "DEADLINE"),
+            (Self::METADATA, "METADATA"),
+        ];
+        let mut list = f.debug_set();
+        for (flag, name) in names.iter() {
+            if self.contains(*flag) {
+                list.entry(&format_args!("{}", name));
+            }
+        }
+        list.finish()
+    }
+}
 
 /// FIXME: Replace this hard-coded value to some constant evaluation
-const PACKET_HEADER_SIZE: usize = 12;
+const PACKET_HEADER_SIZE: usize = 18;
+
+const DEADLINE_SIZE: usize = std::mem::size_of::<u64>();
+
+const METADATA_LENGTH_SIZE: usize = std::mem::size_of::<u32>();
+
+/// Where the metadata starts, which is right after the deadline.
+fn metadata_offset(flags: PacketFlags) -> usize {
+    if flags.contains(PacketFlags::DEADLINE) {
+        PacketHeader::len() + DEADLINE_SIZE
+    } else {
+        PacketHeader::len()
+    }
+}
 
 #[test]
 fn packet_header_size() {
-    let x = PacketHeader {
-        slot: SlotId(0),
-        service_object_id: 0,
-        method: 0,
-    };
+    let x = PacketHeader::new(PacketFlags::REQUEST, SlotId(0), 0, 0);
     assert_eq!(bincode::serialize(&x).unwrap().len(), PACKET_HEADER_SIZE);
 }
 
 #[derive(Serialize, Deserialize)]
 struct PacketHeader {
+    pub version: u8,
+    pub flags: PacketFlags,
     pub slot: SlotId,
     pub service_object_id: ServiceObjectId,
     pub method: MethodId,
+    /// Length of the data that follows the header.
+    pub length: u32,
 }
 
 impl PacketHeader {
@@ -88,11 +185,19 @@ impl PacketHeader {
         PACKET_HEADER_SIZE
     }
 
-    pub fn new(slot: SlotId, service_object_id: ServiceObjectId, method: MethodId) -> Self {
+    pub fn new(
+        flags: PacketFlags,
+        slot: SlotId,
+        service_object_id: ServiceObjectId,
+        method: MethodId,
+    ) -> Self {
         PacketHeader {
+            version: PROTOCOL_VERSION,
+            flags,
             slot,
             service_object_id,
             method,
+            length: 0,
         }
     }
 
@@ -114,7 +219,8 @@ impl<'a> fmt::Display for PacketView<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Packet {{ slot: {}, object id: {}, method: {} }}",
+            "Packet {{ flags: {:?}, slot: {}, object id: {}, method: {} }}",
+            self.flags(),
             self.slot(),
             self.object_id(),
             self.method()
@@ -127,12 +233,115 @@ impl<'a> PacketView<'a> {
         Self { buffer }
     }
 
+    /// Checks whether the buffer is a well-formed packet, which is required for all other methods not to panic.
+    pub fn validate(buffer: &[u8]) -> Result<(), ProtocolError> {
+        if buffer.len() < PacketHeader::len() {
+            return Err(ProtocolError::MalformedPacket(format!(
+                "too short ({} bytes)",
+                buffer.len()
+            )));
+        }
+        let header: PacketHeader = bincode::deserialize(&buffer[..PacketHeader::len()])
+            .map_err(|err| ProtocolError::MalformedPacket(err.to_string()))?;
+        if header.version != PROTOCOL_VERSION {
+            return Err(ProtocolError::MalformedPacket(format!(
+                "invalid version {}",
+                header.version
+            )));
+        }
+        if header.length as usize != buffer.len() - PacketHeader::len() {
+            return Err(ProtocolError::MalformedPacket(format!(
+                "length {} doesn't match the actual data length {}",
+                header.length,
+                buffer.len() - PacketHeader::len()
+            )));
+        }
+        let kinds = [
+            PacketFlags::REQUEST,
+            PacketFlags::RESPONSE,
+            PacketFlags::CONTROL,
+        ];
+        let only_with = |flag, kind| header.flags.contains(flag) && !header.flags.contains(kind);
+        if kinds.iter().filter(|x| header.flags.contains(**x)).count() != 1
+            || only_with(PacketFlags::ONEWAY, PacketFlags::REQUEST)
+            || only_with(PacketFlags::DEADLINE, PacketFlags::REQUEST)
+            || only_with(PacketFlags::METADATA, PacketFlags::REQUEST)
+            || only_with(PacketFlags::CANCEL, PacketFlags::CONTROL)
+        {
+            return Err(ProtocolError::MalformedPacket(format!(
+                "invalid flags {:?}",
+                header.flags
+            )));
+        }
+        let offset = metadata_offset(header.flags);
+        let too_short =
+            || ProtocolError::MalformedPacket("too short for the extensions".to_owned());
+        if buffer.len() < offset {
+            return Err(too_short());
+        }
+        if header.flags.contains(PacketFlags::METADATA) {
+            let length = buffer
+                .get(offset..offset + METADATA_LENGTH_SIZE)
+                .ok_or_else(too_short)?;
+            let length = u32::from_le_bytes([length[0], length[1], length[2], length[3]]) as usize;
+            let start = offset + METADATA_LENGTH_SIZE;
+            let metadata = buffer.get(start..start + length).ok_or_else(too_short)?;
+            bincode::deserialize::<CallMetadata>(metadata).map_err(|err| {
+                ProtocolError::MalformedPacket(format!("invalid metadata: {}", err))
+            })?;
+        }
+        Ok(())
+    }
+
     pub fn header(&self) -> &'a [u8] {
         &self.buffer[0..PacketHeader::len()]
     }
 
     pub fn data(&self) -> &'a [u8] {
-        &self.buffer[PacketHeader::len()..]
+        let header = PacketHeader::from_buffer(self.buffer);
+        let start = match self.metadata_bytes() {
+            Some(metadata) => metadata_offset(header.flags) + METADATA_LENGTH_SIZE + metadata.len(),
+            None => metadata_offset(header.flags),
+        };
+        &self.buffer[start..PacketHeader::len() + header.length as usize]
+    }
+
+    fn metadata_bytes(&self) -> Option<&'a [u8]> {
+        let flags = self.flags();
+        if !flags.contains(PacketFlags::METADATA) {
+            return None;
+        }
+        let offset = metadata_offset(flags);
+        let mut length = [0u8; METADATA_LENGTH_SIZE];
+        length.copy_from_slice(&self.buffer[offset..offset + METADATA_LENGTH_SIZE]);
+        let start = offset + METADATA_LENGTH_SIZE;
+        Some(&self.buffer[start..start + u32::from_le_bytes(length) as usize])
+    }
+
+    /// The metadata that the caller has given to this request.
+    pub fn metadata(&self) -> Option<CallMetadata> {
+        self.metadata_bytes()
+            .map(|metadata| bincode::deserialize(metadata).expect("It is validated"))
+    }
+
+    /// The time left for the call that the caller has given to this request.
+    pub fn deadline(&self) -> Option<time::Duration> {
+        if !self.flags().contains(PacketFlags::DEADLINE) {
+            return None;
+        }
+        let mut micros = [0u8; DEADLINE_SIZE];
+        micros.copy_from_slice(
+            &self.buffer[PacketHeader::len()..PacketHeader::len() + DEADLINE_SIZE],
+        );
+        Some(time::Duration::from_micros(u64::from_le_bytes(micros)))
+    }
+
+    pub fn version(&self) -> u8 {
+        PacketHeader::from_buffer(self.buffer).version
+    }
+
+    pub fn flags(&self) -> PacketFlags {
+        PacketHeader::from_buffer(self.buffer).flags
     }
 
     pub fn slot(&self) -> SlotId {
@@ -149,6 +358,56 @@ impl<'a> PacketView<'a> {
         header.method
     }
 
+    /// Whether this is a request that doesn't expect a response.
+    pub fn is_oneway(&self) -> bool {
+        self.flags().contains(PacketFlags::ONEWAY)
+    }
+
+    /// Whether this is a request to cancel the call of the same slot.
+    pub fn is_cancel(&self) -> bool {
+        self.flags().contains(PacketFlags::CANCEL)
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.flags().contains(PacketFlags::ERROR)
+    }
+
+    /// Decodes the error that an error response carries.
+    pub fn error(&self) -> RemoteError {
+        bincode::deserialize(self.data()).unwrap_or(RemoteError::InvalidData)
+    }
+
+    /// Checks whether this is a valid handshake packet of the same protocol version,
+    /// and whether the initial service of the other end can be imported as `initial_service`.
+    pub fn check_handshake(&self, initial_service: &ServiceSignature) -> Result<(), String> {
+        let buffer = self.buffer;
+        // The handshake packet always starts with the version, followed by the flags.
+        if buffer.len() < 2 || !PacketFlags(buffer[1]).contains(PacketFlags::CONTROL) {
+            return Err(
+                "the other end didn't start with a handshake. It might be using the protocol version 1"
+                    .to_owned(),
+            );
+        }
+        if buffer[0] != PROTOCOL_VERSION {
+            return Err(format!(
+                "protocol version mismatch: ours is {} but the other end's is {}",
+                PROTOCOL_VERSION, buffer[0]
+            ));
+        }
+        if Self::validate(buffer).is_err() || !self.data().starts_with(HANDSHAKE_MAGIC) {
+            return Err("the other end sent an invalid handshake".to_owned());
+        }
+        let exported = &self.data()[HANDSHAKE_MAGIC.len()..];
+        if exported.is_empty() {
+            return Ok(());
+        }
+        let exported: ServiceSignature = bincode::deserialize(exported)
+            .map_err(|_| "the other end sent an invalid handshake".to_owned())?;
+        initial_service
+            .check_compatibility(&exported)
+            .map_err(|reason| format!("the initial service is incompatible: {}", reason))
+    }
+
     pub fn to_vec(&self) -> Vec<u8> {
         self.buffer.to_vec()
     }
@@ -175,30 +434,64 @@ impl Packet {
         Self { buffer }
     }
 
-    pub fn new_response_from_request(request: PacketView) -> Self {
-        let buffer = request.header().to_vec();
+    fn new_with_header(header: PacketHeader, data: &[u8]) -> Self {
+        let mut buffer = vec![0_u8; PacketHeader::len()];
+        header.write(&mut buffer);
         let mut packet = Self { buffer };
+        packet.append_data(data);
+        packet
+    }
 
-        let mut header = packet.header();
-        header.slot.change_to_response();
-        header.write(&mut packet.buffer);
+    pub fn new_response_from_request(request: PacketView) -> Self {
+        let mut header = PacketHeader::from_buffer(request.buffer);
+        header.flags = PacketFlags::RESPONSE;
+        header.length = 0;
+        Self::new_with_header(header, &[])
+    }
 
+    pub fn new_error_response_from_request(request: PacketView, error: &RemoteError) -> Self {
+        let mut packet = Self::new_response_from_request(request);
+        packet.insert_flags(PacketFlags::ERROR);
+        packet.append_data(&bincode::serialize(error).unwrap());
         packet
     }
 
     pub fn new_request(service_object_id: ServiceObjectId, method: MethodId, args: &[u8]) -> Self {
-        let mut buffer = vec![0_u8; PacketHeader::len() + args.len()];
-        let header = PacketHeader::new(SlotId::new_request(), service_object_id, method);
-        header.write(&mut buffer);
-        buffer[PacketHeader::len()..].copy_from_slice(args);
-        Self { buffer }
+        let header = PacketHeader::new(
+            PacketFlags::REQUEST,
+            SlotId::new_request(),
+            service_object_id,
+            method,
+        );
+        Self::new_with_header(header, args)
+    }
+
+    /// Creates a packet that cancels the request, which must have its slot set.
+    pub fn new_cancel(request: PacketView) -> Self {
+        let mut header = PacketHeader::from_buffer(request.buffer);
+        header.flags = PacketFlags::CONTROL;
+        header.flags.insert(PacketFlags::CANCEL);
+        header.length = 0;
+        Self::new_with_header(header, &[])
+    }
+
+    /// Creates a handshake packet, which must be the first packet to send.
+    ///
+    /// It carries the signature of the initial service, so that the other end can check it before importing.
+    pub fn new_handshake(initial_service: Option<&ServiceSignature>) -> Self {
+        let header = PacketHeader::new(PacketFlags::CONTROL, SlotId::new_request(), 0, 0);
+        let mut data = HANDSHAKE_MAGIC.to_vec();
+        if let Some(initial_service) = initial_service {
+            data.extend(bincode::serialize(initial_service).expect("It is always serializable"));
+        }
+        Self::new_with_header(header, &data)
     }
 
     pub fn buffer(&self) -> &[u8] {
         &self.buffer
     }
 
-    pub fn view(&self) -> PacketView {
+    pub fn view(&self) -> PacketView<'_> {
         PacketView::new(&self.buffer)
     }
 
@@ -213,6 +506,9 @@ impl Packet {
     // FIXME: Use Cursor to reduce data copy
     pub fn append_data(&mut self, data: &[u8]) {
         self.buffer.extend_from_slice(data);
+        let mut header = self.header();
+        header.length += data.len() as u32;
+        header.write(&mut self.buffer);
     }
 
     pub fn set_slot(&mut self, slot_id: SlotId) {
@@ -221,7 +517,209 @@ impl Packet {
         header.write(&mut self.buffer);
     }
 
+    /// Gives the request the time left for the call, which must be done only once.
+    pub fn set_deadline(&mut self, remaining: time::Duration) {
+        let mut header = self.header();
+        assert!(!header.flags.contains(PacketFlags::DEADLINE));
+        header.flags.insert(PacketFlags::DEADLINE);
+        header.length += DEADLINE_SIZE as u32;
+        header.write(&mut self.buffer);
+        let micros = remaining.as_micros().min(u64::MAX as u128) as u64;
+        let at = PacketHeader::len();
+        self.buffer
+            .splice(at..at, micros.to_le_bytes().iter().copied());
+    }
+
+    /// Gives the request the call metadata, which must be done only once.
+    pub fn set_metadata(&mut self, metadata: &CallMetadata) {
+        let bytes = bincode::serialize(metadata).unwrap();
+        let mut header = self.header();
+        assert!(!header.flags.contains(PacketFlags::METADATA));
+        header.flags.insert(PacketFlags::METADATA);
+        header.length += (METADATA_LENGTH_SIZE + bytes.len()) as u32;
+        header.write(&mut self.buffer);
+        let at = metadata_offset(header.flags);
+        let length = (bytes.len() as u32).to_le_bytes();
+        self.buffer
+            .splice(at..at, length.iter().chain(bytes.iter()).copied());
+    }
+
+    pub fn insert_flags(&mut self, flags: PacketFlags) {
+        let mut header = self.header();
+        header.flags.insert(flags);
+        header.write(&mut self.buffer);
+    }
+
     pub fn into_vec(self) -> Vec<u8> {
         self.buffer
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_and_response() {
+        let mut request = Packet::new_request(3, 7, &[1, 2, 3]);
+        request.set_slot(SlotId::new(5));
+        assert!(request.view().flags().contains(PacketFlags::REQUEST));
+        assert_eq!(request.view().version(), PROTOCOL_VERSION);
+        assert_eq!(request.data(), &[1, 2, 3]);
+
+        let mut response = Packet::new_response_from_request(request.view());
+        response.append_data(&[4, 5]);
+        let view = response.view();
+        assert_eq!(view.flags(), PacketFlags::RESPONSE);
+        assert_eq!(view.slot().as_raw(), 5);
+        assert_eq!(view.object_id(), 3);
+        assert_eq!(view.method(), 7);
+        assert_eq!(view.data(), &[4, 5]);
+
+        let error = Packet::new_error_response_from_request(request.view(), &RemoteError::TimeOut);
+        assert!(error.view().is_error());
+        assert_eq!(error.view().error(), RemoteError::TimeOut);
+    }
+
+    #[test]
+    fn validate() {
+        let request = Packet::new_request(3, 7, &[1, 2, 3]);
+        assert!(PacketView::validate(request.buffer()).is_ok());
+        assert!(PacketView::validate(Packet::new_handshake(None).buffer()).is_ok());
+
+        assert!(PacketView::validate(&[]).is_err());
+        assert!(PacketView::validate(&request.buffer()[..PacketHeader::len() + 1]).is_err());
+        let mut longer = request.view().to_vec();
+        longer.push(0);
+        assert!(PacketView::validate(&longer).is_err());
+
+        let mut other_version = request.view().to_vec();
+        other_version[0] = PROTOCOL_VERSION + 1;
+        assert!(PacketView::validate(&other_version).is_err());
+
+        let mut both = Packet::new_request(3, 7, &[]);
+        both.insert_flags(PacketFlags::RESPONSE);
+        assert!(PacketView::validate(both.buffer()).is_err());
+
+        let mut oneway = Packet::new_request(3, 7, &[]);
+        oneway.insert_flags(PacketFlags::ONEWAY);
+        assert!(PacketView::validate(oneway.buffer()).is_ok());
+        assert!(oneway.view().is_oneway());
+        let mut oneway_response = Packet::new_response_from_request(request.view());
+        oneway_response.insert_flags(PacketFlags::ONEWAY);
+        assert!(PacketView::validate(oneway_response.buffer()).is_err());
+
+        let mut deadline = Packet::new_request(3, 7, &[]);
+        deadline.insert_flags(PacketFlags::DEADLINE);
+        assert!(PacketView::validate(deadline.buffer()).is_err());
+        let mut cancel = Packet::new_request(3, 7, &[]);
+        cancel.insert_flags(PacketFlags::CANCEL);
+        assert!(PacketView::validate(cancel.buffer()).is_err());
+    }
+
+    #[test]
+    fn deadline_and_cancel() {
+        let mut request = Packet::new_request(3, 7, &[1, 2, 3]);
+        request.set_slot(SlotId::new(5));
+        assert_eq!(request.view().deadline(), None);
+        request.set_deadline(time::Duration::from_millis(1500));
+        request.append_data(&[4]);
+        assert!(PacketView::validate(request.buffer()).is_ok());
+        assert_eq!(
+            request.view().deadline(),
+            Some(time::Duration::from_millis(1500))
+        );
+        assert_eq!(request.data(), &[1, 2, 3, 4]);
+
+        let response = Packet::new_response_from_request(request.view());
+        assert_eq!(response.view().deadline(), None);
+        assert!(response.data().is_empty());
+
+        let cancel = Packet::new_cancel(request.view());
+        assert!(PacketView::validate(cancel.buffer()).is_ok());
+        assert!(cancel.view().is_cancel());
+        assert_eq!(cancel.view().slot().as_raw(), 5);
+        assert!(cancel.data().is_empty());
+    }
+
+    #[test]
+    fn metadata() {
+        let mut metadata = CallMetadata::new();
+        metadata.insert("trace-id".to_owned(), "1234".to_owned());
+
+        // It works in any order with the deadline.
+        let mut first = Packet::new_request(3, 7, &[1, 2, 3]);
+        first.set_metadata(&metadata);
+        first.set_deadline(time::Duration::from_millis(10));
+        let mut second = Packet::new_request(3, 7, &[1, 2, 3]);
+        second.set_deadline(time::Duration::from_millis(10));
+        second.set_metadata(&metadata);
+        for packet in [first, second].iter() {
+            assert!(PacketView::validate(packet.buffer()).is_ok());
+            assert_eq!(packet.view().metadata(), Some(metadata.clone()));
+            assert_eq!(
+                packet.view().deadline(),
+                Some(time::Duration::from_millis(10))
+            );
+            assert_eq!(packet.data(), &[1, 2, 3]);
+        }
+
+        let request = Packet::new_request(3, 7, &[1, 2, 3]);
+        assert_eq!(request.view().metadata(), None);
+        let response = Packet::new_response_from_request(request.view());
+        assert_eq!(response.view().metadata(), None);
+
+        let mut broken = Packet::new_request(3, 7, &[100, 0, 0, 0, 1]);
+        broken.insert_flags(PacketFlags::METADATA);
+        assert!(PacketView::validate(broken.buffer()).is_err());
+    }
+
+    #[test]
+    fn handshake() {
+        let null = ServiceSignature::new("NullService", vec![]);
+        assert!(Packet::new_handshake(None)
+            .view()
+            .check_handshake(&null)
+            .is_ok());
+        assert!(Packet::new_request(3, 7, &[])
+            .view()
+            .check_handshake(&null)
+            .is_err());
+
+        let mut other_version = Packet::new_handshake(None).into_vec();
+        other_version[0] = PROTOCOL_VERSION + 1;
+        assert!(PacketView::new(&other_version)
+            .check_handshake(&null)
+            .unwrap_err()
+            .contains("mismatch"));
+    }
+
+    #[test]
+    fn handshake_with_initial_service() {
+        use crate::MethodSignature;
+
+        let null = ServiceSignature::new("NullService", vec![]);
+        let store = ServiceSignature::new(
+            "Store",
+            vec![MethodSignature::new(70, "buy", &["u64"], "u64")],
+        );
+        let handshake = Packet::new_handshake(Some(&store));
+        assert!(handshake.view().check_handshake(&null).is_ok());
+        assert!(handshake.view().check_handshake(&store).is_ok());
+        // It can't be checked if the other end doesn't tell.
+        assert!(Packet::new_handshake(None)
+            .view()
+            .check_handshake(&store)
+            .is_ok());
+
+        let cart = ServiceSignature::new(
+            "Cart",
+            vec![MethodSignature::new(70, "add", &["u64"], "u64")],
+        );
+        assert!(handshake
+            .view()
+            .check_handshake(&cart)
+            .unwrap_err()
+            .contains("incompatible"));
+    }
+}
@@ -4,7 +4,13 @@ pub mod types;
 
 pub use self::types::Handler;
 use crate::forwarder::ServiceForwarder;
-use crate::forwarder::{ServiceObjectId, DELETE_REQUEST};
+use crate::forwarder::{
+    ServiceObjectId, DELETE_REQUEST, META_SERVICE_OBJECT_ID, MINT_HANDOFF_REQUEST,
+    REDEEM_HANDOFF_REQUEST, RETAIN_REQUEST, REVOKE_HANDOFF_REQUEST,
+};
+use crate::handoff::HandoffToken;
+use crate::interceptor::Interceptor;
+use crate::metrics::MetricsRecorder;
 use crate::packet::{Packet, PacketView};
 use crate::raw_exchange::{HandleToExchange, Skeleton};
 use crate::service::*;
@@ -16,9 +22,69 @@ use std::sync::{
 };
 
 pub trait Port: std::fmt::Debug + Send + Sync + 'static {
-    fn call(&self, packet: PacketView) -> Packet;
+    fn call(&self, packet: PacketView) -> Result<Packet, RemoteError>;
+    /// Sends the request right away, and returns a future that resolves with the response.
+    ///
+    /// The default implementation just blocks on `call()`.
+    fn call_async(&self, packet: PacketView) -> BoxFuture<'static, Result<Packet, RemoteError>> {
+        Box::pin(std::future::ready(self.call(packet)))
+    }
+    /// Sends a request that doesn't expect a response, without taking a call slot.
+    ///
+    /// The default implementation just waits for the response of `call()`.
+    fn send_oneway(&self, packet: PacketView) -> Result<(), RemoteError> {
+        self.call(packet).map(|_| ())
+    }
+    /// Interceptors that wrap the calls made through this port.
+    fn client_interceptors(&self) -> &[Arc<dyn Interceptor>] {
+        &[]
+    }
+    /// Where the metrics of the calls made through this port are recorded.
+    fn metrics(&self) -> Option<Arc<MetricsRecorder>> {
+        None
+    }
     fn delete_request(&self, id: ServiceObjectId);
+    /// Adds a reference to a service object imported through this port,
+    /// so that the other side deletes it only after one more delete request.
+    fn retain_request(&self, id: ServiceObjectId) -> Result<(), RemoteError> {
+        let packet = Packet::new_request(id, RETAIN_REQUEST, &[]);
+        self.call(packet.view()).map(|_| ())
+    }
+    /// Asks the other side to mint a token for a service object imported through this port.
+    fn mint_handoff_token(&self, id: ServiceObjectId) -> Result<HandoffToken, RemoteError> {
+        let packet = Packet::new_request(id, MINT_HANDOFF_REQUEST, &[]);
+        HandoffToken::from_bytes(self.call(packet.view())?.data()).ok_or(RemoteError::InvalidData)
+    }
+    /// Asks the other side to export the service object of a token, which is then imported through this port.
+    fn redeem_handoff_token(&self, token: HandoffToken) -> Result<HandleToExchange, RemoteError> {
+        let packet = Packet::new_request(
+            META_SERVICE_OBJECT_ID,
+            REDEEM_HANDOFF_REQUEST,
+            &token.to_bytes(),
+        );
+        let response = self.call(packet.view())?;
+        let mut id = [0; 4];
+        if response.data().len() != id.len() {
+            return Err(RemoteError::InvalidData);
+        }
+        id.copy_from_slice(response.data());
+        Ok(HandleToExchange(ServiceObjectId::from_le_bytes(id)))
+    }
+    /// Asks the other side to discard a token for a service object imported through this port,
+    /// which is passed on to the exporter if the service is forwarded by the other side.
+    fn revoke_handoff_token(
+        &self,
+        id: ServiceObjectId,
+        token: HandoffToken,
+    ) -> Result<(), RemoteError> {
+        let packet = Packet::new_request(id, REVOKE_HANDOFF_REQUEST, &token.to_bytes());
+        self.call(packet.view()).map(|_| ())
+    }
     fn register_service(&self, service_object: Arc<dyn Dispatch>) -> HandleToExchange;
+    /// The registry of the service objects exported through this port, which the meta service tells the other side about.
+    fn registry(&self) -> Option<Arc<ServiceForwarder>> {
+        None
+    }
 }
 
 /// Weak::new() is not implemented for ?Sized.
@@ -36,30 +102,49 @@ pub struct BasicPort {
     /// This is useful when the port-port connection is terminating and you don't really
     /// care about the garabage collection.
     no_drop: AtomicBool,
+    client_interceptors: Vec<Arc<dyn Interceptor>>,
 }
 
 impl Port for BasicPort {
-    fn call(&self, packet: PacketView) -> Packet {
+    fn call(&self, packet: PacketView) -> Result<Packet, RemoteError> {
         self.client.as_ref().unwrap().call(packet)
     }
 
+    fn call_async(&self, packet: PacketView) -> BoxFuture<'static, Result<Packet, RemoteError>> {
+        self.client.as_ref().unwrap().call_async(packet)
+    }
+
+    fn send_oneway(&self, packet: PacketView) -> Result<(), RemoteError> {
+        self.client.as_ref().unwrap().send_oneway(packet)
+    }
+
+    fn client_interceptors(&self) -> &[Arc<dyn Interceptor>] {
+        &self.client_interceptors
+    }
+
+    fn metrics(&self) -> Option<Arc<MetricsRecorder>> {
+        Some(Arc::clone(self.registry.metrics()))
+    }
+
     fn delete_request(&self, id: ServiceObjectId) {
         if self.no_drop.load(Ordering::SeqCst) {
             return;
         }
         let packet = Packet::new_request(id, DELETE_REQUEST, &[]);
-        assert!(self
-            .client
-            .as_ref()
-            .unwrap()
-            .call(packet.view())
-            .data()
-            .is_empty());
+        match self.client.as_ref().unwrap().call_detached(packet.view()) {
+            Ok(response) => assert!(response.data().is_empty()),
+            // The other side can't use the object anymore anyway.
+            Err(err) => warn!("Failed to request delete of {}: {}", id, err),
+        }
     }
 
     fn register_service(&self, service_object: Arc<dyn Dispatch>) -> HandleToExchange {
         HandleToExchange(self.registry.register_service_object(service_object))
     }
+
+    fn registry(&self) -> Option<Arc<ServiceForwarder>> {
+        Some(Arc::clone(&self.registry))
+    }
 }
 
 impl BasicPort {
@@ -69,10 +154,12 @@ impl BasicPort {
         meta_sevice: Skeleton,
         initial_service: Skeleton,
     ) -> Arc<Self> {
+        let client_interceptors = config.client_interceptors.clone();
         let arc = Arc::new(Self {
             registry: Arc::new(ServiceForwarder::new(config, meta_sevice, initial_service)),
             client: Some(client),
             no_drop: AtomicBool::new(false),
+            client_interceptors,
         });
         let arc2 = arc.clone() as Arc<dyn Port>;
         arc.registry.set_port(Arc::downgrade(&arc2));
@@ -83,6 +170,10 @@ impl BasicPort {
         self.registry.clone()
     }
 
+    pub fn call_slots_in_use(&self) -> usize {
+        self.client.as_ref().unwrap().call_slots_in_use()
+    }
+
     pub fn clear_registry(&self) {
         self.registry.clear();
     }
@@ -1,10 +1,18 @@
-use crate::packet::{Packet, PacketView, SlotId};
-use crate::queue::Queue;
+use crate::cancel;
+use crate::packet::{
+    Packet, PacketFlags, PacketView, ProtocolError, ProtocolErrorReporter, SlotId,
+};
+use crate::queue::{PopError, Queue};
+use crate::service::{BoxFuture, RemoteError};
 use crate::transport::{TransportError, TransportRecv, TransportSend};
 use crate::Config;
 use crossbeam::channel::RecvTimeoutError::{Disconnected, Timeout};
-use crossbeam::channel::{bounded, Receiver, Sender};
+use crossbeam::channel::{bounded, Receiver, Sender, TryRecvError};
+use parking_lot::Mutex;
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{self, Poll, Waker};
 use std::thread;
 use std::time;
 
@@ -15,10 +23,22 @@ struct CallSlot {
     response: Receiver<Result<Packet, TransportError>>,
 }
 
+/// Call slots that are waiting for their responses, and the error that has stopped the receiver.
+#[derive(Debug)]
+struct SlotStates {
+    active: Vec<bool>,
+    /// Wakers of the pending futures made by `Client::call_async()`.
+    wakers: Vec<Option<Waker>>,
+    /// Slots whose calls are cancelled. Each of them is reused once its response arrives.
+    abandoned: Vec<Option<CallSlot>>,
+    failure: Option<TransportError>,
+}
+
 #[derive(Debug)]
 pub struct Client {
     config: Config,
     call_slots: Arc<Queue<CallSlot>>,
+    slot_states: Arc<Mutex<SlotStates>>,
     transport_send: Arc<dyn TransportSend>,
     receiver_thread: Option<thread::JoinHandle<()>>,
     joined_event_receiver: Receiver<()>,
@@ -29,6 +49,7 @@ impl Client {
         config: Config,
         transport_send: Arc<dyn TransportSend>,
         transport_recv: Box<dyn TransportRecv>,
+        protocol_error: ProtocolErrorReporter,
     ) -> Self {
         let (joined_event_sender, joined_event_receiver) = bounded(1);
         let callslot_size = SlotId::new(config.call_slots as u32);
@@ -46,17 +67,32 @@ impl Client {
             to_slot_receivers.push(send_to_slot_recv);
         }
 
+        let slot_states = Arc::new(Mutex::new(SlotStates {
+            active: vec![false; callslot_size.as_usize()],
+            wakers: vec![None; callslot_size.as_usize()],
+            abandoned: (0..callslot_size.as_usize()).map(|_| None).collect(),
+            failure: None,
+        }));
         let name = config.name.clone();
+        let call_slots_ = Arc::clone(&call_slots);
+        let slot_states_ = Arc::clone(&slot_states);
 
         Client {
             config,
             call_slots,
+            slot_states,
             transport_send,
             receiver_thread: Some(
                 thread::Builder::new()
                     .name(format!("[{}] client", name))
                     .spawn(move || {
-                        receive_loop(transport_recv, to_slot_receivers);
+                        receive_loop(
+                            transport_recv,
+                            to_slot_receivers,
+                            call_slots_,
+                            slot_states_,
+                            protocol_error,
+                        );
                         joined_event_sender.send(()).unwrap();
                     })
                     .unwrap(),
@@ -65,35 +101,135 @@ impl Client {
         }
     }
 
-    pub fn call(&self, packet: PacketView) -> Packet {
-        // TODO: handle the error
-        let slot = self
-            .call_slots
-            .pop(self.config.call_timeout)
-            .expect("Too many calls on port");
+    /// Makes a call, which is limited by the deadline of the call that the current thread is handling.
+    pub fn call(&self, packet: PacketView) -> Result<Packet, RemoteError> {
+        self.call_with_timeout(packet, cancel::inherited_timeout(self.config.call_timeout))
+    }
 
-        let packet = {
-            let mut packet = packet.to_owned();
-            packet.set_slot(slot.id.into_request());
-            packet
+    /// Makes a call regardless of the deadline of the call being handled, like a delete request that must be made anyway.
+    pub fn call_detached(&self, packet: PacketView) -> Result<Packet, RemoteError> {
+        self.call_with_timeout(packet, self.config.call_timeout)
+    }
+
+    fn call_with_timeout(
+        &self,
+        packet: PacketView,
+        timeout: Option<time::Duration>,
+    ) -> Result<Packet, RemoteError> {
+        let (slot, cancel) = self.send_request(packet, timeout, timeout)?;
+
+        let response_packet = if let Some(timeout) = timeout {
+            match slot.response.recv_timeout(timeout) {
+                Ok(x) => x,
+                Err(Timeout) => {
+                    warn!("{} is cancelled due to the timeout", slot.id);
+                    send_cancel(&*self.transport_send, &cancel, self.config.call_timeout);
+                    abandon_slot(&self.call_slots, &self.slot_states, slot);
+                    return Err(RemoteError::TimeOut);
+                }
+                Err(Disconnected) => return Err(RemoteError::Disconnected),
+            }
+        } else {
+            match slot.response.recv() {
+                Ok(x) => x,
+                Err(_) => return Err(RemoteError::Disconnected),
+            }
         };
 
-        // TODO: handle the error
+        release_slot(&self.call_slots, &self.slot_states, slot);
+        check_response(response_packet)
+    }
+
+    /// Sends the request, and returns a future that resolves when the response arrives.
+    ///
+    /// `Config::call_timeout` is applied only to sending the request, while the deadline of the call
+    /// that the current thread is handling is given to the request.
+    /// If the future is dropped before it resolves, the call is cancelled just like a timed-out one.
+    pub fn call_async(
+        &self,
+        packet: PacketView,
+    ) -> BoxFuture<'static, Result<Packet, RemoteError>> {
+        let deadline = cancel::inherited_timeout(None);
+        let timeout = cancel::inherited_timeout(self.config.call_timeout);
+        match self.send_request(packet, deadline, timeout) {
+            Ok((slot, cancel)) => Box::pin(ReplyFuture {
+                slot: Some(slot),
+                cancel,
+                call_slots: Arc::clone(&self.call_slots),
+                slot_states: Arc::clone(&self.slot_states),
+                transport_send: Arc::clone(&self.transport_send),
+                send_timeout: self.config.call_timeout,
+            }),
+            Err(err) => Box::pin(std::future::ready(Err(err))),
+        }
+    }
+
+    /// Sends a request with the `ONEWAY` flag, which doesn't take a slot since no response will come.
+    pub fn send_oneway(&self, packet: PacketView) -> Result<(), RemoteError> {
+        // The receiver has stopped, so the connection is broken.
+        if let Some(err) = self.slot_states.lock().failure.clone() {
+            return Err(err.into());
+        }
+        let mut packet = packet.to_owned();
+        packet.insert_flags(PacketFlags::ONEWAY);
         self.transport_send
             .send(packet.buffer(), self.config.call_timeout)
-            .unwrap();
-        let response_packet = slot.response.recv().expect(
-            "counterparty send is managed by client. \n\
-        This error might be due to drop after disconnection of the two remote-trait-object contexts. \n\
-        Please consider disable_garbage_collection() or explicit drop for the imported services.",
-        );
+            .map_err(Into::into)
+    }
+
+    /// Takes a free slot and sends the request with it, giving it the deadline.
+    /// The slot is active on success, and returned with the packet that cancels the call.
+    fn send_request(
+        &self,
+        packet: PacketView,
+        deadline: Option<time::Duration>,
+        timeout: Option<time::Duration>,
+    ) -> Result<(CallSlot, Packet), RemoteError> {
+        if deadline == Some(time::Duration::from_secs(0)) {
+            return Err(RemoteError::TimeOut);
+        }
+        let slot = self.call_slots.pop(timeout).map_err(|err| match err {
+            PopError::Timeout => RemoteError::TooManyCalls,
+            PopError::QueueClosed => RemoteError::Disconnected,
+        })?;
+
+        {
+            let mut slot_states = self.slot_states.lock();
+            // The receiver has stopped, so no response will ever come.
+            if let Some(err) = slot_states.failure.clone() {
+                drop(slot_states);
+                self.call_slots
+                    .push(slot)
+                    .expect("Client does not close the queue");
+                return Err(err.into());
+            }
+            slot_states.active[slot.id.as_usize()] = true;
+        }
+
+        let packet = {
+            let mut packet = packet.to_owned();
+            packet.set_slot(slot.id);
+            if let Some(deadline) = deadline {
+                packet.set_deadline(deadline);
+            }
+            packet
+        };
 
-        self.call_slots
-            .push(slot)
-            .expect("Client does not close the queue");
+        if let Err(err) = self.transport_send.send(packet.buffer(), timeout) {
+            release_slot(&self.call_slots, &self.slot_states, slot);
+            return Err(err.into());
+        }
+        Ok((slot, Packet::new_cancel(packet.view())))
+    }
 
-        // TODO: handle the error
-        response_packet.unwrap()
+    /// Number of the slots that are taken by calls, including the cancelled ones waiting for their responses.
+    pub fn call_slots_in_use(&self) -> usize {
+        self.slot_states
+            .lock()
+            .active
+            .iter()
+            .filter(|active| **active)
+            .count()
     }
 
     pub fn shutdown(&mut self) {
@@ -115,6 +251,108 @@ impl Client {
     }
 }
 
+/// Returns a slot, which is done with its call, back to the queue.
+fn release_slot(call_slots: &Queue<CallSlot>, slot_states: &Mutex<SlotStates>, slot: CallSlot) {
+    {
+        let mut slot_states = slot_states.lock();
+        slot_states.active[slot.id.as_usize()] = false;
+        slot_states.wakers[slot.id.as_usize()] = None;
+    }
+    call_slots
+        .push(slot)
+        .expect("Client does not close the queue");
+}
+
+/// Gives up a slot whose call is cancelled, which is reused once the response arrives.
+fn abandon_slot(call_slots: &Queue<CallSlot>, slot_states: &Mutex<SlotStates>, slot: CallSlot) {
+    let mut states = slot_states.lock();
+    let id = slot.id.as_usize();
+    states.wakers[id] = None;
+    match slot.response.try_recv() {
+        Err(TryRecvError::Empty) => states.abandoned[id] = Some(slot),
+        // The response has arrived just now.
+        Ok(_) => {
+            states.active[id] = false;
+            drop(states);
+            call_slots
+                .push(slot)
+                .expect("Client does not close the queue");
+        }
+        // The connection is broken, so the slot won't be used anymore anyway.
+        Err(TryRecvError::Disconnected) => (),
+    }
+}
+
+/// Sends the packet that cancels a call.
+///
+/// This must be done before the slot is abandoned, since the exporter tells calls apart only by their slots.
+/// Otherwise a late response could recycle the slot, and the cancellation would hit the next call on it.
+fn send_cancel(
+    transport_send: &dyn TransportSend,
+    cancel: &Packet,
+    timeout: Option<time::Duration>,
+) {
+    if let Err(err) = transport_send.send(cancel.buffer(), timeout) {
+        debug!("Failed to send {}: {:?}", cancel, err);
+    }
+}
+
+fn check_response(response_packet: Result<Packet, TransportError>) -> Result<Packet, RemoteError> {
+    let response_packet = response_packet?;
+    if response_packet.view().is_error() {
+        return Err(response_packet.view().error());
+    }
+    Ok(response_packet)
+}
+
+/// The future of a call made by `Client::call_async()`.
+struct ReplyFuture {
+    /// This is None once the response is taken.
+    slot: Option<CallSlot>,
+    cancel: Packet,
+    call_slots: Arc<Queue<CallSlot>>,
+    slot_states: Arc<Mutex<SlotStates>>,
+    transport_send: Arc<dyn TransportSend>,
+    send_timeout: Option<time::Duration>,
+}
+
+impl Future for ReplyFuture {
+    type Output = Result<Packet, RemoteError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let slot = self
+            .slot
+            .as_ref()
+            .expect("ReplyFuture is polled after completion");
+        // The waker must be registered before checking the slot, not to miss a response in between.
+        self.slot_states.lock().wakers[slot.id.as_usize()] = Some(cx.waker().clone());
+        let response_packet = match slot.response.try_recv() {
+            Ok(x) => x,
+            Err(TryRecvError::Empty) => {
+                // The receiver has stopped, so no response will ever come.
+                return match self.slot_states.lock().failure.clone() {
+                    Some(err) => Poll::Ready(Err(err.into())),
+                    None => Poll::Pending,
+                };
+            }
+            Err(TryRecvError::Disconnected) => return Poll::Ready(Err(RemoteError::Disconnected)),
+        };
+        let slot = self.slot.take().unwrap();
+        release_slot(&self.call_slots, &self.slot_states, slot);
+        Poll::Ready(check_response(response_packet))
+    }
+}
+
+impl Drop for ReplyFuture {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot.take() {
+            debug!("{} is cancelled since its future is dropped", slot.id);
+            send_cancel(&*self.transport_send, &self.cancel, self.send_timeout);
+            abandon_slot(&self.call_slots, &self.slot_states, slot);
+        }
+    }
+}
+
 impl Drop for Client {
     fn drop(&mut self) {
         assert!(self.receiver_thread.is_none(), "Please call shutdown");
@@ -124,19 +362,60 @@ impl Drop for Client {
 fn receive_loop(
     transport_recv: Box<dyn TransportRecv>,
     to_slot_receivers: Vec<Sender<Result<Packet, TransportError>>>,
+    call_slots: Arc<Queue<CallSlot>>,
+    slot_states: Arc<Mutex<SlotStates>>,
+    protocol_error: ProtocolErrorReporter,
 ) {
     loop {
         match transport_recv.recv(None) {
             Ok(x) => {
                 let packet = Packet::new_from_buffer(x);
                 let slot_id = packet.view().slot();
-                to_slot_receivers[slot_id.as_usize()]
-                    .send(Ok(packet))
-                    .expect("Slot receivers are managed in Client. Client must be dropped after this thread");
+                let to_slot_receiver = match to_slot_receivers.get(slot_id.as_usize()) {
+                    Some(x) => x,
+                    None => {
+                        protocol_error.report(ProtocolError::InvalidSlot(slot_id.as_raw()));
+                        continue;
+                    }
+                };
+                // It is done with the lock, not to race with a caller that gives up the slot.
+                let mut states = slot_states.lock();
+                if let Some(slot) = states.abandoned[slot_id.as_usize()].take() {
+                    debug!("Late response for {} is discarded", slot_id);
+                    states.active[slot_id.as_usize()] = false;
+                    drop(states);
+                    call_slots
+                        .push(slot)
+                        .expect("Client does not close the queue");
+                    continue;
+                }
+                if !states.active[slot_id.as_usize()] {
+                    drop(states);
+                    protocol_error.report(ProtocolError::InvalidSlot(slot_id.as_raw()));
+                    continue;
+                }
+                if to_slot_receiver.try_send(Ok(packet)).is_err() {
+                    debug!("Duplicated response for {} is discarded", slot_id);
+                }
+                let waker = states.wakers[slot_id.as_usize()].take();
+                drop(states);
+                if let Some(waker) = waker {
+                    waker.wake();
+                }
             }
-            Err(TransportError::Termination) => return,
-            Err(_err) => {
-                // TODO: Broadcast the error to all **active** call slots
+            Err(err) => {
+                let mut slot_states = slot_states.lock();
+                // Even for the termination, so that no caller waits for a response that will never come.
+                for (id, _) in slot_states.active.iter().enumerate().filter(|(_, x)| **x) {
+                    // The caller might have given up this slot due to the timeout.
+                    if to_slot_receivers[id].try_send(Err(err.clone())).is_err() {
+                        debug!("Failed to notify {} of the error", SlotId::new(id as u32));
+                    }
+                }
+                for waker in slot_states.wakers.iter_mut().filter_map(Option::take) {
+                    waker.wake();
+                }
+                slot_states.failure = Some(err);
                 return;
             }
         };
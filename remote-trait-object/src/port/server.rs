@@ -1,9 +1,14 @@
 use super::types::Handler;
-use crate::packet::Packet;
+use crate::call::CallInfo;
+use crate::cancel::CancellationToken;
+use crate::packet::{Packet, PacketView};
+use crate::service::{RemoteError, Runtime};
 use crate::transport::{TransportError, TransportRecv, TransportSend};
 use crate::Config;
 use crossbeam::channel::RecvTimeoutError::{Disconnected, Timeout};
 use crossbeam::channel::{self, Receiver};
+use parking_lot::Mutex;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::Arc;
 use std::thread;
@@ -25,10 +30,12 @@ impl Server {
         H: Handler + Send + 'static,
     {
         let (joined_event_sender, joined_event_receiver) = channel::bounded(1);
+        // Calls are handled in the runtime that the context is created in, which drives async service methods.
+        let runtime = Runtime::current();
         let receiver_thread = thread::Builder::new()
             .name(format!("[{}] port server receiver", config.name))
             .spawn(move || {
-                receiver(config, handler, transport_send, transport_recv);
+                receiver(config, runtime, handler, transport_send, transport_recv);
                 joined_event_sender
                     .send(())
                     .expect("Server will be dropped after thread is joined");
@@ -67,25 +74,108 @@ impl Drop for Server {
     }
 }
 
+/// Creates the response packet to a request, from the result of handling it.
+pub(crate) fn create_response(request: PacketView, result: Result<Vec<u8>, RemoteError>) -> Packet {
+    match result {
+        Ok(response) => {
+            let mut response_packet = Packet::new_response_from_request(request);
+            response_packet.append_data(&response);
+            response_packet
+        }
+        Err(err) => {
+            warn!("{} failed: {}", request, err);
+            Packet::new_error_response_from_request(request, &err)
+        }
+    }
+}
+
+/// Calls that are being handled, each of which can be cancelled by the caller.
+///
+/// Each call is answered exactly once, either with its result or with `RemoteError::Cancelled`,
+/// so that the caller can reuse the slot once it gets the response.
+#[derive(Debug, Default)]
+pub(crate) struct OngoingCalls(Mutex<HashMap<u32, CancellationToken>>);
+
+impl OngoingCalls {
+    /// Starts a call, which must be done in the order of the requests and cancellations.
+    pub fn start(&self, request: PacketView) -> CancellationToken {
+        let deadline = request.deadline().map(|x| time::Instant::now() + x);
+        let token = CancellationToken::new(deadline);
+        // The slot might still be taken by a cancelled call that is running, which is not answered anymore.
+        self.0.lock().insert(request.slot().as_raw(), token.clone());
+        token
+    }
+
+    /// Finishes a call, and returns whether it should be answered, which is not the case if it has been cancelled.
+    pub fn finish(&self, request: PacketView, token: &CancellationToken) -> bool {
+        let mut calls = self.0.lock();
+        let slot = request.slot().as_raw();
+        match calls.get(&slot) {
+            Some(x) if x.is_same(token) => {
+                calls.remove(&slot);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Cancels a call, and returns the response that answers it instead.
+    pub fn cancel(&self, cancel: PacketView) -> Option<Packet> {
+        let token = self.0.lock().remove(&cancel.slot().as_raw())?;
+        debug!("{} is cancelled", cancel);
+        token.cancel();
+        Some(Packet::new_error_response_from_request(
+            cancel,
+            &RemoteError::Cancelled,
+        ))
+    }
+}
+
 fn handle_single_call<H: Handler>(
     packet: Packet,
+    call: CallInfo,
     handler: Arc<H>,
     transport_send: Arc<dyn TransportSend>,
+    calls: Arc<OngoingCalls>,
     count: Arc<AtomicI32>,
 ) {
-    let response = handler.handle(packet.view());
-    let mut response_packet = Packet::new_response_from_request(packet.view());
-    response_packet.append_data(&response);
-    if let Err(_err) = transport_send.send(response_packet.buffer(), None) {
-        // TODO: report the error to the context
+    let token = call.cancellation_token().clone();
+    let result = {
+        let _guard = call.enter();
+        handler.handle(packet.view())
+    };
+    if packet.view().is_oneway() {
+        // Nobody waits for the response.
+        if let Err(err) = result {
+            warn!("{} failed: {}", packet, err);
+        }
         count.fetch_sub(1, Ordering::Release);
         return;
-    };
+    }
+    if !calls.finish(packet.view(), &token) {
+        debug!("Result of the cancelled {} is discarded", packet);
+        count.fetch_sub(1, Ordering::Release);
+        return;
+    }
+    let response_packet = create_response(packet.view(), result);
+    match transport_send.send(response_packet.buffer(), None) {
+        Err(err @ TransportError::FrameTooLarge { .. }) => {
+            // Let the caller know, instead of leaving it waiting for the timeout.
+            warn!("Response of {} couldn't be sent: {:?}", packet, err);
+            let error_packet = create_response(packet.view(), Err(RemoteError::from(err)));
+            let _ = transport_send.send(error_packet.buffer(), None);
+        }
+        Err(_err) => {
+            // TODO: report the error to the context
+        }
+        Ok(()) => (),
+    }
     count.fetch_sub(1, Ordering::Release);
 }
 
 fn receiver<H>(
     config: Config,
+    runtime: Runtime,
     handler: Arc<H>,
     transport_send: Arc<dyn TransportSend>,
     transport_recv: Box<dyn TransportRecv>,
@@ -93,19 +183,36 @@ fn receiver<H>(
     H: Handler + 'static,
 {
     let count = Arc::new(AtomicI32::new(0));
+    let calls = Arc::new(OngoingCalls::default());
     loop {
         match transport_recv.recv(None) {
             Ok(request) => {
                 let packet = Packet::new_from_buffer(request);
+                if packet.view().is_cancel() {
+                    if let Some(response) = calls.cancel(packet.view()) {
+                        let _ = transport_send.send(response.buffer(), None);
+                    }
+                    continue;
+                }
+                // This must be done here, not to miss a cancellation that follows right after.
+                let token = if packet.view().is_oneway() {
+                    CancellationToken::new(None)
+                } else {
+                    calls.start(packet.view())
+                };
+                let call = CallInfo::new(&config.name, packet.view(), token);
                 let handler = Arc::clone(&handler);
                 let transport_send = Arc::clone(&transport_send);
+                let calls = Arc::clone(&calls);
+                let runtime = runtime.clone();
 
                 count.fetch_add(1, Ordering::Release);
                 let count = Arc::clone(&count);
-                config
-                    .thread_pool
-                    .lock()
-                    .execute(move || handle_single_call(packet, handler, transport_send, count));
+                config.thread_pool.lock().execute(move || {
+                    runtime.enter(|| {
+                        handle_single_call(packet, call, handler, transport_send, calls, count)
+                    })
+                });
             }
             Err(TransportError::Termination) => break,
             Err(_err) => {
@@ -1,14 +1,15 @@
 use crate::packet::PacketView;
+use crate::service::RemoteError;
 
 pub trait Handler: Send + Sync {
-    fn handle(&self, input: PacketView) -> Vec<u8>;
+    fn handle(&self, input: PacketView) -> Result<Vec<u8>, RemoteError>;
 }
 
 impl<F> Handler for F
 where
-    F: Fn(PacketView) -> Vec<u8> + Send + Sync,
+    F: Fn(PacketView) -> Result<Vec<u8>, RemoteError> + Send + Sync,
 {
-    fn handle(&self, input: PacketView) -> Vec<u8> {
+    fn handle(&self, input: PacketView) -> Result<Vec<u8>, RemoteError> {
         self(input)
     }
 }
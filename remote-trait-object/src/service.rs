@@ -1,28 +1,56 @@
+pub mod error;
 pub mod export_import;
+mod future;
 pub mod handle;
 pub mod id;
 mod null;
+mod relay;
 pub mod serde_support;
+mod signature;
 
 use crate::forwarder::ServiceObjectId;
 use crate::port::Port;
 use std::sync::Weak;
 
+pub use error::RemoteError;
+pub(crate) use future::Runtime;
+pub use future::{block_on, BoxFuture, PendingReply};
 pub use handle::Handle;
 pub use null::{create_null_service, NullService};
+pub use signature::{DescribeService, MethodSignature, ServiceSignature};
 pub type MethodId = u32;
 
 /// Exporter sides's interface to the service object. This will be implemented
 /// by each service trait's unique wrapper in the macro
 pub trait Dispatch: Send + Sync {
-    fn dispatch_and_call(&self, method: MethodId, args: &[u8]) -> Vec<u8>;
+    /// Calls the method with the serialized arguments, and returns the serialized return value.
+    ///
+    /// It must not panic on any input, since both come from the other side.
+    fn dispatch_and_call(&self, method: MethodId, args: &[u8]) -> Result<Vec<u8>, RemoteError>;
+
+    /// Name of the service trait, which is used to describe an error.
+    fn trait_name(&self) -> &'static str {
+        ""
+    }
+
+    /// Name of the method, which is used to describe a call to interceptors.
+    fn method_name(&self, _method: MethodId) -> &'static str {
+        ""
+    }
+
+    /// Signature of the service trait, which the other side checks for the compatibility with its proxy object.
+    ///
+    /// `None` means that it is unknown, and every proxy object is accepted.
+    fn signature(&self) -> Option<ServiceSignature> {
+        None
+    }
 }
 
 impl<F> Dispatch for F
 where
-    F: Fn(MethodId, &[u8]) -> Vec<u8> + Send + Sync,
+    F: Fn(MethodId, &[u8]) -> Result<Vec<u8>, RemoteError> + Send + Sync,
 {
-    fn dispatch_and_call(&self, method: MethodId, args: &[u8]) -> Vec<u8> {
+    fn dispatch_and_call(&self, method: MethodId, args: &[u8]) -> Result<Vec<u8>, RemoteError> {
         self(method, args)
     }
 }
@@ -0,0 +1,111 @@
+use super::MethodId;
+use crate::transport::TransportError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// An error that can happen while carrying out a remote call.
+///
+/// A method of a service trait whose return type is `Result<T, RemoteError>` is treated as _fallible_ by the macro.
+/// Its proxy object will return such errors instead of panicking.
+/// You can also use your own error type, as long as it implements `From<RemoteError>`,
+/// by putting `#[fallible]` on the method.
+///
+/// Methods that are not fallible will keep panicking on these errors.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RemoteError {
+    /// The call couldn't be finished within [`call_timeout`].
+    ///
+    /// [`call_timeout`]: ../struct.Config.html#structfield.call_timeout
+    TimeOut,
+
+    /// All call slots were occupied for longer than [`call_timeout`].
+    ///
+    /// [`call_timeout`]: ../struct.Config.html#structfield.call_timeout
+    TooManyCalls,
+
+    /// The connection is closed, either by the other end or by the local [`Context`].
+    ///
+    /// [`Context`]: ../struct.Context.html
+    Disconnected,
+
+    /// Arguments or a return value couldn't be de/serialized, or were too large for the transport.
+    InvalidData,
+
+    /// The other side has no service object with the given id.
+    UnknownObject(u32),
+
+    /// The service object on the other side doesn't have the method.
+    UnknownMethod {
+        trait_name: String,
+        method: MethodId,
+    },
+
+    /// The service object panicked while handling the call on the other side.
+    Panic {
+        trait_name: String,
+        method: MethodId,
+        message: String,
+    },
+
+    /// The caller cancelled the call, usually because it gave up waiting for the response.
+    ///
+    /// A service object sees this from [`CancellationToken::check()`].
+    ///
+    /// [`CancellationToken::check()`]: ../struct.CancellationToken.html#method.check
+    Cancelled,
+
+    /// The other side has no service object for the [`HandoffToken`].
+    ///
+    /// The token might have been redeemed already, or minted by another process.
+    ///
+    /// [`HandoffToken`]: ../raw_exchange/struct.HandoffToken.html
+    UnknownHandoffToken,
+
+    /// The service object on the other side can't be called by the proxy object of the service trait.
+    ///
+    /// It carries the reason, as given by [`ServiceSignature::check_compatibility()`].
+    ///
+    /// [`ServiceSignature::check_compatibility()`]: ../struct.ServiceSignature.html#method.check_compatibility
+    IncompatibleService(String),
+}
+
+impl fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemoteError::TimeOut => write!(f, "remote call timed out"),
+            RemoteError::TooManyCalls => write!(f, "too many concurrent calls on the context"),
+            RemoteError::Disconnected => write!(f, "remote-trait-object connection is closed"),
+            RemoteError::InvalidData => write!(f, "failed to de/serialize a remote call"),
+            RemoteError::UnknownObject(id) => write!(f, "no service object with id {}", id),
+            RemoteError::UnknownMethod { trait_name, method } => {
+                write!(f, "{} has no method with id {}", trait_name, method)
+            }
+            RemoteError::Panic {
+                trait_name,
+                method,
+                message,
+            } => write!(
+                f,
+                "method {} of {} panicked on the other side: {}",
+                method, trait_name, message
+            ),
+            RemoteError::Cancelled => write!(f, "remote call was cancelled"),
+            RemoteError::UnknownHandoffToken => write!(f, "no service object with the token"),
+            RemoteError::IncompatibleService(reason) => {
+                write!(f, "incompatible service: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RemoteError {}
+
+impl From<TransportError> for RemoteError {
+    fn from(err: TransportError) -> Self {
+        match err {
+            TransportError::TimeOut => RemoteError::TimeOut,
+            TransportError::FrameTooLarge { .. } => RemoteError::InvalidData,
+            TransportError::Termination | TransportError::Custom => RemoteError::Disconnected,
+        }
+    }
+}
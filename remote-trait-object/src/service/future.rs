@@ -0,0 +1,162 @@
+use super::RemoteError;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+/// A boxed future, which is what an `async fn` of a service trait returns.
+///
+/// The macro rewrites `async fn method(&self) -> T` into `fn method(&self) -> BoxFuture<'_, T>`,
+/// so the service object implements it as
+/// ```ignore
+/// fn method(&self) -> BoxFuture<'_, T> {
+///     Box::pin(async move { ... })
+/// }
+/// ```
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs a future to completion on the current thread, blocking it while the future is pending.
+///
+/// The skeleton uses this to drive the future of an `async fn` service method.
+/// It is also handy to call an `async fn` of a proxy object without an async runtime.
+/// Note that it doesn't provide any reactor by itself, so a future that relies on a specific runtime
+/// (like IO and timers of `tokio`) works only if the current thread is in that runtime.
+/// With the `tokio` feature, a context handles calls in the tokio runtime that it has been created in, if any.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    poll_until(&mut future, None).expect("It waits without a deadline")
+}
+
+/// The tokio runtime that a context has been created in, if any, which drives async service methods.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Runtime {
+    #[cfg(feature = "tokio")]
+    handle: Option<tokio::runtime::Handle>,
+}
+
+impl Runtime {
+    /// The runtime that the current thread is in.
+    pub fn current() -> Self {
+        Self {
+            #[cfg(feature = "tokio")]
+            handle: tokio::runtime::Handle::try_current().ok(),
+        }
+    }
+
+    /// Runs the function in the runtime, so that the futures polled by it can use the runtime.
+    pub fn enter<T>(&self, f: impl FnOnce() -> T) -> T {
+        #[cfg(feature = "tokio")]
+        let _guard = self.handle.as_ref().map(|handle| handle.enter());
+        f()
+    }
+}
+
+/// Polls the future on the current thread until it is ready, or the deadline passes.
+fn poll_until<F: Future + Unpin>(future: &mut F, deadline: Option<Instant>) -> Option<F::Output> {
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = Pin::new(&mut *future).poll(&mut context) {
+            return Some(output);
+        }
+        match deadline {
+            None => thread::park(),
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return None;
+                }
+                thread::park_timeout(deadline - now);
+            }
+        }
+    }
+}
+
+/// A reply to a remote call, whose request has been sent already.
+///
+/// This lets a single thread make many calls at once, and then gather the results.
+/// A proxy object returns this from the `_pending` variant of a method that has the `#[pending]` attribute.
+/// ```ignore
+/// let replies: Vec<PendingReply<u64>> = stores.iter().map(|store| store.stock_pending()).collect();
+/// let total: u64 = replies.into_iter().map(|reply| reply.wait().unwrap()).sum();
+/// ```
+/// [`Config::call_timeout`] doesn't apply here; use [`wait_timeout()`] instead.
+/// It can also be awaited, as a future of the result.
+///
+/// Dropping it without taking the result cancels the call, just like a timed-out one.
+///
+/// [`Config::call_timeout`]: ./struct.Config.html#structfield.call_timeout
+/// [`wait_timeout()`]: #method.wait_timeout
+pub struct PendingReply<T> {
+    future: BoxFuture<'static, Result<T, RemoteError>>,
+}
+
+impl<T> fmt::Debug for PendingReply<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingReply").finish()
+    }
+}
+
+impl<T: Send + 'static> PendingReply<T> {
+    /// Creates a reply that already has the result, which is useful for a local service object.
+    pub fn ready(result: Result<T, RemoteError>) -> Self {
+        Self::new(Box::pin(std::future::ready(result)))
+    }
+}
+
+impl<T> PendingReply<T> {
+    pub(crate) fn new(future: BoxFuture<'static, Result<T, RemoteError>>) -> Self {
+        PendingReply { future }
+    }
+
+    /// Blocks until the result arrives.
+    pub fn wait(mut self) -> Result<T, RemoteError> {
+        poll_until(&mut self.future, None).expect("It waits without a deadline")
+    }
+
+    /// Blocks until the result arrives, or the timeout passes.
+    ///
+    /// It gives the reply back on timeout, so that you can wait for it again.
+    pub fn wait_timeout(mut self, timeout: Duration) -> Result<Result<T, RemoteError>, Self> {
+        match poll_until(&mut self.future, Some(Instant::now() + timeout)) {
+            Some(result) => Ok(result),
+            None => Err(self),
+        }
+    }
+
+    /// Cancels the call, letting the other side know that the result is not needed anymore.
+    ///
+    /// It is the same as dropping the reply.
+    pub fn cancel(self) {}
+
+    /// Takes the result if it has arrived, or gives the reply back otherwise.
+    pub fn try_get(mut self) -> Result<Result<T, RemoteError>, Self> {
+        match poll_until(&mut self.future, Some(Instant::now())) {
+            Some(result) => Ok(result),
+            None => Err(self),
+        }
+    }
+}
+
+impl<T> Future for PendingReply<T> {
+    type Output = Result<T, RemoteError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.future.as_mut().poll(cx)
+    }
+}
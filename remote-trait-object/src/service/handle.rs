@@ -1,23 +1,51 @@
 use super::*;
-use crate::forwarder::NULL_ID;
+use crate::forwarder::{META_SERVICE_OBJECT_ID, NULL_ID};
+use crate::interceptor::{Chain, InterceptedCall};
+use crate::metrics::{Direction, MetricsRecorder};
 use crate::packet::Packet;
 use crate::raw_exchange::HandleToExchange;
-use crate::service::{MethodId, SerdeFormat};
+use crate::service::{MethodId, PendingReply, RemoteError, SerdeFormat};
+use crate::trace::ClientSpan;
+use std::future::Future;
+use std::sync::Arc;
 
 /// Proxy service will carry this.
 #[derive(Debug)]
 pub struct Handle {
     pub id: ServiceObjectId,
     pub port: Weak<dyn Port>,
+    /// Name of the service trait, which describes a call to interceptors.
+    pub trait_name: &'static str,
+    /// Looks up the name of a method, which describes a call to interceptors.
+    pub method_name: fn(MethodId) -> &'static str,
 }
 
 impl Handle {
     pub fn new(imported_id: HandleToExchange, port: Weak<dyn Port>) -> Self {
+        // The proxy object of the meta service is the context's own.
+        if imported_id.0 != NULL_ID && imported_id.0 != META_SERVICE_OBJECT_ID {
+            if let Some(metrics) = port.upgrade().and_then(|port| port.metrics()) {
+                metrics.proxy_imported();
+            }
+        }
         Handle {
             id: imported_id.0,
             port,
+            trait_name: "",
+            method_name: |_| "",
         }
     }
+
+    /// Gives the names of the service trait and its methods, which the macro knows.
+    pub fn with_names(
+        mut self,
+        trait_name: &'static str,
+        method_name: fn(MethodId) -> &'static str,
+    ) -> Self {
+        self.trait_name = trait_name;
+        self.method_name = method_name;
+        self
+    }
 }
 
 impl Handle {
@@ -25,34 +53,291 @@ impl Handle {
     /// It carries out user's remote call in a generic way.
     /// Invoking this method is role of the macro, by putting appropriate instantiation of this generic
     /// for each service trait's method, according to the method signature of each.
+    ///
+    /// It panics if the call fails. Fallible methods use [`try_call()`] instead.
+    ///
+    /// [`try_call()`]: #method.try_call
     pub fn call<F: SerdeFormat, S: serde::Serialize, D: serde::de::DeserializeOwned>(
         &self,
         method: MethodId,
         args: &S,
     ) -> D {
+        self.call_raw::<F, S, D>(method, args)
+            .unwrap_or_else(|err| panic!("Remote call failed: {}", err))
+    }
+
+    /// A "call stub" for a method that returns `Result<T, E>`.
+    ///
+    /// Any failure in carrying out the call is converted into `E` and returned.
+    pub fn try_call<F, S, T, E>(&self, method: MethodId, args: &S) -> Result<T, E>
+    where
+        F: SerdeFormat,
+        S: serde::Serialize,
+        T: serde::de::DeserializeOwned,
+        E: serde::de::DeserializeOwned + From<RemoteError>,
+    {
+        match self.call_raw::<F, S, Result<T, E>>(method, args) {
+            Ok(result) => result,
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// A "call stub" for an `async fn`, which sends the request right away and returns a future of the result.
+    ///
+    /// The future panics if the call fails, like [`call()`].
+    /// Note that `Config::call_timeout` doesn't apply to waiting for the response.
+    ///
+    /// [`call()`]: #method.call
+    pub fn call_async<F, S, D>(
+        &self,
+        method: MethodId,
+        args: &S,
+    ) -> impl Future<Output = D> + Send + 'static
+    where
+        F: SerdeFormat,
+        S: serde::Serialize,
+        D: serde::de::DeserializeOwned + Send + 'static,
+    {
+        let result = self.call_raw_async::<F, S, D>(method, args);
+        async move {
+            result
+                .await
+                .unwrap_or_else(|err| panic!("Remote call failed: {}", err))
+        }
+    }
+
+    /// A "call stub" for an `async fn` that returns `Result<T, E>`, like [`try_call()`].
+    ///
+    /// [`try_call()`]: #method.try_call
+    pub fn try_call_async<F, S, T, E>(
+        &self,
+        method: MethodId,
+        args: &S,
+    ) -> impl Future<Output = Result<T, E>> + Send + 'static
+    where
+        F: SerdeFormat,
+        S: serde::Serialize,
+        T: serde::de::DeserializeOwned + Send + 'static,
+        E: serde::de::DeserializeOwned + From<RemoteError> + Send + 'static,
+    {
+        let result = self.call_raw_async::<F, S, Result<T, E>>(method, args);
+        async move {
+            match result.await {
+                Ok(result) => result,
+                Err(err) => Err(err.into()),
+            }
+        }
+    }
+
+    /// A "call stub" that returns as soon as the request is sent, leaving the response to [`PendingReply`].
+    ///
+    /// [`PendingReply`]: ../struct.PendingReply.html
+    pub fn call_pending<F, S, D>(&self, method: MethodId, args: &S) -> PendingReply<D>
+    where
+        F: SerdeFormat,
+        S: serde::Serialize,
+        D: serde::de::DeserializeOwned + Send + 'static,
+    {
+        PendingReply::new(Box::pin(self.call_raw_async::<F, S, D>(method, args)))
+    }
+
+    /// A "call stub" for a `#[oneway]` method, which returns as soon as the request is sent.
+    ///
+    /// Nobody waits for the result, so a failure is only logged.
+    pub fn call_oneway<F: SerdeFormat, S: serde::Serialize>(&self, method: MethodId, args: &S) {
+        if let Err(err) = self.send_oneway_raw::<F, S>(method, args) {
+            warn!(
+                "Oneway call to method {} of {} failed: {}",
+                method, self.id, err
+            );
+        }
+    }
+
+    /// A "call stub" for a fallible `#[oneway]` method, which returns `Result<(), E>`.
+    ///
+    /// Only a failure in sending the request is returned, since nobody waits for the result.
+    pub fn try_call_oneway<F, S, E>(&self, method: MethodId, args: &S) -> Result<(), E>
+    where
+        F: SerdeFormat,
+        S: serde::Serialize,
+        E: From<RemoteError>,
+    {
+        self.send_oneway_raw::<F, S>(method, args)
+            .map_err(Into::into)
+    }
+
+    fn send_oneway_raw<F: SerdeFormat, S: serde::Serialize>(
+        &self,
+        method: MethodId,
+        args: &S,
+    ) -> Result<(), RemoteError> {
+        assert_ne!(
+            self.id, NULL_ID,
+            "You invoked a method of a null proxy object."
+        );
+
+        super::serde_support::port_thread_local::set_port(self.port.clone());
+        let span = ClientSpan::new(self.trait_name, (self.method_name)(method));
+        let result = span.in_scope(|| {
+            let args = F::to_vec(args).map_err(|_| RemoteError::InvalidData)?;
+            let port = self.port.upgrade().ok_or(RemoteError::Disconnected)?;
+            let call = self.intercepted_call(method, &args);
+            let (chain, entered) =
+                Chain::enter(port.client_interceptors(), outgoing(&*port), &call);
+            let result = entered
+                .and_then(|()| port.send_oneway(self.new_request(method, &args, &span).view()));
+            chain.exit(&call, result.as_ref().map(|()| &[][..]));
+            result
+        });
+        super::serde_support::port_thread_local::remove_port();
+        result
+    }
+
+    fn intercepted_call<'a>(&self, method: MethodId, args: &'a [u8]) -> InterceptedCall<'a> {
+        InterceptedCall::new(
+            self.trait_name,
+            (self.method_name)(method),
+            method,
+            self.id,
+            args,
+        )
+    }
+
+    /// Creates a request, with the metadata given by `with_call_metadata()` and the trace context of the span.
+    fn new_request(&self, method: MethodId, args: &[u8], span: &ClientSpan) -> Packet {
+        let mut packet = Packet::new_request(self.id, method, args);
+        let mut metadata = crate::call::outgoing_metadata().unwrap_or_default();
+        span.inject(&mut metadata);
+        if !metadata.is_empty() {
+            packet.set_metadata(&metadata);
+        }
+        packet
+    }
+
+    fn call_raw<F: SerdeFormat, S: serde::Serialize, D: serde::de::DeserializeOwned>(
+        &self,
+        method: MethodId,
+        args: &S,
+    ) -> Result<D, RemoteError> {
         assert_ne!(
             self.id, NULL_ID,
             "You invoked a method of a null proxy object."
         );
 
         super::serde_support::port_thread_local::set_port(self.port.clone());
-        let args = F::to_vec(args).unwrap();
-        let packet = Packet::new_request(self.id, method, &args);
-        let response = self.port.upgrade().unwrap().call(packet.view());
-        let result = F::from_slice(response.data()).unwrap();
+        let span = ClientSpan::new(self.trait_name, (self.method_name)(method));
+        let result = span.in_scope(|| {
+            let args = F::to_vec(args).map_err(|_| RemoteError::InvalidData)?;
+            let response = self.send(method, &args, &span)?;
+            F::from_slice(response.data()).map_err(|_| RemoteError::InvalidData)
+        });
         super::serde_support::port_thread_local::remove_port();
         result
     }
+
+    /// Calls the method with the arguments that are already serialized, and returns the serialized return value.
+    ///
+    /// It is for forwarding a call as it is, so the port is not set for the de/serialization.
+    pub(crate) fn call_serialized(
+        &self,
+        method: MethodId,
+        args: &[u8],
+    ) -> Result<Vec<u8>, RemoteError> {
+        assert_ne!(
+            self.id, NULL_ID,
+            "You invoked a method of a null proxy object."
+        );
+
+        let span = ClientSpan::new(self.trait_name, (self.method_name)(method));
+        span.in_scope(|| {
+            self.send(method, args, &span)
+                .map(|response| response.data().to_vec())
+        })
+    }
+
+    /// Sends the request through the interceptors, and waits for the response.
+    fn send(
+        &self,
+        method: MethodId,
+        args: &[u8],
+        span: &ClientSpan,
+    ) -> Result<Packet, RemoteError> {
+        let port = self.port.upgrade().ok_or(RemoteError::Disconnected)?;
+        let call = self.intercepted_call(method, args);
+        let (chain, entered) = Chain::enter(port.client_interceptors(), outgoing(&*port), &call);
+        let response =
+            entered.and_then(|()| port.call(self.new_request(method, args, span).view()));
+        chain.exit(&call, response.as_ref().map(|x| x.data()));
+        response
+    }
+
+    fn call_raw_async<F, S, D>(
+        &self,
+        method: MethodId,
+        args: &S,
+    ) -> impl Future<Output = Result<D, RemoteError>> + Send + 'static
+    where
+        F: SerdeFormat,
+        S: serde::Serialize,
+        D: serde::de::DeserializeOwned + Send + 'static,
+    {
+        assert_ne!(
+            self.id, NULL_ID,
+            "You invoked a method of a null proxy object."
+        );
+
+        super::serde_support::port_thread_local::set_port(self.port.clone());
+        let span = ClientSpan::new(self.trait_name, (self.method_name)(method));
+        let response = span.in_scope(|| -> Result<_, RemoteError> {
+            let args = F::to_vec(args).map_err(|_| RemoteError::InvalidData)?;
+            let port = self.port.upgrade().ok_or(RemoteError::Disconnected)?;
+            let (chain, entered) = Chain::enter(
+                port.client_interceptors(),
+                outgoing(&*port),
+                &self.intercepted_call(method, &args),
+            );
+            let response = match entered {
+                Ok(()) => port.call_async(self.new_request(method, &args, &span).view()),
+                Err(err) => Box::pin(std::future::ready(Err(err))),
+            };
+            Ok((chain, args, response))
+        });
+        super::serde_support::port_thread_local::remove_port();
+
+        let port = self.port.clone();
+        let (id, trait_name, method_name) = (self.id, self.trait_name, (self.method_name)(method));
+        span.instrument(async move {
+            let (chain, args, response) = response?;
+            let response = response.await;
+            let call = InterceptedCall::new(trait_name, method_name, method, id, &args);
+            chain.exit(&call, response.as_ref().map(|x| x.data()));
+            let response = response?;
+            // The future might be polled on any thread, so the port is set only while deserializing.
+            super::serde_support::port_thread_local::set_port(port);
+            let result = F::from_slice(response.data()).map_err(|_| RemoteError::InvalidData);
+            super::serde_support::port_thread_local::remove_port();
+            result
+        })
+    }
+}
+
+fn outgoing(port: &dyn Port) -> Option<(Arc<MetricsRecorder>, Direction)> {
+    port.metrics().map(|metrics| (metrics, Direction::Outgoing))
 }
 
 impl Drop for Handle {
     /// Dropping handle will be signaled to the exporter (_delete request_), so that it can remove the service object as well.
     fn drop(&mut self) {
-        if self.id != NULL_ID {
-            self.port
+        // The meta service lives as long as the context, however many proxy objects of it are made.
+        if self.id != NULL_ID && self.id != META_SERVICE_OBJECT_ID {
+            let port = self
+                .port
                 .upgrade()
-                .expect("You must drop the proxy object before the RTO context is dropped")
-                .delete_request(self.id);
+                .expect("You must drop the proxy object before the RTO context is dropped");
+            if let Some(metrics) = port.metrics() {
+                metrics.proxy_dropped();
+            }
+            port.delete_request(self.id);
         }
     }
 }
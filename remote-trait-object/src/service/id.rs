@@ -36,6 +36,7 @@ pub struct IdMap {
 /// # Examples
 /// ```
 /// use remote_trait_object::macro_env::*;
+/// use std::collections::HashMap;
 /// #[allow(non_upper_case_globals)]
 /// static ID_METHOD_MyTrait_mymethod: MethodIdAtomic = MethodIdAtomic::new(1);
 /// #[linkme::distributed_slice(MID_REG)]
@@ -46,8 +47,7 @@ pub struct IdMap {
 /// fn id_method_setter_MyTrait_mymethod(id: MethodId) {
 ///     ID_METHOD_MyTrait_mymethod.store(id, ID_ORDERING);
 /// }
-/// #[test]
-/// fn setup() {
+/// fn main() {
 ///     let id_map: HashMap<(String, String), MethodId> =
 ///         [(("MyTrait".to_owned(), "mymethod".to_owned()), 123)].iter().cloned().collect();
 ///     let id_map = IdMap {
@@ -75,10 +75,12 @@ pub fn setup_identifiers(descriptor: &IdMap) {
     // method ids have default values decided by the order, so it is ok to leave them in an ordinary case.
     if let Some(map) = descriptor.method_map.as_ref() {
         for (trait_name, method_name, setter) in MID_REG {
-            setter(
-                *map.get(&((*trait_name).to_owned(), (*method_name).to_owned()))
-                    .expect("Invalid handle descriptor"),
-            );
+            match map.get(&((*trait_name).to_owned(), (*method_name).to_owned())) {
+                Some(id) => setter(*id),
+                // The meta service belongs to this library, so the users don't have to know its methods.
+                None if *trait_name == "MetaService" => (),
+                None => panic!("Invalid handle descriptor"),
+            }
         }
     }
 }
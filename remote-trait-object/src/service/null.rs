@@ -19,6 +19,16 @@ impl Service for NullServiceImpl {}
 // They are slightly different from the actual expansion result of NullService (which happens to succeed), since the macro
 // doesn't take account of such special case.
 
+#[allow(non_snake_case)]
+fn signature_NullService() -> crate::macro_env::ServiceSignature {
+    crate::macro_env::ServiceSignature::new("NullService", vec![])
+}
+impl crate::macro_env::DescribeService for dyn NullService {
+    fn signature() -> crate::macro_env::ServiceSignature {
+        signature_NullService()
+    }
+}
+
 pub struct NullServiceBoxDispatcher {}
 impl NullServiceBoxDispatcher {
     fn new(_object: Box<dyn NullService>) -> Self {
@@ -26,8 +36,21 @@ impl NullServiceBoxDispatcher {
     }
 }
 impl crate::macro_env::Dispatch for NullServiceBoxDispatcher {
-    fn dispatch_and_call(&self, _method: crate::macro_env::MethodId, _args: &[u8]) -> Vec<u8> {
-        panic!("Invalid remote-trait-object call. Fatal Error.")
+    fn dispatch_and_call(
+        &self,
+        method: crate::macro_env::MethodId,
+        _args: &[u8],
+    ) -> Result<Vec<u8>, crate::macro_env::RemoteError> {
+        Err(crate::macro_env::RemoteError::UnknownMethod {
+            trait_name: "NullService".to_owned(),
+            method,
+        })
+    }
+    fn trait_name(&self) -> &'static str {
+        "NullService"
+    }
+    fn signature(&self) -> Option<crate::macro_env::ServiceSignature> {
+        Some(signature_NullService())
     }
 }
 impl crate::macro_env::IntoSkeleton<dyn NullService> for Box<dyn NullService> {
@@ -42,8 +65,21 @@ impl NullServiceArcDispatcher {
     }
 }
 impl crate::macro_env::Dispatch for NullServiceArcDispatcher {
-    fn dispatch_and_call(&self, _method: crate::macro_env::MethodId, _args: &[u8]) -> Vec<u8> {
-        panic!("Invalid remote-trait-object call. Fatal Error.")
+    fn dispatch_and_call(
+        &self,
+        method: crate::macro_env::MethodId,
+        _args: &[u8],
+    ) -> Result<Vec<u8>, crate::macro_env::RemoteError> {
+        Err(crate::macro_env::RemoteError::UnknownMethod {
+            trait_name: "NullService".to_owned(),
+            method,
+        })
+    }
+    fn trait_name(&self) -> &'static str {
+        "NullService"
+    }
+    fn signature(&self) -> Option<crate::macro_env::ServiceSignature> {
+        Some(signature_NullService())
     }
 }
 impl crate::macro_env::IntoSkeleton<dyn NullService> for std::sync::Arc<dyn NullService> {
@@ -58,8 +94,21 @@ impl NullServiceRwLockDispatcher {
     }
 }
 impl crate::macro_env::Dispatch for NullServiceRwLockDispatcher {
-    fn dispatch_and_call(&self, _method: crate::macro_env::MethodId, _args: &[u8]) -> Vec<u8> {
-        panic!("Invalid remote-trait-object call. Fatal Error.")
+    fn dispatch_and_call(
+        &self,
+        method: crate::macro_env::MethodId,
+        _args: &[u8],
+    ) -> Result<Vec<u8>, crate::macro_env::RemoteError> {
+        Err(crate::macro_env::RemoteError::UnknownMethod {
+            trait_name: "NullService".to_owned(),
+            method,
+        })
+    }
+    fn trait_name(&self) -> &'static str {
+        "NullService"
+    }
+    fn signature(&self) -> Option<crate::macro_env::ServiceSignature> {
+        Some(signature_NullService())
     }
 }
 impl crate::macro_env::IntoSkeleton<dyn NullService>
@@ -73,6 +122,7 @@ impl crate::macro_env::IntoSkeleton<dyn NullService>
 }
 #[derive(Debug)]
 pub struct NullServiceProxy {
+    #[allow(dead_code)]
     handle: crate::macro_env::Handle,
 }
 impl NullService for NullServiceProxy {}
@@ -0,0 +1,85 @@
+use super::*;
+use crate::call::{current_call, with_call_metadata};
+use crate::forwarder::NULL_ID;
+use crate::raw_exchange::HandleToExchange;
+use parking_lot::Mutex;
+
+/// A service object that forwards every call to a service imported from another context, as it is.
+///
+/// It is what an imported service becomes when it is exported again,
+/// so that a service can be passed on to a third party without a hand-written wrapper.
+/// The call metadata, deadline and trace context of the call being forwarded are passed on too.
+///
+/// Since the arguments and the return values are not deserialized,
+/// the service objects and handoffs passed through the methods of the forwarded service can't be forwarded.
+/// Their ids would be wrong on the other side, so such a method fails with `RemoteError::InvalidData` instead.
+/// They are found by the signature of the forwarded service, which doesn't see service objects inside other types.
+pub(crate) struct Relay {
+    handle: Handle,
+    /// Methods that pass service objects, which are looked up at the first call.
+    passing_services: Mutex<Option<Vec<MethodId>>>,
+}
+
+impl Relay {
+    pub fn new(handle: HandleToExchange, port: Weak<dyn Port>) -> Self {
+        Self {
+            handle: Handle::new(handle, port),
+            passing_services: Mutex::new(None),
+        }
+    }
+
+    fn passes_services(&self, method: MethodId) -> bool {
+        let mut passing_services = self.passing_services.lock();
+        let passing_services = passing_services.get_or_insert_with(|| {
+            let methods = match self.signature() {
+                Some(signature) => signature.methods,
+                None => return Vec::new(),
+            };
+            methods
+                .into_iter()
+                .filter(|method| method.passes_services())
+                .map(|method| method.id)
+                .collect()
+        });
+        passing_services.contains(&method)
+    }
+}
+
+impl Dispatch for Relay {
+    fn dispatch_and_call(&self, method: MethodId, args: &[u8]) -> Result<Vec<u8>, RemoteError> {
+        if self.passes_services(method) {
+            warn!(
+                "Method {} of a forwarded service passes service objects, which can't be forwarded",
+                method
+            );
+            return Err(RemoteError::InvalidData);
+        }
+        let metadata = current_call()
+            .map(|call| call.metadata().clone())
+            .unwrap_or_default();
+        with_call_metadata(metadata, || self.handle.call_serialized(method, args))
+    }
+
+    /// Asks the exporter, since the relay doesn't know which trait it forwards.
+    fn signature(&self) -> Option<ServiceSignature> {
+        crate::meta_service::exported_signature(&self.handle.port, self.handle.id).unwrap_or_else(
+            |err| {
+                debug!(
+                    "Failed to get the signature of a forwarded service: {}",
+                    err
+                );
+                None
+            },
+        )
+    }
+}
+
+impl Drop for Relay {
+    fn drop(&mut self) {
+        // The context that the service is imported from might have been dropped before the one that forwards it.
+        // Then there is nobody to send the delete request to.
+        if self.handle.port.upgrade().is_none() {
+            self.handle.id = NULL_ID;
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use super::export_import::*;
+use super::relay::Relay;
 use super::*;
+use crate::forwarder::NULL_ID;
 use crate::raw_exchange::HandleToExchange;
+use parking_lot::Mutex;
 use serde::de::{Deserialize, Deserializer};
 use serde::ser::{Serialize, Serializer};
 use std::cell::RefCell;
@@ -100,6 +103,13 @@ impl<T: ?Sized + Service> ServiceToExport<T> {
 /// `ServiceToImport` is a wrapper of [`HandleToExchange`] that hides the detail exchange process.
 /// However you don't have to know what [`HandleToExchange`] is, unless you're going to perform [raw export and import].
 ///
+/// It can be exported again to another context, without being imported, to pass the service on to a third party.
+/// Make it into a [`ServiceToExport`] with [`ServiceToExport::new()`], or put it in [`ServiceRef`] as it is.
+/// Then the context that re-exports it forwards the calls of the third party to the exporter.
+/// Since the calls are forwarded as they are, services can't be passed through the methods of such a service,
+/// and calling a method that takes or returns a `ServiceRef` fails with [`RemoteError::InvalidData`].
+/// If you need that, import it and export the proxy object instead.
+///
 /// **NOTE**: it implements [`Deserialize`], but you must **NEVER** try to deserialize it.
 /// It has a side effect of registering the handle in the context,
 /// and so should be called only by `remote-trait-object`'s internal process.
@@ -126,9 +136,12 @@ fn do_some_imports(x: Box<dyn Hello>) {
 /// [`service`]: attr.service.html
 /// [Service compatibility]: ./index.html#service_compatibility
 /// [raw export and import]: raw_exchange/index.html
+/// [`RemoteError::InvalidData`]: enum.RemoteError.html#variant.InvalidData
 pub struct ServiceToImport<T: ?Sized + Service> {
     handle: HandleToExchange,
     port: Weak<dyn Port>,
+    /// The handle that it is re-exported as, once it is serialized.
+    forwarded: Mutex<Option<HandleToExchange>>,
     _marker: PhantomData<T>,
 }
 
@@ -138,21 +151,56 @@ impl<T: ?Sized + Service> ServiceToImport<T> {
         P::import_proxy(self.port, self.handle)
     }
 
+    /// Makes another `ServiceToImport` of the same service object, which can be imported independently.
+    ///
+    /// It adds a reference to the service object on the exporter side, which then deletes the object
+    /// only after the proxy objects imported from both are dropped.
+    /// Note that, like the original one, it must be imported eventually, or the service object is never deleted.
+    ///
+    /// It fails if the exporter can't be reached, or the service object has already been deleted.
+    pub fn try_clone(&self) -> Result<Self, RemoteError> {
+        if self.handle.0 != NULL_ID {
+            self.port
+                .upgrade()
+                .ok_or(RemoteError::Disconnected)?
+                .retain_request(self.handle.0)?;
+        }
+        Ok(Self {
+            handle: HandleToExchange(self.handle.0),
+            port: self.port.clone(),
+            forwarded: Default::default(),
+            _marker: PhantomData,
+        })
+    }
+
     /// Casts into another `ServiceToImport` with a different service trait.
     ///
-    /// If the target trait is not compatible with the original one, it returns `Err`.
+    /// It asks the exporter for the signature of the service object, and
+    /// if the target trait is not compatible with it, it returns [`RemoteError::IncompatibleService`].
+    /// The service is dropped in that case, as if it were imported and dropped.
+    /// If the exporter doesn't know the signature, the cast always succeeds.
     ///
     /// See [Service Compatiblity] section for more.
     ///
     /// [Service compatiblity]: ./index.html#service_compatibility
-    #[allow(clippy::result_unit_err)]
-    pub fn cast_service<U: ?Sized + Service>(self) -> Result<ServiceToImport<U>, ()> {
-        // TODO: Check the compatibility between traits using IDL
-        Ok(ServiceToImport {
-            handle: self.handle,
-            port: self.port,
-            _marker: PhantomData,
-        })
+    /// [`RemoteError::IncompatibleService`]: ./enum.RemoteError.html#variant.IncompatibleService
+    pub fn cast_service<U: ?Sized + Service + DescribeService>(
+        self,
+    ) -> Result<ServiceToImport<U>, RemoteError> {
+        if self.handle.0 != NULL_ID {
+            let checked = crate::meta_service::exported_signature(&self.port, self.handle.0)
+                .and_then(|exported| match exported {
+                    Some(exported) => U::signature()
+                        .check_compatibility(&exported)
+                        .map_err(RemoteError::IncompatibleService),
+                    None => Ok(()),
+                });
+            if let Err(err) = checked {
+                self.discard();
+                return Err(err);
+            }
+        }
+        Ok(self.cast_service_without_compatibility_check())
     }
 
     /// Casts into another `ServiceToImport` with a different service trait, without check.
@@ -169,14 +217,33 @@ impl<T: ?Sized + Service> ServiceToImport<T> {
         ServiceToImport {
             handle: self.handle,
             port: self.port,
+            forwarded: Default::default(),
             _marker: PhantomData,
         }
     }
 
+    pub(crate) fn handle(&self) -> HandleToExchange {
+        self.handle
+    }
+
+    pub(crate) fn port(&self) -> &Weak<dyn Port> {
+        &self.port
+    }
+
+    /// Drops it without importing, making a delete request for it.
+    pub(crate) fn discard(self) {
+        if self.handle.0 != NULL_ID {
+            if let Some(port) = self.port.upgrade() {
+                port.delete_request(self.handle.0);
+            }
+        }
+    }
+
     pub(crate) fn from_raw_import(handle: HandleToExchange, port: Weak<dyn Port>) -> Self {
         Self {
             handle,
             port,
+            forwarded: Default::default(),
             _marker: PhantomData,
         }
     }
@@ -248,6 +315,9 @@ pub trait PizzaStore: Service {
 /// - implemented and exported - you will be using `Import` variant for an argument, and `Export` variant for the return value.
 /// - imported and locally invoked - you will be using `Export` variant for an argument, and `Import` variant for the return value.
 ///
+/// An `Import` variant can also be passed on to another context as it is, which re-exports the service.
+/// See [`ServiceToImport`] for more.
+///
 /// ## Example
 /**
 ```ignore
@@ -359,6 +429,40 @@ impl<T: ?Sized + Service> Serialize for ServiceToExport<T> {
     }
 }
 
+impl<T: ?Sized + Service> IntoSkeleton<T> for ServiceToImport<T> {
+    /// Makes a skeleton that forwards the calls to the exporter.
+    fn into_skeleton(self) -> Skeleton {
+        create_skeleton(Arc::new(Relay::new(self.handle, self.port)))
+    }
+}
+
+impl<T: ?Sized + Service> Serialize for ServiceToImport<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let error = "You must not de/serialize ServiceRef by yourself. If you not, this is a bug.";
+        // A null handle doesn't need to be forwarded, since nobody can call it anyway.
+        if self.handle.0 == NULL_ID {
+            return self.handle.serialize(serializer);
+        }
+        let mut forwarded = self.forwarded.lock();
+        let handle = match *forwarded {
+            Some(handle) => handle,
+            None => {
+                let relay = Relay::new(self.handle, self.port.clone());
+                let handle = port_thread_local::get_port()
+                    .upgrade()
+                    .expect(error)
+                    .register_service(Arc::new(relay));
+                *forwarded = Some(handle);
+                handle
+            }
+        };
+        handle.serialize(serializer)
+    }
+}
+
 impl<'de, T: ?Sized + Service> Deserialize<'de> for ServiceToImport<T> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -368,6 +472,7 @@ impl<'de, T: ?Sized + Service> Deserialize<'de> for ServiceToImport<T> {
         Ok(ServiceToImport {
             handle,
             port: port_thread_local::get_port(),
+            forwarded: Default::default(),
             _marker: std::marker::PhantomData,
         })
     }
@@ -380,9 +485,7 @@ impl<T: ?Sized + Service> Serialize for ServiceRef<T> {
     {
         match self {
             ServiceRef::Export(x) => x.serialize(serializer),
-            ServiceRef::Import(_) => panic!(
-                "If you want to re-export an imported object, first completely import it with `into_proxy()` and make it into `ServiceToExport`."
-            ),
+            ServiceRef::Import(x) => x.serialize(serializer),
         }
     }
 }
@@ -416,7 +519,7 @@ mod tests {
         }
 
         impl Port for MockPort {
-            fn call(&self, _packet: PacketView) -> Packet {
+            fn call(&self, _packet: PacketView) -> Result<Packet, RemoteError> {
                 unimplemented!()
             }
 
@@ -436,7 +539,11 @@ mod tests {
         impl Foo for FooImpl {}
         impl Service for FooImpl {}
         impl Dispatch for FooImpl {
-            fn dispatch_and_call(&self, _method: MethodId, _args: &[u8]) -> Vec<u8> {
+            fn dispatch_and_call(
+                &self,
+                _method: MethodId,
+                _args: &[u8],
+            ) -> Result<Vec<u8>, RemoteError> {
                 unimplemented!()
             }
         }
@@ -0,0 +1,167 @@
+use super::MethodId;
+use serde::{Deserialize, Serialize};
+
+/// The structure of a service trait, which decides whether a proxy object can call a service object.
+///
+/// The macro describes each service trait with this, which is available from [`DescribeService`].
+/// Types are described as the macro sees them: references are removed, and only the last segment of each path is kept.
+/// For example, `&str` and `String` are described the same, as they are serialized the same.
+///
+/// [`DescribeService`]: ./trait.DescribeService.html
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceSignature {
+    pub trait_name: String,
+    /// Methods in the order of the declaration.
+    pub methods: Vec<MethodSignature>,
+}
+
+/// The structure of a method in a [`ServiceSignature`].
+///
+/// [`ServiceSignature`]: ./struct.ServiceSignature.html
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MethodSignature {
+    /// The id that the method is called with, which might have been changed by [`setup_identifiers()`].
+    ///
+    /// [`setup_identifiers()`]: ./fn.setup_identifiers.html
+    pub id: MethodId,
+    pub name: String,
+    /// Types of the parameters, except the receiver.
+    pub params: Vec<String>,
+    /// Type of the return value, which is the output of the future for an async method.
+    pub output: String,
+}
+
+impl ServiceSignature {
+    pub fn new(trait_name: &str, methods: Vec<MethodSignature>) -> Self {
+        Self {
+            trait_name: trait_name.to_owned(),
+            methods,
+        }
+    }
+
+    /// Checks whether a proxy object of this trait can call a service object of `exported`.
+    ///
+    /// Every method of this must be in `exported`, with the same id, name and types.
+    /// The names of the traits don't matter, and `exported` may have more methods.
+    /// Otherwise, the reason of the incompatibility is returned.
+    pub fn check_compatibility(&self, exported: &ServiceSignature) -> Result<(), String> {
+        for method in &self.methods {
+            let found = match exported.methods.iter().find(|x| x.id == method.id) {
+                Some(found) => found,
+                None => {
+                    return Err(format!(
+                        "{} has no method for {}::{} (id {})",
+                        exported.trait_name, self.trait_name, method.name, method.id
+                    ))
+                }
+            };
+            if found.name != method.name {
+                return Err(format!(
+                    "{}::{} is called as {}::{} (id {})",
+                    exported.trait_name, found.name, self.trait_name, method.name, method.id
+                ));
+            }
+            if found.params != method.params || found.output != method.output {
+                return Err(format!(
+                    "{}::{} takes ({}) -> {}, but {}::{} takes ({}) -> {}",
+                    exported.trait_name,
+                    found.name,
+                    found.params.join(", "),
+                    found.output,
+                    self.trait_name,
+                    method.name,
+                    method.params.join(", "),
+                    method.output
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl MethodSignature {
+    pub fn new(id: MethodId, name: &str, params: &[&str], output: &str) -> Self {
+        Self {
+            id,
+            name: name.to_owned(),
+            params: params.iter().map(|x| (*x).to_owned()).collect(),
+            output: output.to_owned(),
+        }
+    }
+
+    /// Whether the method passes service objects, either as they are or as handoff tokens.
+    ///
+    /// Only the types written directly in the signature are seen, not the ones inside other types.
+    pub(crate) fn passes_services(&self) -> bool {
+        self.params
+            .iter()
+            .chain(std::iter::once(&self.output))
+            .any(|x| x.contains("ServiceRef<") || x.contains("Handoff<"))
+    }
+}
+
+/// A service trait that is described by a [`ServiceSignature`].
+///
+/// The macro implements this for `dyn Trait` of each service trait, so you don't have to.
+///
+/// [`ServiceSignature`]: ./struct.ServiceSignature.html
+pub trait DescribeService {
+    fn signature() -> ServiceSignature;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> ServiceSignature {
+        ServiceSignature::new(
+            "Store",
+            vec![
+                MethodSignature::new(70, "order_pizza", &["Pizza", "u32"], "String"),
+                MethodSignature::new(71, "order_coke", &["String", "u32"], "String"),
+            ],
+        )
+    }
+
+    #[test]
+    fn compatibility() {
+        assert!(store().check_compatibility(&store()).is_ok());
+
+        let small = ServiceSignature::new(
+            "SmallStore",
+            vec![MethodSignature::new(
+                70,
+                "order_pizza",
+                &["Pizza", "u32"],
+                "String",
+            )],
+        );
+        assert!(small.check_compatibility(&store()).is_ok());
+        assert!(store().check_compatibility(&small).is_err());
+
+        let renamed = ServiceSignature::new(
+            "Store",
+            vec![MethodSignature::new(
+                70,
+                "order",
+                &["Pizza", "u32"],
+                "String",
+            )],
+        );
+        assert!(renamed.check_compatibility(&store()).is_err());
+
+        let retyped = ServiceSignature::new(
+            "Store",
+            vec![MethodSignature::new(
+                70,
+                "order_pizza",
+                &["Pizza", "u64"],
+                "String",
+            )],
+        );
+        assert!(retyped
+            .check_compatibility(&store())
+            .unwrap_err()
+            .contains("(Pizza, u32) -> String"));
+    }
+}
@@ -32,7 +32,7 @@ impl TestDispatchMap {
     }
 
     fn get_cloned(&mut self, id: u32) -> Arc<dyn Dispatch> {
-        Arc::clone(&self.map.get(&id).unwrap())
+        Arc::clone(self.map.get(&id).unwrap())
     }
 
     fn remove(&mut self, id: u32) {
@@ -67,13 +67,13 @@ impl std::fmt::Debug for TestPort {
 }
 
 impl Port for TestPort {
-    fn call(&self, packet: PacketView) -> Packet {
+    fn call(&self, packet: PacketView) -> Result<Packet, RemoteError> {
         let object_id = packet.object_id();
         let dispatcher = self.dispatch_map.lock().get_cloned(object_id);
-        let response = dispatcher.dispatch_and_call(packet.method(), packet.data());
+        let response = dispatcher.dispatch_and_call(packet.method(), packet.data())?;
         let mut response_packet = Packet::new_response_from_request(packet);
         response_packet.append_data(&response);
-        response_packet
+        Ok(response_packet)
     }
 
     fn delete_request(&self, id: ServiceObjectId) {
@@ -171,8 +171,8 @@ fn macro_no_skeleton() {
     let proxy =
         <Box<dyn HelloWithRef> as ImportProxy<dyn HelloWithRef>>::import_proxy(port_weak, handle);
 
-    let source = vec![1, 2, 3, 4];
-    let source2 = vec![(&source[0], &source[1]), (&source[2], &source[3])];
+    let source = [1, 2, 3, 4];
+    let source2 = [(&source[0], &source[1]), (&source[2], &source[3])];
     let source3 = vec![&source2[0], &source2[1]];
 
     assert_eq!(proxy.f(&source3), 10);
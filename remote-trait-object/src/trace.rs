@@ -0,0 +1,237 @@
+//! Spans of remote calls, which are linked across the contexts by the trace context carried in the call metadata.
+//!
+//! Everything here does nothing unless the `tracing` feature is on.
+
+use crate::call::CallMetadata;
+#[cfg(feature = "tracing")]
+use std::cell::Cell;
+#[cfg(feature = "tracing")]
+use std::fmt;
+use std::future::Future;
+
+/// The key of the call metadata that carries the trace context, as in W3C Trace Context.
+#[cfg(feature = "tracing")]
+const TRACEPARENT: &str = "traceparent";
+
+#[cfg(feature = "tracing")]
+thread_local!(static CURRENT: Cell<Option<TraceContext>> = const { Cell::new(None) });
+
+/// Identifies a span of a remote call in a distributed trace.
+///
+/// It is carried with each call as `traceparent` of [W3C Trace Context](https://www.w3.org/TR/trace-context/),
+/// so that the spans of the caller and the callee share the same trace id, even across processes.
+/// Each span records them as `trace_id`, `span_id` and `parent_span_id`.
+#[cfg(feature = "tracing")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+}
+
+#[cfg(feature = "tracing")]
+impl TraceContext {
+    /// The trace context of the remote call that the current thread is handling, if any.
+    ///
+    /// The calls made by the current thread become its children.
+    pub fn current() -> Option<Self> {
+        CURRENT.with(Cell::get)
+    }
+
+    /// The id of the trace, which is shared by all the spans in it.
+    pub fn trace_id(&self) -> u128 {
+        self.trace_id
+    }
+
+    /// The id of the span.
+    pub fn span_id(&self) -> u64 {
+        self.span_id
+    }
+
+    /// Creates a new span in the trace of `parent`, or in a new trace if there is no parent.
+    fn child_of(parent: Option<TraceContext>) -> Self {
+        let trace_id = match parent {
+            Some(parent) => parent.trace_id,
+            None => u128::from(random_id()) << 64 | u128::from(random_id()),
+        };
+        TraceContext {
+            trace_id,
+            span_id: random_id(),
+        }
+    }
+
+    fn from_traceparent(value: &str) -> Option<Self> {
+        let mut fields = value.split('-');
+        let (version, trace_id, span_id) = (fields.next()?, fields.next()?, fields.next()?);
+        if version != "00" || trace_id.len() != 32 || span_id.len() != 16 {
+            return None;
+        }
+        Some(TraceContext {
+            trace_id: u128::from_str_radix(trace_id, 16).ok()?,
+            span_id: u64::from_str_radix(span_id, 16).ok()?,
+        })
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl fmt::Display for TraceContext {
+    /// Formats it as the value of `traceparent`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "00-{:032x}-{:016x}-01", self.trace_id, self.span_id)
+    }
+}
+
+/// A non-zero random id, which doesn't need to be cryptographically secure.
+#[cfg(feature = "tracing")]
+pub(crate) fn random_id() -> u64 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    loop {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        let id = hasher.finish();
+        if id != 0 {
+            return id;
+        }
+    }
+}
+
+/// Takes the trace context of the caller out of the metadata of a call.
+#[cfg(feature = "tracing")]
+pub(crate) fn extract(metadata: &mut CallMetadata) -> Option<TraceContext> {
+    metadata
+        .remove(TRACEPARENT)
+        .and_then(|value| TraceContext::from_traceparent(&value))
+}
+
+/// The span of a call made through a proxy object.
+pub(crate) struct ClientSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    context: TraceContext,
+}
+
+impl ClientSpan {
+    /// Opens the span, as a child of the call that the current thread is handling.
+    #[allow(unused_variables)]
+    pub fn new(trait_name: &str, method_name: &str) -> Self {
+        #[cfg(feature = "tracing")]
+        {
+            let parent = TraceContext::current();
+            let context = TraceContext::child_of(parent);
+            let span = tracing::info_span!(
+                "remote_call",
+                otel.name = %format_args!("{}::{}", trait_name, method_name),
+                otel.kind = "client",
+                rpc.service = trait_name,
+                rpc.method = method_name,
+                trace_id = %format_args!("{:032x}", context.trace_id),
+                span_id = %format_args!("{:016x}", context.span_id),
+                parent_span_id = parent.map(|x| tracing::field::display(format!("{:016x}", x.span_id))),
+            );
+            ClientSpan { span, context }
+        }
+        #[cfg(not(feature = "tracing"))]
+        ClientSpan {}
+    }
+
+    /// Gives the trace context to the callee.
+    #[allow(unused_variables)]
+    pub fn inject(&self, metadata: &mut CallMetadata) {
+        #[cfg(feature = "tracing")]
+        metadata.insert(TRACEPARENT.to_owned(), self.context.to_string());
+    }
+
+    /// Makes the future run in the span, which closes when the future completes.
+    pub fn instrument<F: Future>(self, future: F) -> impl Future<Output = F::Output> {
+        #[cfg(feature = "tracing")]
+        {
+            tracing::Instrument::instrument(future, self.span)
+        }
+        #[cfg(not(feature = "tracing"))]
+        future
+    }
+
+    /// Runs the closure in the span.
+    pub fn in_scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.in_scope(f)
+        }
+        #[cfg(not(feature = "tracing"))]
+        f()
+    }
+}
+
+/// The span of a call that a service object handles, which is entered until it is dropped.
+pub(crate) struct ServerSpan {
+    #[cfg(feature = "tracing")]
+    _entered: tracing::span::EnteredSpan,
+    #[cfg(feature = "tracing")]
+    previous: Option<TraceContext>,
+}
+
+impl ServerSpan {
+    /// Opens the span as a child of the caller's span, and makes it the parent of the calls made while handling.
+    #[allow(unused_variables)]
+    pub fn enter(trait_name: &str, method_name: &str) -> Self {
+        #[cfg(feature = "tracing")]
+        {
+            let parent = crate::call::current_call().and_then(|call| call.trace_parent());
+            let context = TraceContext::child_of(parent);
+            let span = tracing::info_span!(
+                "handle_call",
+                otel.name = %format_args!("{}::{}", trait_name, method_name),
+                otel.kind = "server",
+                rpc.service = trait_name,
+                rpc.method = method_name,
+                trace_id = %format_args!("{:032x}", context.trace_id),
+                span_id = %format_args!("{:016x}", context.span_id),
+                parent_span_id = parent.map(|x| tracing::field::display(format!("{:016x}", x.span_id))),
+            );
+            let previous = CURRENT.with(|current| current.replace(Some(context)));
+            ServerSpan {
+                _entered: span.entered(),
+                previous,
+            }
+        }
+        #[cfg(not(feature = "tracing"))]
+        ServerSpan {}
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for ServerSpan {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.previous));
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traceparent() {
+        let context = TraceContext::child_of(None);
+        assert_eq!(
+            TraceContext::from_traceparent(&context.to_string()),
+            Some(context)
+        );
+        let child = TraceContext::child_of(Some(context));
+        assert_eq!(child.trace_id(), context.trace_id());
+        assert_ne!(child.span_id(), context.span_id());
+
+        let parsed = TraceContext::from_traceparent(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        )
+        .unwrap();
+        assert_eq!(parsed.trace_id(), 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert_eq!(parsed.span_id(), 0x00f067aa0ba902b7);
+        assert_eq!(TraceContext::from_traceparent("01-00-00-01"), None);
+        assert_eq!(TraceContext::from_traceparent("garbage"), None);
+    }
+}
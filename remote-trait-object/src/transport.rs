@@ -3,8 +3,29 @@
 //! You have to implement these traits in your own requirement, to use `remote-trait-object` over them.
 //! It can be ordinary in-process communication, inter-process communication, or even networking over
 //! different machines.
+//!
+//! Some common transports are provided in the submodules. [`intra`], [`framed`] and [`process`] are always available,
+//! and the others are behind cargo features of the same names.
+//! [`asynchronous`] provides the traits of an asynchronous transport for [`AsyncContext`], behind the `tokio` feature.
+//!
+//! [`intra`]: intra/index.html
+//! [`framed`]: framed/index.html
+//! [`process`]: process/index.html
+//! [`asynchronous`]: asynchronous/index.html
+//! [`AsyncContext`]: ../struct.AsyncContext.html
 
+#[cfg(feature = "tokio")]
+pub mod asynchronous;
+pub mod framed;
+pub mod intra;
 pub(crate) mod multiplex;
+pub mod process;
+#[cfg(all(feature = "shm", target_os = "linux"))]
+pub mod shm;
+#[cfg(feature = "tcp")]
+pub mod tcp;
+#[cfg(all(feature = "unix", unix))]
+pub mod unix;
 
 /// An error that can be returned in [`send()`] or [`recv()`].
 ///
@@ -24,6 +45,12 @@ pub enum TransportError {
     /// [`terminate()`]: trait.Terminate.html#tymethod.terminate
     Termination,
 
+    /// An error that indicates that a packet is larger than the transport can carry.
+    ///
+    /// It is returned by `send()` for an outgoing packet, without breaking the connection.
+    /// If it is returned by `recv()`, the connection can't be used anymore.
+    FrameTooLarge { size: usize, max: usize },
+
     // TODO: Provide an appropriate type for this
     /// An opaque error that will be just passed to the user.
     Custom,
@@ -0,0 +1,153 @@
+//! Abstractions of an asynchronous transport, which is used by [`AsyncContext`].
+//!
+//! Unlike [`TransportSend`] and [`TransportRecv`], they don't take a timeout nor provide a terminator.
+//! [`AsyncContext`] wraps them with its own timeout if needed, and stops a pending call by dropping its future.
+//!
+//! [`framed()`] makes a transport out of any tokio byte stream, like a `TcpStream` or a `UnixStream`.
+//! It uses the same frame format as [`framed`](../framed/index.html) and [`tcp`](../tcp/index.html),
+//! so an `AsyncContext` can talk to a `Context` over a socket.
+//!
+//! [`AsyncContext`]: ../../struct.AsyncContext.html
+//! [`TransportSend`]: ../trait.TransportSend.html
+//! [`TransportRecv`]: ../trait.TransportRecv.html
+//! [`framed()`]: fn.framed.html
+
+use super::TransportError;
+use crate::BoxFuture;
+use std::fmt;
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::Mutex;
+
+/// An abstraction of a sending half of an asynchronous transport.
+///
+/// It is shared by all tasks of an [`AsyncContext`], so `send()` may be called concurrently.
+/// Each packet must be sent as a whole, without being interleaved with another.
+///
+/// [`AsyncContext`]: ../../struct.AsyncContext.html
+pub trait AsyncTransportSend: Send + Sync + fmt::Debug {
+    /// Sends a packet.
+    fn send<'a>(&'a self, data: &'a [u8]) -> BoxFuture<'a, Result<(), TransportError>>;
+}
+
+/// An abstraction of a receiving half of an asynchronous transport.
+///
+/// It is owned by a single task of an [`AsyncContext`].
+///
+/// [`AsyncContext`]: ../../struct.AsyncContext.html
+pub trait AsyncTransportRecv: Send {
+    /// Receives a packet.
+    ///
+    /// The future may be dropped before it completes, when the context is closed.
+    fn recv(&mut self) -> BoxFuture<'_, Result<Vec<u8>, TransportError>>;
+}
+
+const LENGTH_SIZE: usize = std::mem::size_of::<u32>();
+
+fn map_error(err: io::Error) -> TransportError {
+    match err.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => TransportError::TimeOut,
+        _ => {
+            debug!("Async framed transport error: {}", err);
+            TransportError::Custom
+        }
+    }
+}
+
+/// The sending half over an [`AsyncWrite`](https://docs.rs/tokio/1/tokio/io/trait.AsyncWrite.html).
+pub struct AsyncFramedSend<W> {
+    writer: Mutex<W>,
+    max_frame_size: usize,
+}
+
+impl<W> fmt::Debug for AsyncFramedSend<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncFramedSend")
+            .field("max_frame_size", &self.max_frame_size)
+            .finish()
+    }
+}
+
+impl<W: AsyncWrite + Unpin + Send> AsyncTransportSend for AsyncFramedSend<W> {
+    fn send<'a>(&'a self, data: &'a [u8]) -> BoxFuture<'a, Result<(), TransportError>> {
+        Box::pin(async move {
+            if data.len() > self.max_frame_size {
+                return Err(TransportError::FrameTooLarge {
+                    size: data.len(),
+                    max: self.max_frame_size,
+                });
+            }
+            let mut frame = Vec::with_capacity(LENGTH_SIZE + data.len());
+            frame.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            frame.extend_from_slice(data);
+
+            let mut writer = self.writer.lock().await;
+            writer.write_all(&frame).await.map_err(map_error)?;
+            writer.flush().await.map_err(map_error)
+        })
+    }
+}
+
+/// The receiving half over an [`AsyncRead`](https://docs.rs/tokio/1/tokio/io/trait.AsyncRead.html).
+pub struct AsyncFramedRecv<R> {
+    reader: R,
+    max_frame_size: usize,
+}
+
+impl<R: AsyncRead + Unpin + Send> AsyncTransportRecv for AsyncFramedRecv<R> {
+    fn recv(&mut self) -> BoxFuture<'_, Result<Vec<u8>, TransportError>> {
+        Box::pin(async move {
+            let mut length = [0u8; LENGTH_SIZE];
+            self.reader
+                .read_exact(&mut length)
+                .await
+                .map_err(map_error)?;
+            let size = u32::from_le_bytes(length) as usize;
+            if size > self.max_frame_size {
+                warn!(
+                    "Received a frame of {} bytes, which exceeds the maximum {}",
+                    size, self.max_frame_size
+                );
+                return Err(TransportError::FrameTooLarge {
+                    size,
+                    max: self.max_frame_size,
+                });
+            }
+            let mut frame = vec![0u8; size];
+            self.reader
+                .read_exact(&mut frame)
+                .await
+                .map_err(map_error)?;
+            Ok(frame)
+        })
+    }
+}
+
+/// Creates a `(send, recv)` pair over a reader and a writer, each frame of which is prefixed with its length.
+///
+/// Use [`DEFAULT_MAX_FRAME_SIZE`](../framed/constant.DEFAULT_MAX_FRAME_SIZE.html) unless you have a reason not to.
+/// ```ignore
+/// let (reader, writer) = TcpStream::connect(addr).await?.into_split();
+/// let (send, recv) = asynchronous::framed(reader, writer, framed::DEFAULT_MAX_FRAME_SIZE);
+/// ```
+pub fn framed<R, W>(
+    reader: R,
+    writer: W,
+    max_frame_size: usize,
+) -> (AsyncFramedSend<W>, AsyncFramedRecv<R>)
+where
+    R: AsyncRead + Unpin + Send,
+    W: AsyncWrite + Unpin + Send,
+{
+    let max_frame_size = max_frame_size.min(u32::MAX as usize);
+    (
+        AsyncFramedSend {
+            writer: Mutex::new(writer),
+            max_frame_size,
+        },
+        AsyncFramedRecv {
+            reader,
+            max_frame_size,
+        },
+    )
+}
@@ -0,0 +1,360 @@
+//! An adapter that makes a transport out of any byte stream, like pipes or child stdio.
+//!
+//! Each packet is sent as a frame, prefixed with its length as a little-endian `u32`.
+//! A frame larger than the configured maximum is rejected with [`TransportError::FrameTooLarge`],
+//! both when sending and receiving.
+//!
+//! Since [`Read`] has no notion of timeout or termination, [`FramedRecv`] reads frames on its own thread.
+//! Its `recv()` honors the timeout and returns [`Termination`] right after being terminated,
+//! while the thread itself exits only after the stream is closed.
+//! Give a _shutdown_ callback that closes the stream to make it exit promptly.
+//! On the other hand, [`FramedSend`] writes on the calling thread. It honors the timeout by setting it on the writer,
+//! if it is given a way to do so with [`FramedSend::with_write_timeout()`]. Otherwise a write can't be interrupted,
+//! and `send()` returns [`TimeOut`] after the frame is written, if it has taken longer than the timeout.
+//! Note that a write that times out may leave a partial frame in the stream, after which the connection is broken.
+//!
+//! A new stream-based transport only needs to call [`new()`]:
+//! ```ignore
+//! let stream = TcpStream::connect(addr)?;
+//! let shutdown = stream.try_clone()?;
+//! let (send, recv) = framed::new(
+//!     stream.try_clone()?,
+//!     stream,
+//!     framed::DEFAULT_MAX_FRAME_SIZE,
+//!     move || drop(shutdown.shutdown(Shutdown::Both)),
+//! );
+//! ```
+//!
+//! [`TransportError::FrameTooLarge`]: ../enum.TransportError.html#variant.FrameTooLarge
+//! [`Termination`]: ../enum.TransportError.html#variant.Termination
+//! [`Read`]: https://doc.rust-lang.org/std/io/trait.Read.html
+//! [`FramedRecv`]: struct.FramedRecv.html
+//! [`FramedSend`]: struct.FramedSend.html
+//! [`FramedSend::with_write_timeout()`]: struct.FramedSend.html#method.with_write_timeout
+//! [`TimeOut`]: ../enum.TransportError.html#variant.TimeOut
+//! [`new()`]: fn.new.html
+
+use super::{Terminate, TransportError, TransportRecv, TransportSend};
+use crossbeam::channel::{bounded, Receiver, Select, Sender};
+use parking_lot::Mutex;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The default maximum size of a frame, which is 64MiB.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+const LENGTH_SIZE: usize = std::mem::size_of::<u32>();
+/// Number of received frames that can be queued before the reader thread blocks.
+const RECV_QUEUE_SIZE: usize = 16;
+
+type ShutdownFn = Arc<dyn Fn() + Send + Sync>;
+type WriteTimeoutFn<W> = Box<dyn Fn(&W, Option<Duration>) -> io::Result<()> + Send + Sync>;
+
+/// A switch shared by a half of the transport and its terminators.
+struct Termination {
+    terminated: AtomicBool,
+    /// Wakes up a blocking `recv()`. It stays filled once terminated.
+    wake_send: Sender<()>,
+    wake_recv: Receiver<()>,
+    shutdown: ShutdownFn,
+}
+
+impl Termination {
+    fn new(shutdown: ShutdownFn) -> Arc<Self> {
+        let (wake_send, wake_recv) = bounded(1);
+        Arc::new(Termination {
+            terminated: AtomicBool::new(false),
+            wake_send,
+            wake_recv,
+            shutdown,
+        })
+    }
+
+    fn is_terminated(&self) -> bool {
+        self.terminated.load(Ordering::SeqCst)
+    }
+}
+
+/// A [`Terminate`] of [`FramedSend`] or [`FramedRecv`], which calls the shutdown callback.
+///
+/// [`Terminate`]: ../trait.Terminate.html
+/// [`FramedSend`]: struct.FramedSend.html
+/// [`FramedRecv`]: struct.FramedRecv.html
+pub struct FramedTerminator(Arc<Termination>);
+
+impl Terminate for FramedTerminator {
+    fn terminate(&self) {
+        if !self.0.terminated.swap(true, Ordering::SeqCst) {
+            let _ = self.0.wake_send.try_send(());
+            (self.0.shutdown)();
+        }
+    }
+}
+
+fn map_error(err: io::Error, terminated: bool) -> TransportError {
+    if terminated {
+        return TransportError::Termination;
+    }
+    match err.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => TransportError::TimeOut,
+        _ => {
+            debug!("Framed transport error: {}", err);
+            TransportError::Custom
+        }
+    }
+}
+
+/// Returns the time left until the deadline, or `Err(TimeOut)` if it has passed.
+fn remaining(deadline: Option<Instant>) -> Result<Option<Duration>, TransportError> {
+    match deadline {
+        None => Ok(None),
+        Some(deadline) => {
+            let now = Instant::now();
+            if now >= deadline {
+                Err(TransportError::TimeOut)
+            } else {
+                Ok(Some(deadline - now))
+            }
+        }
+    }
+}
+
+/// The sending half over a [`Write`](https://doc.rust-lang.org/std/io/trait.Write.html).
+pub struct FramedSend<W: Write + Send> {
+    writer: Mutex<W>,
+    max_frame_size: usize,
+    set_write_timeout: Option<WriteTimeoutFn<W>>,
+    termination: Arc<Termination>,
+}
+
+impl<W: Write + Send> fmt::Debug for FramedSend<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FramedSend")
+            .field("max_frame_size", &self.max_frame_size)
+            .field("terminated", &self.termination.is_terminated())
+            .finish()
+    }
+}
+
+impl<W: Write + Send> FramedSend<W> {
+    /// Creates a sending half. `shutdown` is called when it is terminated, and should wake up a blocking write.
+    pub fn new(
+        writer: W,
+        max_frame_size: usize,
+        shutdown: impl Fn() + Send + Sync + 'static,
+    ) -> Self {
+        FramedSend {
+            writer: Mutex::new(writer),
+            max_frame_size: max_frame_size.min(u32::MAX as usize),
+            set_write_timeout: None,
+            termination: Termination::new(Arc::new(shutdown)),
+        }
+    }
+
+    /// Lets `send()` honor its timeout by setting it on the writer before each write,
+    /// like [`TcpStream::set_write_timeout()`](https://doc.rust-lang.org/std/net/struct.TcpStream.html#method.set_write_timeout).
+    pub fn with_write_timeout(
+        mut self,
+        set_write_timeout: impl Fn(&W, Option<Duration>) -> io::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.set_write_timeout = Some(Box::new(set_write_timeout));
+        self
+    }
+}
+
+impl<W: Write + Send> TransportSend for FramedSend<W> {
+    fn send(&self, data: &[u8], timeout: Option<Duration>) -> Result<(), TransportError> {
+        if self.termination.is_terminated() {
+            return Err(TransportError::Termination);
+        }
+        if data.len() > self.max_frame_size {
+            return Err(TransportError::FrameTooLarge {
+                size: data.len(),
+                max: self.max_frame_size,
+            });
+        }
+        let mut frame = Vec::with_capacity(LENGTH_SIZE + data.len());
+        frame.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        frame.extend_from_slice(data);
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut writer = match timeout {
+            Some(timeout) => self
+                .writer
+                .try_lock_for(timeout)
+                .ok_or(TransportError::TimeOut)?,
+            None => self.writer.lock(),
+        };
+        if let Some(set_write_timeout) = &self.set_write_timeout {
+            set_write_timeout(&writer, remaining(deadline)?)
+                .map_err(|err| map_error(err, self.termination.is_terminated()))?;
+        }
+        writer
+            .write_all(&frame)
+            .and_then(|_| writer.flush())
+            .map_err(|err| map_error(err, self.termination.is_terminated()))?;
+        if self.set_write_timeout.is_none() {
+            remaining(deadline)?;
+        }
+        Ok(())
+    }
+
+    fn create_terminator(&self) -> Box<dyn Terminate> {
+        Box::new(FramedTerminator(Arc::clone(&self.termination)))
+    }
+}
+
+/// The receiving half over a [`Read`](https://doc.rust-lang.org/std/io/trait.Read.html).
+pub struct FramedRecv {
+    frames: Receiver<Result<Vec<u8>, TransportError>>,
+    /// The error that has closed the stream, which is returned for all later calls.
+    closed: Mutex<Option<TransportError>>,
+    termination: Arc<Termination>,
+}
+
+impl fmt::Debug for FramedRecv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FramedRecv")
+            .field("closed", &*self.closed.lock())
+            .field("terminated", &self.termination.is_terminated())
+            .finish()
+    }
+}
+
+impl FramedRecv {
+    /// Creates a receiving half, spawning a thread that reads frames from `reader`.
+    /// `shutdown` is called when it is terminated, and should wake up a blocking read.
+    pub fn new<R: Read + Send + 'static>(
+        reader: R,
+        max_frame_size: usize,
+        shutdown: impl Fn() + Send + Sync + 'static,
+    ) -> Self {
+        let (frame_send, frames) = bounded(RECV_QUEUE_SIZE);
+        thread::Builder::new()
+            .name("framed reader".to_owned())
+            .spawn(move || read_loop(reader, max_frame_size, frame_send))
+            .unwrap();
+        FramedRecv {
+            frames,
+            closed: Mutex::new(None),
+            termination: Termination::new(Arc::new(shutdown)),
+        }
+    }
+}
+
+fn read_frame(reader: &mut impl Read, max_frame_size: usize) -> Result<Vec<u8>, TransportError> {
+    let mut length = [0u8; LENGTH_SIZE];
+    reader
+        .read_exact(&mut length)
+        .map_err(|err| map_error(err, false))?;
+    let size = u32::from_le_bytes(length) as usize;
+    if size > max_frame_size {
+        warn!(
+            "Received a frame of {} bytes, which exceeds the maximum {}",
+            size, max_frame_size
+        );
+        return Err(TransportError::FrameTooLarge {
+            size,
+            max: max_frame_size,
+        });
+    }
+    let mut frame = vec![0u8; size];
+    reader
+        .read_exact(&mut frame)
+        .map_err(|err| map_error(err, false))?;
+    Ok(frame)
+}
+
+fn read_loop(
+    mut reader: impl Read,
+    max_frame_size: usize,
+    frame_send: Sender<Result<Vec<u8>, TransportError>>,
+) {
+    loop {
+        let frame = read_frame(&mut reader, max_frame_size);
+        let is_err = frame.is_err();
+        if frame_send.send(frame).is_err() || is_err {
+            // Either FramedRecv is dropped, or we can't find the next frame anymore.
+            return;
+        }
+    }
+}
+
+impl TransportRecv for FramedRecv {
+    fn recv(&self, timeout: Option<Duration>) -> Result<Vec<u8>, TransportError> {
+        if self.termination.is_terminated() {
+            return Err(TransportError::Termination);
+        }
+        if let Some(err) = self.closed.lock().clone() {
+            return Err(err);
+        }
+        let mut selector = Select::new();
+        let frames_index = selector.recv(&self.frames);
+        let terminator_index = selector.recv(&self.termination.wake_recv);
+
+        let selected_op = if let Some(timeout) = timeout {
+            selector
+                .select_timeout(timeout)
+                .map_err(|_| TransportError::TimeOut)?
+        } else {
+            selector.select()
+        };
+
+        match selected_op.index() {
+            i if i == frames_index => {
+                let result = selected_op
+                    .recv(&self.frames)
+                    .unwrap_or(Err(TransportError::Custom));
+                if let Err(err) = &result {
+                    self.closed.lock().replace(err.clone());
+                }
+                result
+            }
+            i if i == terminator_index => {
+                let _ = selected_op.recv(&self.termination.wake_recv);
+                let _ = self.termination.wake_send.try_send(());
+                Err(TransportError::Termination)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn create_terminator(&self) -> Box<dyn Terminate> {
+        Box::new(FramedTerminator(Arc::clone(&self.termination)))
+    }
+}
+
+/// Calls the shutdown callback when dropped.
+struct ShutdownOnDrop(ShutdownFn);
+
+impl Drop for ShutdownOnDrop {
+    fn drop(&mut self) {
+        (self.0)()
+    }
+}
+
+/// Creates a `(send, recv)` pair over a reader and a writer that share a single `shutdown` callback.
+///
+/// `shutdown` is also called when both halves and their terminators are dropped,
+/// so that the reader thread exits and the other end sees the stream closed.
+pub fn new<R, W>(
+    reader: R,
+    writer: W,
+    max_frame_size: usize,
+    shutdown: impl Fn() + Send + Sync + 'static,
+) -> (FramedSend<W>, FramedRecv)
+where
+    R: Read + Send + 'static,
+    W: Write + Send,
+{
+    let shutdown = Arc::new(ShutdownOnDrop(Arc::new(shutdown)));
+    let shutdown_ = Arc::clone(&shutdown);
+    (
+        FramedSend::new(writer, max_frame_size, move || (shutdown_.0)()),
+        FramedRecv::new(reader, max_frame_size, move || (shutdown.0)()),
+    )
+}
@@ -0,0 +1,190 @@
+//! An in-process transport over crossbeam channels.
+//!
+//! This is useful for connecting two [`Context`]s in a single process,
+//! for example to isolate a plugin on its own thread pool, or to test your services.
+//!
+//! [`Context`]: ../../struct.Context.html
+
+use super::{Terminate, TransportError, TransportRecv, TransportSend};
+use crossbeam::channel::{bounded, Receiver, Select, Sender};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The default number of packets that can be queued in each direction.
+pub const DEFAULT_CAPACITY: usize = 256;
+
+/// A switch shared by a half of the transport and its terminators.
+#[derive(Debug)]
+struct Termination {
+    terminated: AtomicBool,
+    /// Wakes up blocking operations. It has capacity 1, and stays filled once terminated.
+    wake_send: Sender<()>,
+    wake_recv: Receiver<()>,
+}
+
+impl Termination {
+    fn new() -> Arc<Self> {
+        let (wake_send, wake_recv) = bounded(1);
+        Arc::new(Termination {
+            terminated: AtomicBool::new(false),
+            wake_send,
+            wake_recv,
+        })
+    }
+
+    fn is_terminated(&self) -> bool {
+        self.terminated.load(Ordering::SeqCst)
+    }
+
+    /// Fills the wake-up channel again after consuming it, so that other blocking operations also wake up.
+    fn rewake(&self) {
+        let _ = self.wake_send.try_send(());
+    }
+}
+
+/// A [`Terminate`] of [`IntraSend`] or [`IntraRecv`].
+///
+/// [`Terminate`]: ../trait.Terminate.html
+/// [`IntraSend`]: struct.IntraSend.html
+/// [`IntraRecv`]: struct.IntraRecv.html
+#[derive(Debug)]
+pub struct IntraTerminator(Arc<Termination>);
+
+impl Terminate for IntraTerminator {
+    fn terminate(&self) {
+        if !self.0.terminated.swap(true, Ordering::SeqCst) {
+            self.0.rewake();
+        }
+    }
+}
+
+/// The sending half of the in-process transport.
+#[derive(Debug)]
+pub struct IntraSend {
+    data_sender: Sender<Vec<u8>>,
+    termination: Arc<Termination>,
+}
+
+impl TransportSend for IntraSend {
+    fn send(&self, data: &[u8], timeout: Option<Duration>) -> Result<(), TransportError> {
+        if self.termination.is_terminated() {
+            return Err(TransportError::Termination);
+        }
+        let data = data.to_vec();
+        let mut selector = Select::new();
+        let data_sender_index = selector.send(&self.data_sender);
+        let terminator_index = selector.recv(&self.termination.wake_recv);
+
+        let selected_op = if let Some(timeout) = timeout {
+            selector
+                .select_timeout(timeout)
+                .map_err(|_| TransportError::TimeOut)?
+        } else {
+            selector.select()
+        };
+
+        match selected_op.index() {
+            i if i == data_sender_index => {
+                selected_op.send(&self.data_sender, data).map_err(|_| {
+                    debug!("Counterparty connection is closed in Intra");
+                    TransportError::Custom
+                })
+            }
+            i if i == terminator_index => {
+                let _ = selected_op.recv(&self.termination.wake_recv);
+                self.termination.rewake();
+                Err(TransportError::Termination)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn create_terminator(&self) -> Box<dyn Terminate> {
+        Box::new(IntraTerminator(Arc::clone(&self.termination)))
+    }
+}
+
+/// The receiving half of the in-process transport.
+#[derive(Debug)]
+pub struct IntraRecv {
+    data_receiver: Receiver<Vec<u8>>,
+    termination: Arc<Termination>,
+}
+
+impl TransportRecv for IntraRecv {
+    fn recv(&self, timeout: Option<Duration>) -> Result<Vec<u8>, TransportError> {
+        if self.termination.is_terminated() {
+            return Err(TransportError::Termination);
+        }
+        let mut selector = Select::new();
+        let data_receiver_index = selector.recv(&self.data_receiver);
+        let terminator_index = selector.recv(&self.termination.wake_recv);
+
+        let selected_op = if let Some(timeout) = timeout {
+            selector
+                .select_timeout(timeout)
+                .map_err(|_| TransportError::TimeOut)?
+        } else {
+            selector.select()
+        };
+
+        match selected_op.index() {
+            i if i == data_receiver_index => selected_op.recv(&self.data_receiver).map_err(|_| {
+                debug!("Counterparty connection is closed in Intra");
+                TransportError::Custom
+            }),
+            i if i == terminator_index => {
+                let _ = selected_op.recv(&self.termination.wake_recv);
+                self.termination.rewake();
+                Err(TransportError::Termination)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn create_terminator(&self) -> Box<dyn Terminate> {
+        Box::new(IntraTerminator(Arc::clone(&self.termination)))
+    }
+}
+
+/// Two ends of a connection, where `send1` delivers to `recv2` and `send2` delivers to `recv1`.
+#[derive(Debug)]
+pub struct TransportEnds {
+    pub send1: IntraSend,
+    pub recv1: IntraRecv,
+    pub send2: IntraSend,
+    pub recv2: IntraRecv,
+}
+
+/// Creates a connection with [`DEFAULT_CAPACITY`](constant.DEFAULT_CAPACITY.html).
+pub fn create() -> TransportEnds {
+    create_with_capacity(DEFAULT_CAPACITY)
+}
+
+/// Creates a connection that can queue `capacity` packets in each direction.
+///
+/// `send()` blocks while the queue is full. A capacity of 0 makes each `send()` wait for the matching `recv()`.
+pub fn create_with_capacity(capacity: usize) -> TransportEnds {
+    let (a_sender, a_receiver) = bounded(capacity);
+    let (b_sender, b_receiver) = bounded(capacity);
+
+    TransportEnds {
+        send1: IntraSend {
+            data_sender: b_sender,
+            termination: Termination::new(),
+        },
+        recv1: IntraRecv {
+            data_receiver: a_receiver,
+            termination: Termination::new(),
+        },
+        send2: IntraSend {
+            data_sender: a_sender,
+            termination: Termination::new(),
+        },
+        recv2: IntraRecv {
+            data_receiver: b_receiver,
+            termination: Termination::new(),
+        },
+    }
+}
@@ -1,8 +1,11 @@
-use crate::packet::PacketView;
+use crate::packet::{PacketView, ProtocolError, ProtocolErrorReporter};
+use crate::service::ServiceSignature;
 use crate::transport::{Terminate, TransportError, TransportRecv};
 use crate::Config;
 use crossbeam::channel::{self, Receiver, Sender};
 use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread;
 
 pub struct MultiplexedRecv {
@@ -11,10 +14,13 @@ pub struct MultiplexedRecv {
 
 impl TransportRecv for MultiplexedRecv {
     fn recv(&self, timeout: Option<std::time::Duration>) -> Result<Vec<u8>, TransportError> {
+        // The multiplexer may have already quit after delivering its last error.
         if let Some(timeout) = timeout {
-            self.recv.recv_timeout(timeout).unwrap()
+            self.recv
+                .recv_timeout(timeout)
+                .unwrap_or(Err(TransportError::Custom))
         } else {
-            self.recv.recv().unwrap()
+            self.recv.recv().unwrap_or(Err(TransportError::Custom))
         }
     }
 
@@ -27,10 +33,13 @@ impl TransportRecv for MultiplexedRecv {
 pub enum ForwardResult {
     Request,
     Response,
+    /// A cancellation of a call, which goes to the same place as the requests.
+    Cancel,
+    Control,
 }
 
 pub trait Forward {
-    fn forward(data: PacketView) -> ForwardResult;
+    fn forward(data: &PacketView) -> ForwardResult;
 }
 
 pub struct MultiplexResult {
@@ -43,12 +52,17 @@ pub struct Multiplexer {
     receiver_thread: Option<thread::JoinHandle<()>>,
     /// Here Mutex is used to make the Multiplxer Sync, while dyn Terminate isn't.
     receiver_terminator: Option<Mutex<Box<dyn Terminate>>>,
+    /// Whether the connection has been closed, by the other end or by a transport error.
+    closed: Arc<AtomicBool>,
 }
 
 impl Multiplexer {
+    /// Starts receiving, after the handshake that must tell an initial service compatible with `initial_service`.
     pub fn multiplex<TransportReceiver, Forwarder>(
         config: Config,
         transport_recv: TransportReceiver,
+        protocol_error: ProtocolErrorReporter,
+        initial_service: ServiceSignature,
     ) -> MultiplexResult
     where
         TransportReceiver: TransportRecv + 'static,
@@ -58,6 +72,8 @@ impl Multiplexer {
         let (response_send, response_recv) = channel::bounded(1);
         let receiver_terminator: Option<Mutex<Box<dyn Terminate>>> =
             Some(Mutex::new(transport_recv.create_terminator()));
+        let closed = Arc::new(AtomicBool::new(false));
+        let closed_ = Arc::clone(&closed);
 
         let receiver_thread = thread::Builder::new()
             .name(format!("[{}] receiver multiplexer", config.name))
@@ -66,6 +82,9 @@ impl Multiplexer {
                     transport_recv,
                     request_send,
                     response_send,
+                    protocol_error,
+                    closed_,
+                    initial_service,
                 )
             })
             .unwrap();
@@ -78,6 +97,7 @@ impl Multiplexer {
             multiplexer: Multiplexer {
                 receiver_thread: Some(receiver_thread),
                 receiver_terminator,
+                closed,
             },
         }
     }
@@ -91,6 +111,10 @@ impl Multiplexer {
         self.receiver_thread.take().unwrap().join().unwrap();
     }
 
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
     pub fn wait(mut self, _timeout: Option<std::time::Duration>) -> Result<(), Self> {
         self.receiver_thread.take().unwrap().join().unwrap();
         Ok(())
@@ -101,24 +125,50 @@ fn receiver_loop<Forwarder: Forward, Receiver: TransportRecv>(
     transport_recv: Receiver,
     request_send: Sender<Result<Vec<u8>, TransportError>>,
     response_send: Sender<Result<Vec<u8>, TransportError>>,
+    protocol_error: ProtocolErrorReporter,
+    closed: Arc<AtomicBool>,
+    initial_service: ServiceSignature,
 ) {
+    let close = |err: TransportError| {
+        if err != TransportError::Termination {
+            debug!("Connection is closed: {:?}", err);
+            closed.store(true, Ordering::SeqCst);
+        }
+        // The receiving sides may have already quit, if the context is being dropped.
+        let _ = request_send.send(Err(err.clone()));
+        let _ = response_send.send(Err(err));
+    };
+
+    // The first packet must be a handshake, so that we never misinterpret packets of another protocol version.
+    let handshake = match transport_recv.recv(None) {
+        Err(err) => return close(err),
+        Ok(data) => data,
+    };
+    if let Err(reason) = PacketView::new(&handshake).check_handshake(&initial_service) {
+        protocol_error.report(ProtocolError::Handshake(reason));
+        return close(TransportError::Custom);
+    }
+
     loop {
         let message = match transport_recv.recv(None) {
-            Err(err) => {
-                request_send.send(Err(err.clone())).unwrap();
-                response_send.send(Err(err)).unwrap();
-                return;
-            }
+            Err(err) => return close(err),
             Ok(data) => data,
         };
 
+        if let Err(err) = PacketView::validate(&message) {
+            protocol_error.report(err);
+            continue;
+        }
         let packet_view = PacketView::new(&message);
         trace!("Receive message in multiplex {}", packet_view);
-        let forward_result = Forwarder::forward(packet_view);
+        let forward_result = Forwarder::forward(&packet_view);
 
         match forward_result {
-            ForwardResult::Request => request_send.send(Ok(message)).unwrap(),
+            ForwardResult::Request | ForwardResult::Cancel => {
+                request_send.send(Ok(message)).unwrap()
+            }
             ForwardResult::Response => response_send.send(Ok(message)).unwrap(),
+            ForwardResult::Control => debug!("Unexpected control packet {}", packet_view),
         }
     }
 }
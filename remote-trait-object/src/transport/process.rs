@@ -0,0 +1,150 @@
+//! A transport over the stdin and stdout of a child process, which is built on [`framed`].
+//!
+//! The parent spawns the child with [`spawn()`], and the child serves its service with [`serve_stdio()`].
+//! The child's stderr is left as is, so it can be used for logging.
+//! Note that the child must never write anything else to its stdout, since it would corrupt the transport.
+//!
+//! When the child exits, the parent's [`Context`] regards the connection as closed.
+//! See [`Context::is_closed()`].
+//!
+//! Terminating the parent's transport closes the child's stdin, so that the child's context is closed and it exits,
+//! which closes the child's stdout in turn and lets the reader thread exit.
+//! On Unix, terminating the child's transport redirects its stdout to `/dev/null`, so that the parent sees the pipe closed.
+//!
+//! ```ignore
+//! // In the parent
+//! let (context, calculator, mut child): (_, ServiceToImport<dyn Calculator>, _) =
+//!     process::spawn(&mut Command::new("calculator"), Config::default_setup())?;
+//!
+//! // In the child
+//! let context = process::serve_stdio(
+//!     Config::default_setup(),
+//!     ServiceToExport::new(Box::new(SimpleCalculator) as Box<dyn Calculator>),
+//! );
+//! context.wait(None).ok();
+//! ```
+//!
+//! [`framed`]: ../framed/index.html
+//! [`spawn()`]: fn.spawn.html
+//! [`serve_stdio()`]: fn.serve_stdio.html
+//! [`Context`]: ../../struct.Context.html
+//! [`Context::is_closed()`]: ../../struct.Context.html#method.is_closed
+
+use super::framed::{self, FramedRecv, FramedSend};
+use crate::{Config, Context, DescribeService, Service, ServiceToExport, ServiceToImport};
+use parking_lot::Mutex;
+use std::io::{self, Stdout, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::Arc;
+
+/// The stdin of a child process, which is closed when the transport is terminated.
+#[derive(Debug)]
+pub struct ChildInput(Arc<Mutex<Option<ChildStdin>>>);
+
+impl ChildInput {
+    /// Closes the pipe, unless a write is blocked on it.
+    fn close(&self) {
+        match self.0.try_lock() {
+            Some(mut stdin) => drop(stdin.take()),
+            None => debug!("Failed to close the stdin of the child, which is being written"),
+        }
+    }
+}
+
+impl Write for ChildInput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.0.lock().as_mut() {
+            Some(stdin) => stdin.write(buf),
+            None => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.0.lock().as_mut() {
+            Some(stdin) => stdin.flush(),
+            None => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+}
+
+/// Spawns the command with piped stdin and stdout, and returns a `(send, recv)` pair over them.
+pub fn spawn_transport(
+    command: &mut Command,
+) -> io::Result<(Child, FramedSend<ChildInput>, FramedRecv)> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let stdin = Arc::new(Mutex::new(child.stdin.take()));
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let closer = ChildInput(Arc::clone(&stdin));
+    let (send, recv) = framed::new(
+        stdout,
+        ChildInput(stdin),
+        framed::DEFAULT_MAX_FRAME_SIZE,
+        move || closer.close(),
+    );
+    Ok((child, send, recv))
+}
+
+/// Spawns the command, and imports the service that the child exports with [`serve_stdio()`].
+///
+/// Dropping the returned `Context` closes the child's stdin.
+/// The returned [`Child`] should be waited for, after that.
+///
+/// [`serve_stdio()`]: fn.serve_stdio.html
+/// [`Child`]: https://doc.rust-lang.org/std/process/struct.Child.html
+pub fn spawn<S: ?Sized + Service + DescribeService>(
+    command: &mut Command,
+    config: Config,
+) -> io::Result<(Context, ServiceToImport<S>, Child)> {
+    let (child, send, recv) = spawn_transport(command)?;
+    let (context, service) = Context::with_initial_service_import(config, send, recv);
+    Ok((context, service, child))
+}
+
+/// Returns a `(send, recv)` pair over the stdin and stdout of this process.
+pub fn stdio() -> (FramedSend<Stdout>, FramedRecv) {
+    framed::new(
+        io::stdin(),
+        io::stdout(),
+        framed::DEFAULT_MAX_FRAME_SIZE,
+        close_stdout,
+    )
+}
+
+/// Closes the pipe to the parent, which then closes the pipe to this process and lets the reader thread exit.
+///
+/// Stdout is redirected to `/dev/null` instead of being closed, so that its file descriptor is not reused.
+#[cfg(unix)]
+fn close_stdout() {
+    let _ = io::stdout().flush();
+    // Safety: dup2() replaces the file descriptor atomically, and only the temporary one is closed.
+    unsafe {
+        let null = libc::open(
+            b"/dev/null\0".as_ptr() as *const libc::c_char,
+            libc::O_WRONLY,
+        );
+        if null < 0 || libc::dup2(null, libc::STDOUT_FILENO) < 0 {
+            debug!("Failed to close stdout: {}", io::Error::last_os_error());
+        }
+        if null >= 0 {
+            libc::close(null);
+        }
+    }
+}
+
+/// Stdout can't be closed without replacing it on this platform,
+/// so the reader thread exits when the parent closes the pipe to this process.
+#[cfg(not(unix))]
+fn close_stdout() {}
+
+/// Creates a context over the stdin and stdout of this process, exporting the service to the parent.
+///
+/// It is supposed to be called in a child process spawned by [`spawn()`].
+///
+/// [`spawn()`]: fn.spawn.html
+pub fn serve_stdio<S: ?Sized + Service>(config: Config, service: ServiceToExport<S>) -> Context {
+    let (send, recv) = stdio();
+    Context::with_initial_service_export(config, send, recv, service)
+}
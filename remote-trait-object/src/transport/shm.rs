@@ -0,0 +1,506 @@
+//! A transport over shared memory, which avoids the copies through the kernel for large packets.
+//!
+//! A memory-mapped file holds two single-producer/single-consumer ring buffers, one for each direction.
+//! A blocked side sleeps on a futex in the shared memory, which is woken up by the other side.
+//!
+//! One process creates the file with [`create()`], and another process opens it with [`open()`].
+//! The file can be removed after both have mapped it. [`pair()`] creates both ends on an anonymous mapping,
+//! which can be shared with a child made by `fork()`, or used within a single process.
+//!
+//! Each half notifies the other side when dropped, which is then reported as a closed connection.
+//! However, a crash of the other process can't be detected; use timeouts for such a case.
+//!
+//! The positions and the lengths in the shared memory are written by the other process, so they are never trusted.
+//! If they are inconsistent, the ring is closed as if the half were dropped, and [`TransportError::Custom`] is returned.
+//!
+//! This module is available only on Linux.
+//!
+//! [`create()`]: fn.create.html
+//! [`open()`]: fn.open.html
+//! [`pair()`]: fn.pair.html
+//! [`TransportError::Custom`]: ../enum.TransportError.html#variant.Custom
+
+use super::{Terminate, TransportError, TransportRecv, TransportSend};
+use parking_lot::Mutex;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The default capacity of each ring buffer, which is 4MiB.
+pub const DEFAULT_CAPACITY: usize = 4 * 1024 * 1024;
+
+const MAGIC: u64 = 0x7274_6f2d_7368_6d31; // "rto-shm1"
+const LENGTH_SIZE: usize = std::mem::size_of::<u32>();
+/// Size of `FileHeader` and `RingHeader`, which also keeps the ring buffers aligned.
+const HEADER_SIZE: usize = 64;
+
+#[repr(C)]
+struct FileHeader {
+    magic: AtomicU64,
+    capacity: AtomicU64,
+}
+
+/// Header of a ring buffer. `head` and `tail` only increase, and are reduced modulo the capacity on access.
+#[repr(C)]
+struct RingHeader {
+    /// Written by the producer
+    head: AtomicU64,
+    /// Written by the consumer
+    tail: AtomicU64,
+    /// Futex that is bumped whenever `head` advances
+    data_seq: AtomicU32,
+    /// Futex that is bumped whenever `tail` advances
+    space_seq: AtomicU32,
+    producer_closed: AtomicU32,
+    consumer_closed: AtomicU32,
+}
+
+/// A shared memory mapping, which is unmapped when both halves and their terminators are dropped.
+struct Mapping {
+    address: *mut u8,
+    size: usize,
+    capacity: usize,
+}
+
+// Safety: all accesses to the mapping are synchronized by the atomics in the headers.
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.address as *mut libc::c_void, self.size);
+        }
+    }
+}
+
+fn mapping_size(capacity: usize) -> usize {
+    HEADER_SIZE + 2 * (HEADER_SIZE + capacity)
+}
+
+impl Mapping {
+    fn map(fd: Option<&File>, capacity: usize) -> io::Result<Arc<Self>> {
+        let size = mapping_size(capacity);
+        let (flags, fd) = match fd {
+            Some(file) => (libc::MAP_SHARED, file.as_raw_fd()),
+            None => (libc::MAP_SHARED | libc::MAP_ANONYMOUS, -1),
+        };
+        let address = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                flags,
+                fd,
+                0,
+            )
+        };
+        if address == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Arc::new(Mapping {
+            address: address as *mut u8,
+            size,
+            capacity,
+        }))
+    }
+
+    fn file_header(&self) -> &FileHeader {
+        unsafe { &*(self.address as *const FileHeader) }
+    }
+
+    fn ring(self: &Arc<Self>, index: usize) -> Ring {
+        Ring {
+            mapping: Arc::clone(self),
+            offset: HEADER_SIZE + index * (HEADER_SIZE + self.capacity),
+        }
+    }
+}
+
+/// One direction of the transport.
+struct Ring {
+    mapping: Arc<Mapping>,
+    offset: usize,
+}
+
+impl Ring {
+    fn header(&self) -> &RingHeader {
+        unsafe { &*(self.mapping.address.add(self.offset) as *const RingHeader) }
+    }
+
+    fn capacity(&self) -> usize {
+        self.mapping.capacity
+    }
+
+    /// Number of the bytes in the ring, or `None` if the positions are corrupted.
+    fn used(&self, head: u64, tail: u64) -> Option<u64> {
+        head.checked_sub(tail)
+            .filter(|used| *used <= self.capacity() as u64)
+    }
+
+    /// Copies `data` into the ring at the position `at`, wrapping around the end.
+    ///
+    /// # Safety
+    /// The region must be owned by the producer.
+    unsafe fn write(&self, at: u64, data: &[u8]) {
+        let buffer = self.mapping.address.add(self.offset + HEADER_SIZE);
+        let start = (at % self.capacity() as u64) as usize;
+        let first = data.len().min(self.capacity() - start);
+        ptr::copy_nonoverlapping(data.as_ptr(), buffer.add(start), first);
+        ptr::copy_nonoverlapping(data.as_ptr().add(first), buffer, data.len() - first);
+    }
+
+    /// Copies the bytes at the position `at` into `data`, wrapping around the end.
+    ///
+    /// # Safety
+    /// The region must be owned by the consumer.
+    unsafe fn read(&self, at: u64, data: &mut [u8]) {
+        let buffer = self.mapping.address.add(self.offset + HEADER_SIZE);
+        let start = (at % self.capacity() as u64) as usize;
+        let first = data.len().min(self.capacity() - start);
+        ptr::copy_nonoverlapping(buffer.add(start), data.as_mut_ptr(), first);
+        ptr::copy_nonoverlapping(buffer, data.as_mut_ptr().add(first), data.len() - first);
+    }
+}
+
+/// Sleeps while `futex` is `expected`, for at most `timeout`. It may return spuriously.
+fn futex_wait(futex: &AtomicU32, expected: u32, timeout: Option<Duration>) {
+    let timespec = timeout.map(|timeout| libc::timespec {
+        tv_sec: timeout.as_secs() as libc::time_t,
+        tv_nsec: timeout.subsec_nanos() as libc::c_long,
+    });
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            futex as *const AtomicU32,
+            libc::FUTEX_WAIT,
+            expected,
+            timespec
+                .as_ref()
+                .map_or(ptr::null(), |x| x as *const libc::timespec),
+        );
+    }
+}
+
+/// Bumps `futex` and wakes up all waiters on it, in any process.
+fn futex_wake(futex: &AtomicU32) {
+    futex.fetch_add(1, Ordering::Release);
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            futex as *const AtomicU32,
+            libc::FUTEX_WAKE,
+            i32::MAX,
+        );
+    }
+}
+
+/// Returns the time left until the deadline, or `Err(TimeOut)` if it has passed.
+fn remaining(deadline: Option<Instant>) -> Result<Option<Duration>, TransportError> {
+    match deadline {
+        None => Ok(None),
+        Some(deadline) => {
+            let now = Instant::now();
+            if now >= deadline {
+                Err(TransportError::TimeOut)
+            } else {
+                Ok(Some(deadline - now))
+            }
+        }
+    }
+}
+
+/// The sending half, which is the producer of a ring.
+pub struct ShmSend {
+    ring: Ring,
+    /// Serializes the senders, since the ring has a single producer.
+    lock: Mutex<()>,
+    terminated: Arc<AtomicBool>,
+}
+
+impl std::fmt::Debug for ShmSend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShmSend")
+            .field("capacity", &self.ring.capacity())
+            .finish()
+    }
+}
+
+impl TransportSend for ShmSend {
+    fn send(&self, data: &[u8], timeout: Option<Duration>) -> Result<(), TransportError> {
+        let max = self.ring.capacity() - LENGTH_SIZE;
+        if data.len() > max {
+            return Err(TransportError::FrameTooLarge {
+                size: data.len(),
+                max,
+            });
+        }
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let needed = (LENGTH_SIZE + data.len()) as u64;
+        let header = self.ring.header();
+        let _guard = self.lock.lock();
+        loop {
+            // The futex must be read first, so that we never miss a wake-up after the checks.
+            let seq = header.space_seq.load(Ordering::Acquire);
+            if self.terminated.load(Ordering::SeqCst) {
+                return Err(TransportError::Termination);
+            }
+            if header.producer_closed.load(Ordering::Acquire) != 0 {
+                return Err(TransportError::Custom);
+            }
+            if header.consumer_closed.load(Ordering::Acquire) != 0 {
+                debug!("Counterparty connection is closed in shm");
+                return Err(TransportError::Custom);
+            }
+            let head = header.head.load(Ordering::Relaxed);
+            let tail = header.tail.load(Ordering::Acquire);
+            let (used, new_head) = match self.ring.used(head, tail).zip(head.checked_add(needed)) {
+                Some(positions) => positions,
+                None => return Err(self.close_corrupted()),
+            };
+            if self.ring.capacity() as u64 - used >= needed {
+                unsafe {
+                    self.ring.write(head, &(data.len() as u32).to_le_bytes());
+                    self.ring.write(head + LENGTH_SIZE as u64, data);
+                }
+                header.head.store(new_head, Ordering::Release);
+                futex_wake(&header.data_seq);
+                return Ok(());
+            }
+            futex_wait(&header.space_seq, seq, remaining(deadline)?);
+        }
+    }
+
+    fn create_terminator(&self) -> Box<dyn Terminate> {
+        Box::new(ShmTerminator {
+            ring: Ring {
+                mapping: Arc::clone(&self.ring.mapping),
+                offset: self.ring.offset,
+            },
+            is_send: true,
+            terminated: Arc::clone(&self.terminated),
+        })
+    }
+}
+
+impl ShmSend {
+    fn close(&self) {
+        let header = self.ring.header();
+        header.producer_closed.store(1, Ordering::Release);
+        futex_wake(&header.data_seq);
+    }
+
+    fn close_corrupted(&self) -> TransportError {
+        warn!("Corrupted shared memory ring buffer");
+        self.close();
+        TransportError::Custom
+    }
+}
+
+impl Drop for ShmSend {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// The receiving half, which is the consumer of a ring.
+pub struct ShmRecv {
+    ring: Ring,
+    /// Serializes the receivers, since the ring has a single consumer.
+    lock: Mutex<()>,
+    terminated: Arc<AtomicBool>,
+}
+
+impl std::fmt::Debug for ShmRecv {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShmRecv")
+            .field("capacity", &self.ring.capacity())
+            .finish()
+    }
+}
+
+impl TransportRecv for ShmRecv {
+    fn recv(&self, timeout: Option<Duration>) -> Result<Vec<u8>, TransportError> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let header = self.ring.header();
+        let _guard = self.lock.lock();
+        loop {
+            // The futex must be read first, so that we never miss a wake-up after the checks.
+            let seq = header.data_seq.load(Ordering::Acquire);
+            if self.terminated.load(Ordering::SeqCst) {
+                return Err(TransportError::Termination);
+            }
+            if header.consumer_closed.load(Ordering::Acquire) != 0 {
+                return Err(TransportError::Custom);
+            }
+            let tail = header.tail.load(Ordering::Relaxed);
+            let head = header.head.load(Ordering::Acquire);
+            let used = match self.ring.used(head, tail) {
+                Some(used) => used as usize,
+                None => return Err(self.close_corrupted()),
+            };
+            if used != 0 {
+                if used < LENGTH_SIZE {
+                    return Err(self.close_corrupted());
+                }
+                let mut length = [0u8; LENGTH_SIZE];
+                unsafe { self.ring.read(tail, &mut length) };
+                let length = u32::from_le_bytes(length) as usize;
+                if length > used - LENGTH_SIZE {
+                    return Err(self.close_corrupted());
+                }
+                let new_tail = match tail.checked_add((LENGTH_SIZE + length) as u64) {
+                    Some(new_tail) => new_tail,
+                    None => return Err(self.close_corrupted()),
+                };
+                let mut data = vec![0u8; length];
+                unsafe { self.ring.read(tail + LENGTH_SIZE as u64, &mut data) };
+                header.tail.store(new_tail, Ordering::Release);
+                futex_wake(&header.space_seq);
+                return Ok(data);
+            }
+            if header.producer_closed.load(Ordering::Acquire) != 0 {
+                debug!("Counterparty connection is closed in shm");
+                return Err(TransportError::Custom);
+            }
+            futex_wait(&header.data_seq, seq, remaining(deadline)?);
+        }
+    }
+
+    fn create_terminator(&self) -> Box<dyn Terminate> {
+        Box::new(ShmTerminator {
+            ring: Ring {
+                mapping: Arc::clone(&self.ring.mapping),
+                offset: self.ring.offset,
+            },
+            is_send: false,
+            terminated: Arc::clone(&self.terminated),
+        })
+    }
+}
+
+impl ShmRecv {
+    fn close(&self) {
+        let header = self.ring.header();
+        header.consumer_closed.store(1, Ordering::Release);
+        futex_wake(&header.space_seq);
+    }
+
+    fn close_corrupted(&self) -> TransportError {
+        warn!("Corrupted shared memory ring buffer");
+        self.close();
+        TransportError::Custom
+    }
+}
+
+impl Drop for ShmRecv {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// A [`Terminate`] of [`ShmSend`] or [`ShmRecv`], which wakes up the futex that it is blocked on.
+///
+/// [`Terminate`]: ../trait.Terminate.html
+/// [`ShmSend`]: struct.ShmSend.html
+/// [`ShmRecv`]: struct.ShmRecv.html
+pub struct ShmTerminator {
+    ring: Ring,
+    is_send: bool,
+    terminated: Arc<AtomicBool>,
+}
+
+impl Terminate for ShmTerminator {
+    fn terminate(&self) {
+        self.terminated.store(true, Ordering::SeqCst);
+        let header = self.ring.header();
+        if self.is_send {
+            futex_wake(&header.space_seq);
+        } else {
+            futex_wake(&header.data_seq);
+        }
+    }
+}
+
+fn halves(mapping: &Arc<Mapping>, send_ring: usize, recv_ring: usize) -> (ShmSend, ShmRecv) {
+    (
+        ShmSend {
+            ring: mapping.ring(send_ring),
+            lock: Mutex::new(()),
+            terminated: Arc::new(AtomicBool::new(false)),
+        },
+        ShmRecv {
+            ring: mapping.ring(recv_ring),
+            lock: Mutex::new(()),
+            terminated: Arc::new(AtomicBool::new(false)),
+        },
+    )
+}
+
+// `usize::is_multiple_of()` is too new for the toolchains that this crate supports.
+#[allow(clippy::manual_is_multiple_of)]
+fn check_capacity(capacity: usize) -> io::Result<()> {
+    if capacity <= LENGTH_SIZE || capacity % HEADER_SIZE != 0 || capacity > u32::MAX as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Capacity must be a multiple of {} that fits in u32, but {} is given",
+                HEADER_SIZE, capacity
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Creates a file at `path` with rings of the given capacity, and returns the first end of it.
+///
+/// The capacity limits the size of a single packet, and must be a multiple of 64.
+pub fn create(path: impl AsRef<Path>, capacity: usize) -> io::Result<(ShmSend, ShmRecv)> {
+    check_capacity(capacity)?;
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+    file.set_len(mapping_size(capacity) as u64)?;
+    let mapping = Mapping::map(Some(&file), capacity)?;
+    mapping
+        .file_header()
+        .capacity
+        .store(capacity as u64, Ordering::Relaxed);
+    mapping.file_header().magic.store(MAGIC, Ordering::Release);
+    Ok(halves(&mapping, 0, 1))
+}
+
+/// Opens a file created by [`create()`](fn.create.html), and returns the second end of it.
+pub fn open(path: impl AsRef<Path>) -> io::Result<(ShmSend, ShmRecv)> {
+    let file = OpenOptions::new().read(true).write(true).open(path)?;
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Not a shared memory transport");
+    let size = file.metadata()?.len() as usize;
+    if size < HEADER_SIZE {
+        return Err(invalid());
+    }
+    let header = Mapping::map(Some(&file), 0)?;
+    if header.file_header().magic.load(Ordering::Acquire) != MAGIC {
+        return Err(invalid());
+    }
+    let capacity = header.file_header().capacity.load(Ordering::Relaxed) as usize;
+    if check_capacity(capacity).is_err() || mapping_size(capacity) != size {
+        return Err(invalid());
+    }
+    let mapping = Mapping::map(Some(&file), capacity)?;
+    Ok(halves(&mapping, 1, 0))
+}
+
+/// Creates both ends on an anonymous shared mapping.
+#[allow(clippy::type_complexity)]
+pub fn pair(capacity: usize) -> io::Result<((ShmSend, ShmRecv), (ShmSend, ShmRecv))> {
+    check_capacity(capacity)?;
+    let mapping = Mapping::map(None, capacity)?;
+    Ok((halves(&mapping, 0, 1), halves(&mapping, 1, 0)))
+}
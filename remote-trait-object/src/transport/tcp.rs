@@ -0,0 +1,63 @@
+//! A transport over [`std::net::TcpStream`].
+//!
+//! Use [`connect()`] or [`accept()`] to get a `(send, recv)` pair that can be directly given to
+//! [`Context::with_initial_service()`].
+//! It is built on [`framed`], and a timeout of `send()` is set on the stream.
+//! A frame is limited to [`DEFAULT_MAX_FRAME_SIZE`], unless the stream is split with [`split_with_max_frame_size()`].
+//!
+//! [`std::net::TcpStream`]: https://doc.rust-lang.org/std/net/struct.TcpStream.html
+//! [`connect()`]: fn.connect.html
+//! [`accept()`]: fn.accept.html
+//! [`framed`]: ../framed/index.html
+//! [`DEFAULT_MAX_FRAME_SIZE`]: ../framed/constant.DEFAULT_MAX_FRAME_SIZE.html
+//! [`split_with_max_frame_size()`]: fn.split_with_max_frame_size.html
+//! [`Context::with_initial_service()`]: ../../struct.Context.html#method.with_initial_service
+
+use super::framed::{self, FramedRecv, FramedSend, FramedTerminator};
+use std::io;
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+
+/// The sending half of a TCP connection.
+pub type TcpSend = FramedSend<TcpStream>;
+/// The receiving half of a TCP connection.
+pub type TcpRecv = FramedRecv;
+/// A terminator of [`TcpSend`](type.TcpSend.html) or [`TcpRecv`](type.TcpRecv.html).
+pub type TcpTerminator = FramedTerminator;
+
+/// Splits a connected stream into a `(send, recv)` pair, with [`DEFAULT_MAX_FRAME_SIZE`].
+///
+/// Terminating either half shuts down the whole stream.
+///
+/// [`DEFAULT_MAX_FRAME_SIZE`]: ../framed/constant.DEFAULT_MAX_FRAME_SIZE.html
+pub fn split(stream: TcpStream) -> io::Result<(TcpSend, TcpRecv)> {
+    split_with_max_frame_size(stream, framed::DEFAULT_MAX_FRAME_SIZE)
+}
+
+/// Splits a connected stream into a `(send, recv)` pair, which rejects a frame larger than `max_frame_size`
+/// with [`TransportError::FrameTooLarge`].
+///
+/// [`TransportError::FrameTooLarge`]: ../enum.TransportError.html#variant.FrameTooLarge
+pub fn split_with_max_frame_size(
+    stream: TcpStream,
+    max_frame_size: usize,
+) -> io::Result<(TcpSend, TcpRecv)> {
+    stream.set_nodelay(true)?;
+    let shutdown = stream.try_clone()?;
+    let (send, recv) = framed::new(stream.try_clone()?, stream, max_frame_size, move || {
+        if let Err(err) = shutdown.shutdown(Shutdown::Both) {
+            debug!("Failed to shutdown the socket: {}", err);
+        }
+    });
+    Ok((send.with_write_timeout(TcpStream::set_write_timeout), recv))
+}
+
+/// Connects to the given address and returns a `(send, recv)` pair.
+pub fn connect(addr: impl ToSocketAddrs) -> io::Result<(TcpSend, TcpRecv)> {
+    split(TcpStream::connect(addr)?)
+}
+
+/// Accepts a new connection from the listener and returns a `(send, recv)` pair.
+pub fn accept(listener: &TcpListener) -> io::Result<(TcpSend, TcpRecv)> {
+    let (stream, _) = listener.accept()?;
+    split(stream)
+}
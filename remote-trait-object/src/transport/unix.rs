@@ -0,0 +1,147 @@
+//! A transport over [`std::os::unix::net::UnixStream`], which is suitable to connect a host process
+//! with its child processes on the same machine.
+//!
+//! The host creates a connected pair with [`pair()`], makes the child's end inheritable with [`set_inheritable()`],
+//! and passes its file descriptor to the child on the command line or in an environment variable.
+//! Then the child builds its `(send, recv)` with [`from_fd_arg()`] or [`from_env()`].
+//! It is built on [`framed`], and a timeout of `send()` is set on the stream.
+//! A frame is limited to [`DEFAULT_MAX_FRAME_SIZE`], unless the stream is split with [`split_with_max_frame_size()`].
+//!
+//! ```ignore
+//! // In the host
+//! let (host_end, child_end) = UnixStream::pair()?;
+//! unix::set_inheritable(&child_end)?;
+//! let child = Command::new("module")
+//!     .env(unix::FD_ENV_VAR, child_end.as_raw_fd().to_string())
+//!     .spawn()?;
+//! drop(child_end);
+//! let (send, recv) = unix::split(host_end)?;
+//!
+//! // In the child
+//! let (send, recv) = unsafe { unix::from_env(unix::FD_ENV_VAR)? };
+//! ```
+//!
+//! [`std::os::unix::net::UnixStream`]: https://doc.rust-lang.org/std/os/unix/net/struct.UnixStream.html
+//! [`pair()`]: fn.pair.html
+//! [`set_inheritable()`]: fn.set_inheritable.html
+//! [`from_fd_arg()`]: fn.from_fd_arg.html
+//! [`from_env()`]: fn.from_env.html
+//! [`framed`]: ../framed/index.html
+//! [`DEFAULT_MAX_FRAME_SIZE`]: ../framed/constant.DEFAULT_MAX_FRAME_SIZE.html
+//! [`split_with_max_frame_size()`]: fn.split_with_max_frame_size.html
+
+use super::framed::{self, FramedRecv, FramedSend, FramedTerminator};
+use std::io;
+use std::net::Shutdown;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+
+/// The sending half of a Unix domain socket.
+pub type UnixSend = FramedSend<UnixStream>;
+/// The receiving half of a Unix domain socket.
+pub type UnixRecv = FramedRecv;
+/// A terminator of [`UnixSend`](type.UnixSend.html) or [`UnixRecv`](type.UnixRecv.html).
+pub type UnixTerminator = FramedTerminator;
+
+/// The default name of the environment variable that carries the file descriptor to a child process.
+pub const FD_ENV_VAR: &str = "REMOTE_TRAIT_OBJECT_FD";
+
+/// Splits a connected stream into a `(send, recv)` pair, with [`DEFAULT_MAX_FRAME_SIZE`].
+///
+/// Terminating either half shuts down the whole stream.
+///
+/// [`DEFAULT_MAX_FRAME_SIZE`]: ../framed/constant.DEFAULT_MAX_FRAME_SIZE.html
+pub fn split(stream: UnixStream) -> io::Result<(UnixSend, UnixRecv)> {
+    split_with_max_frame_size(stream, framed::DEFAULT_MAX_FRAME_SIZE)
+}
+
+/// Splits a connected stream into a `(send, recv)` pair, which rejects a frame larger than `max_frame_size`
+/// with [`TransportError::FrameTooLarge`].
+///
+/// [`TransportError::FrameTooLarge`]: ../enum.TransportError.html#variant.FrameTooLarge
+pub fn split_with_max_frame_size(
+    stream: UnixStream,
+    max_frame_size: usize,
+) -> io::Result<(UnixSend, UnixRecv)> {
+    let shutdown = stream.try_clone()?;
+    let (send, recv) = framed::new(stream.try_clone()?, stream, max_frame_size, move || {
+        if let Err(err) = shutdown.shutdown(Shutdown::Both) {
+            debug!("Failed to shutdown the socket: {}", err);
+        }
+    });
+    Ok((send.with_write_timeout(UnixStream::set_write_timeout), recv))
+}
+
+/// Connects to the socket at the given path and returns a `(send, recv)` pair.
+pub fn connect(path: impl AsRef<Path>) -> io::Result<(UnixSend, UnixRecv)> {
+    split(UnixStream::connect(path)?)
+}
+
+/// Accepts a new connection from the listener and returns a `(send, recv)` pair.
+pub fn accept(listener: &UnixListener) -> io::Result<(UnixSend, UnixRecv)> {
+    let (stream, _) = listener.accept()?;
+    split(stream)
+}
+
+/// Creates two connected ends with `socketpair()`.
+#[allow(clippy::type_complexity)]
+pub fn pair() -> io::Result<((UnixSend, UnixRecv), (UnixSend, UnixRecv))> {
+    let (a, b) = UnixStream::pair()?;
+    Ok((split(a)?, split(b)?))
+}
+
+/// Makes the socket be inherited by child processes, which is not the case by default.
+pub fn set_inheritable(stream: &UnixStream) -> io::Result<()> {
+    let fd = stream.as_raw_fd();
+    // Safety: fcntl() on a valid file descriptor only changes its flags.
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFD);
+        if flags < 0 || libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Creates a `(send, recv)` pair from a file descriptor of a connected Unix domain socket.
+///
+/// # Safety
+/// `fd` must be an open socket that is not owned by anything else, since it will be closed when the pair is dropped.
+pub unsafe fn from_raw_fd(fd: RawFd) -> io::Result<(UnixSend, UnixRecv)> {
+    split(UnixStream::from_raw_fd(fd))
+}
+
+/// Creates a `(send, recv)` pair from a file descriptor given as a string, like a command line argument.
+///
+/// # Safety
+/// See [`from_raw_fd()`](fn.from_raw_fd.html).
+pub unsafe fn from_fd_arg(arg: &str) -> io::Result<(UnixSend, UnixRecv)> {
+    let fd: RawFd = arg.trim().parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid file descriptor: {:?}", arg),
+        )
+    })?;
+    if fd < 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid file descriptor: {}", fd),
+        ));
+    }
+    from_raw_fd(fd)
+}
+
+/// Creates a `(send, recv)` pair from a file descriptor in the given environment variable.
+///
+/// # Safety
+/// See [`from_raw_fd()`](fn.from_raw_fd.html).
+pub unsafe fn from_env(name: &str) -> io::Result<(UnixSend, UnixRecv)> {
+    let arg = std::env::var(name).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Failed to read {}: {}", name, err),
+        )
+    })?;
+    from_fd_arg(&arg)
+}